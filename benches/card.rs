@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use psxmem::{CardGenerator, MemCard, SaveSpec};
+
+fn card() -> Vec<u8> {
+    CardGenerator::new()
+        .save(SaveSpec::new("WILD ARMS", 1))
+        .save(SaveSpec::new("FINAL FANTASY VII", 3))
+        .save(SaveSpec::new("GRAN TURISMO", 2))
+        .generate()
+        .unwrap()
+}
+
+fn parse(c: &mut Criterion) {
+    let raw = card();
+    c.bench_function("MemCard::from_reader", |b| {
+        b.iter(|| MemCard::from_reader(black_box(&raw[..])).unwrap())
+    });
+}
+
+fn write(c: &mut Criterion) {
+    let m = MemCard::from_reader(&card()[..]).unwrap();
+    let mut buf = Vec::<u8>::new();
+    c.bench_function("MemCard::write_into", |b| {
+        b.iter(|| {
+            buf.clear();
+            black_box(&m).write_into(&mut buf).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, parse, write);
+criterion_main!(benches);
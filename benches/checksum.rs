@@ -0,0 +1,24 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use psxmem::{calc_checksum, validate_block_checksums, CardGenerator, SaveSpec, BLOCK_SIZE};
+
+fn card() -> Vec<u8> {
+    CardGenerator::new()
+        .save(SaveSpec::new("CHECKSUM BENCH", 3))
+        .generate()
+        .unwrap()
+}
+
+fn checksum(c: &mut Criterion) {
+    let card = card();
+    let info = &card[..BLOCK_SIZE];
+
+    c.bench_function("calc_checksum frame", |b| {
+        b.iter(|| calc_checksum(black_box(&info[0x80..0x100])))
+    });
+    c.bench_function("validate_block_checksums info block", |b| {
+        b.iter(|| validate_block_checksums(black_box(info)))
+    });
+}
+
+criterion_group!(benches, checksum);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+#![no_main]
+
+use deku::prelude::*;
+use libfuzzer_sys::fuzz_target;
+use psxmem::{DirectoryFrame, Header, TitleFrame};
+
+fuzz_target!(|input: (Header, DirectoryFrame, TitleFrame)| {
+    let (header, dir, title) = input;
+
+    let bytes = header.to_bytes().unwrap();
+    let (_, h) = Header::from_bytes((&bytes, 0)).unwrap();
+    assert_eq!(header, h);
+
+    let bytes = dir.to_bytes().unwrap();
+    let (_, d) = DirectoryFrame::from_bytes((&bytes, 0)).unwrap();
+    assert_eq!(dir, d);
+    let _ = format!("{}", d);
+
+    let bytes = title.to_bytes().unwrap();
+    let (_, t) = TitleFrame::from_bytes((&bytes, 0)).unwrap();
+    assert_eq!(title, t);
+    let _ = format!("{}", t);
+});
@@ -0,0 +1,42 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use psxmem::{Block, DataBlock, InfoBlock, BLOCKS_PER_CARD, BLOCK_SIZE};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < BLOCK_SIZE * BLOCKS_PER_CARD {
+        return;
+    }
+
+    let mut blocks = Vec::<Block>::new();
+    for chunk in data.chunks_exact(BLOCK_SIZE).take(BLOCKS_PER_CARD) {
+        let mut b = Block {
+            data: [0u8; BLOCK_SIZE],
+        };
+        b.data.copy_from_slice(chunk);
+        blocks.push(b);
+    }
+
+    let info = match InfoBlock::open(&blocks[0]) {
+        Ok(i) => i,
+        Err(_) => return,
+    };
+    let _ = info.validate_chains();
+    let _ = info.validate_unused_frames();
+
+    if let Ok(data) = DataBlock::load_all_data_blocks(&blocks[1..]) {
+        for d in &data {
+            let _ = d.title_frame.decode_title();
+            let _ = format!("{}", d.title_frame);
+        }
+    }
+
+    // Anything that parsed must write back out and parse again
+    let mut out = Vec::<u8>::new();
+    info.write(&mut out).unwrap();
+    let mut b = Block {
+        data: [0u8; BLOCK_SIZE],
+    };
+    b.data.copy_from_slice(&out);
+    InfoBlock::open(&b).unwrap();
+});
@@ -0,0 +1,126 @@
+#ifndef PSXMEM_H
+#define PSXMEM_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// PsxStatus
+//
+// The result of a C API call. The error values mirror `ErrorKind`.
+typedef enum PsxStatus {
+  PSX_STATUS_OK = 0,
+  PSX_STATUS_IO = -1,
+  PSX_STATUS_CORRUPT = -2,
+  PSX_STATUS_UNSUPPORTED = -3,
+  PSX_STATUS_FULL = -4,
+  PSX_STATUS_NOT_FOUND = -5,
+  PSX_STATUS_INVALID_INPUT = -6,
+} PsxStatus;
+
+// PsxCard
+//
+// An opaque handle to a memory card, created by `psxmem_open` or `psxmem_open_bytes` and
+// released with `psxmem_free`.
+typedef struct PsxCard PsxCard;
+
+// PsxSaveInfo
+//
+// The details of a save, filled in by `psxmem_save_info`. Strings are NUL terminated UTF-8, and
+// truncated if they do not fit.
+typedef struct PsxSaveInfo {
+  // The 1-based slot of the first block of the save.
+  size_t slot;
+  // The number of blocks in the save.
+  size_t blocks;
+  // The size of the save in bytes, as recorded in the directory.
+  uint32_t size;
+  // The number of icon frames.
+  size_t icon_frames;
+  // The decoded title, or an empty string if the save has no title frame.
+  char title[128];
+  // The product code of the game, such as "SLUS-00123".
+  char serial[16];
+  // The directory filename of the save.
+  char filename[24];
+} PsxSaveInfo;
+
+// Get the message of the last error on this thread, or null if there was none. The string is
+// valid until the next failing call on this thread.
+const char *psxmem_last_error(void);
+
+// Open the raw card image at `path`. Returns null on failure.
+//
+// # Safety
+//
+// `path` must be null or a valid NUL terminated string.
+struct PsxCard *psxmem_open(const char *path);
+
+// Open a card from the `len` bytes of a raw image at `data`. Returns null on failure.
+//
+// # Safety
+//
+// `data` must point to at least `len` readable bytes.
+struct PsxCard *psxmem_open_bytes(const uint8_t *data, size_t len);
+
+// Release a card. Passing null does nothing.
+//
+// # Safety
+//
+// `card` must be null or a handle returned by `psxmem_open` or `psxmem_open_bytes` that has not
+// been released yet.
+void psxmem_free(struct PsxCard *card);
+
+// Get the number of live saves on the card.
+//
+// # Safety
+//
+// `card` must be a valid handle.
+size_t psxmem_save_count(const struct PsxCard *card);
+
+// Fill in `out` with the details of the `n`th live save on the card.
+//
+// # Safety
+//
+// `card` must be a valid handle, and `out` must point to a writable `PsxSaveInfo`.
+enum PsxStatus psxmem_save_info(const struct PsxCard *card, size_t n, struct PsxSaveInfo *out);
+
+// Export the save in the 1-based `slot` as a `.mcs` file. On success `*out` and `*len` are set
+// to a buffer that must be released with `psxmem_bytes_free`.
+//
+// # Safety
+//
+// `card` must be a valid handle, and `out` and `len` must be writable.
+enum PsxStatus psxmem_export_save(const struct PsxCard *card,
+                                  size_t slot,
+                                  uint8_t **out,
+                                  size_t *len);
+
+// Release a buffer returned by `psxmem_export_save`. Passing null does nothing.
+//
+// # Safety
+//
+// `data` and `len` must be exactly as returned by `psxmem_export_save`, and not released yet.
+void psxmem_bytes_free(uint8_t *data, size_t len);
+
+// Import the `len` bytes of a `.mcs` file at `mcs` into free blocks of the card. On success, if
+// `slot` is not null it is set to the 1-based slot of the first block of the save.
+//
+// # Safety
+//
+// `card` must be a valid handle, `mcs` must point to at least `len` readable bytes, and `slot`
+// must be null or writable.
+enum PsxStatus psxmem_import_save(struct PsxCard *card,
+                                  const uint8_t *mcs,
+                                  size_t len,
+                                  size_t *slot);
+
+// Write the card out as a raw image to `path`.
+//
+// # Safety
+//
+// `card` must be a valid handle, and `path` must be null or a valid NUL terminated string.
+enum PsxStatus psxmem_write(const struct PsxCard *card, const char *path);
+
+#endif  /* PSXMEM_H */
@@ -0,0 +1,136 @@
+//! Reading and writing memory cards with `tokio`, for applications that handle many card files
+//! at once without blocking the runtime.
+
+use std::path::Path;
+
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::atomic::temp_path;
+use crate::backup::{backup_dir, backup_path, is_backup, now_ms};
+use crate::{CardGeometry, MCError, MemCard, OpenOptions, WriteOptions};
+
+impl MemCard {
+    /// Open and parse the memory card file from a filename without blocking. See `open`.
+    pub async fn open_async<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        Self::open_with_async(filename, OpenOptions::default()).await
+    }
+
+    /// Open and parse the memory card file from a filename using the provided `OpenOptions`,
+    /// without blocking. See `open_with`.
+    pub async fn open_with_async<P: AsRef<Path>>(
+        filename: P,
+        opts: OpenOptions,
+    ) -> Result<Self, MCError> {
+        let path = filename.as_ref();
+        let read = async {
+            let file = File::open(path).await?;
+            Self::from_async_reader_with(file, opts).await
+        };
+        read.await
+            .map_err(|e| e.context(format!("Unable to open {}", path.display())))
+    }
+
+    /// Parse a memory card from anything that implements `AsyncRead`, such as a socket.
+    pub async fn from_async_reader<R: AsyncRead + Unpin>(input: R) -> Result<Self, MCError> {
+        Self::from_async_reader_with(input, OpenOptions::default()).await
+    }
+
+    /// Parse a memory card from anything that implements `AsyncRead` using the provided
+    /// `OpenOptions`. The image is read into memory first, and then parsed in one go.
+    pub async fn from_async_reader_with<R: AsyncRead + Unpin>(
+        mut input: R,
+        opts: OpenOptions,
+    ) -> Result<Self, MCError> {
+        let mut image = vec![0u8; CardGeometry::STANDARD.size()];
+        input.read_exact(&mut image).await?;
+
+        Self::from_reader_with(&image[..], opts)
+    }
+
+    /// Write out the `MemCard` data to a file without blocking, replacing it atomically. See
+    /// `write`.
+    pub async fn write_async<P: AsRef<Path>>(&self, filename: P) -> Result<(), MCError> {
+        self.write_with_async(filename, WriteOptions::default())
+            .await
+    }
+
+    /// Write out the `MemCard` data to a file without blocking using the provided
+    /// `WriteOptions`. See `write_with`.
+    pub async fn write_with_async<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        opts: WriteOptions,
+    ) -> Result<(), MCError> {
+        self.check_writable()?;
+        let path = filename.as_ref();
+        let write = async {
+            let path = fs::canonicalize(path)
+                .await
+                .unwrap_or_else(|_| path.to_path_buf());
+            let tmp = temp_path(&path);
+            rotate_async(&path, opts.backups).await?;
+
+            let replace = async {
+                let mut file = File::create(&tmp).await?;
+                if let Ok(meta) = fs::metadata(&path).await {
+                    file.set_permissions(meta.permissions()).await?;
+                }
+                self.write_to_async(&mut file).await?;
+                file.flush().await?;
+                if opts.sync {
+                    file.sync_all().await?;
+                }
+                drop(file);
+                fs::rename(&tmp, &path).await?;
+                Ok::<(), MCError>(())
+            };
+            if let Err(e) = replace.await {
+                let _ = fs::remove_file(&tmp).await;
+                return Err(e);
+            }
+            Ok(())
+        };
+        write
+            .await
+            .map_err(|e| e.context(format!("Unable to write {}", path.display())))
+    }
+
+    /// Write out the `MemCard` data to anything that implements `AsyncWrite`. The image is
+    /// serialized into memory first, and then written in one go.
+    pub async fn write_to_async<T: AsyncWrite + Unpin>(&self, out: &mut T) -> Result<(), MCError> {
+        let mut buf = Vec::<u8>::with_capacity(CardGeometry::STANDARD.size());
+        self.write_into(&mut buf)?;
+        out.write_all(&buf).await?;
+
+        Ok(())
+    }
+}
+
+/// Make a backup of the file at `path` without blocking. See `backup::rotate`.
+async fn rotate_async(path: &Path, keep: usize) -> Result<(), MCError> {
+    if keep == 0 || !fs::metadata(path).await.is_ok_and(|m| m.is_file()) {
+        return Ok(());
+    }
+
+    let mut ms = now_ms();
+    while fs::try_exists(backup_path(path, ms)).await? {
+        ms += 1;
+    }
+    fs::copy(path, backup_path(path, ms)).await?;
+
+    let mut backups = Vec::new();
+    let mut dir = fs::read_dir(backup_dir(path)).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        if is_backup(path, &entry.file_name()) {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    let expired = backups.len().saturating_sub(keep);
+    for old in &backups[..expired] {
+        fs::remove_file(old).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Replacing files atomically, so that a crash in the middle of a write, or an emulator reading
+//! the card at the same time, never sees a partially written image.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Write `data` to `path` by writing it to a temporary file in the same directory, and renaming
+/// that over `path`. The permissions of an existing file are kept, and symbolic links are
+/// followed. With `sync`, the file and the directory are flushed to disk before returning.
+pub(crate) fn write_atomic(path: &Path, data: &[u8], sync: bool) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let tmp = temp_path(&path);
+
+    let write = || -> io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        if let Ok(meta) = fs::metadata(&path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.write_all(data)?;
+        if sync {
+            file.sync_all()?;
+        }
+        drop(file);
+
+        fs::rename(&tmp, &path)?;
+        if sync {
+            sync_dir(&path)?;
+        }
+
+        Ok(())
+    };
+
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// The temporary file to write `path` to, a hidden file next to it.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+    path.with_file_name(name)
+}
+
+/// Flush the directory holding `path`, so that a rename into it is durable.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories can not be opened as files on other platforms, where the rename is flushed along
+/// with the file.
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
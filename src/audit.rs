@@ -0,0 +1,158 @@
+//! A log of the changes made to a memory card, for accountability in shared archives.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backup::utc;
+use crate::{MCError, WipePattern};
+
+/// AuditAction
+///
+/// A change made to a memory card through one of the methods of `MemCard`. Saves are
+/// identified by the directory index of their first block, and by their filename.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    /// A save was imported by `import_mcs`.
+    ImportSave { index: usize, filename: String },
+
+    /// A save was deleted by `delete_save`.
+    DeleteSave { index: usize, filename: String },
+
+    /// A save was renamed by `rename_save`.
+    RenameSave {
+        index: usize,
+        old: String,
+        new: String,
+    },
+
+    /// The card was formatted by `format`.
+    Format,
+
+    /// The card was overwritten by `wipe`.
+    Wipe(WipePattern),
+
+    /// The card was normalized by `canonicalize`.
+    Canonicalize,
+
+    /// The directory was repaired by `repair`, with this many changes.
+    Repair { changes: usize },
+
+    /// This many blocks were moved off of broken frames by `relocate_broken`.
+    RelocateBroken { blocks: usize },
+
+    /// This many saves were given new directory entries by `rebuild_directory`.
+    RebuildDirectory { saves: usize },
+
+    /// `len` bytes of the payload of a save were overwritten at `offset` by `patch_save`.
+    PatchSave {
+        index: usize,
+        filename: String,
+        offset: usize,
+        len: usize,
+    },
+}
+
+/// AuditEntry
+///
+/// An `AuditAction` and the time it was made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub time: SystemTime,
+    pub action: AuditAction,
+}
+
+/// The audit log of a card, and how many of its entries have been appended to a file.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditLog {
+    pub(crate) entries: Vec<AuditEntry>,
+    written: usize,
+}
+
+impl AuditLog {
+    /// Record `action`, made now.
+    pub(crate) fn record(&mut self, action: AuditAction) {
+        self.entries.push(AuditEntry {
+            time: SystemTime::now(),
+            action,
+        });
+    }
+
+    /// Append the entries that were recorded since the last call to the file at `path`, one
+    /// per line.
+    pub(crate) fn append_to(&mut self, path: &Path) -> Result<(), MCError> {
+        let mut out = String::new();
+        for e in &self.entries[self.written..] {
+            out.push_str(&format!("{}\n", e));
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(out.as_bytes())?;
+        self.written = self.entries.len();
+
+        Ok(())
+    }
+}
+
+/// The audit log file of the card file at `card`, such as "card.mcr.log".
+pub(crate) fn log_path(card: &Path) -> PathBuf {
+    let mut name = card.file_name().unwrap_or_default().to_os_string();
+    name.push(".log");
+    card.with_file_name(name)
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditAction::ImportSave { index, filename } => {
+                write!(f, "Imported {} into block {}", filename, index)
+            }
+            AuditAction::DeleteSave { index, filename } => {
+                write!(f, "Deleted {} from block {}", filename, index)
+            }
+            AuditAction::RenameSave { index, old, new } => {
+                write!(f, "Renamed {} in block {} to {}", old, index, new)
+            }
+            AuditAction::Format => write!(f, "Formatted the card"),
+            AuditAction::Wipe(pattern) => write!(f, "Wiped the card with {:?}", pattern),
+            AuditAction::Canonicalize => write!(f, "Canonicalized the card"),
+            AuditAction::Repair { changes } => {
+                write!(f, "Repaired the directory with {} changes", changes)
+            }
+            AuditAction::RelocateBroken { blocks } => {
+                write!(f, "Moved {} blocks off of broken frames", blocks)
+            }
+            AuditAction::RebuildDirectory { saves } => {
+                write!(f, "Rebuilt the directory entries of {} saves", saves)
+            }
+            AuditAction::PatchSave {
+                index,
+                filename,
+                offset,
+                len,
+            } => write!(
+                f,
+                "Patched {} bytes at offset {} of {} in block {}",
+                len, offset, filename, index
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let [year, month, day, hour, min, sec, ms] = utc(ms);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z {}",
+            year, month, day, hour, min, sec, ms, self.action
+        )
+    }
+}
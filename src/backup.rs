@@ -0,0 +1,132 @@
+//! Versioned backups of card files, made before they are overwritten.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Copy the file at `path`, if there is one, to a new backup next to it, and remove all but the
+/// `keep` newest backups. Backups are named after the file and the UTC time they were made, such
+/// as "card.mcr.20240131-235959.123.bak", so they sort from oldest to newest.
+pub(crate) fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.is_file() {
+        return Ok(());
+    }
+
+    // Writes within the same millisecond get the next free name, keeping the order
+    let backup = (now_ms()..)
+        .map(|ms| backup_path(path, ms))
+        .find(|p| !p.exists())
+        .expect("a backup name is free");
+    fs::copy(path, &backup)?;
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backup_dir(path))? {
+        let entry = entry?;
+        if is_backup(path, &entry.file_name()) {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    let expired = backups.len().saturating_sub(keep);
+    for old in &backups[..expired] {
+        fs::remove_file(old)?;
+    }
+
+    Ok(())
+}
+
+/// The current time in milliseconds since the Unix epoch.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// The directory that holds the backups of `path`.
+pub(crate) fn backup_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    }
+}
+
+/// Check if `file` is the name of a backup of `path`.
+pub(crate) fn is_backup(path: &Path, file: &OsStr) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let file = file.to_string_lossy();
+    file.strip_prefix(name.as_ref())
+        .and_then(|f| f.strip_prefix('.'))
+        .and_then(|f| f.strip_suffix(".bak"))
+        .is_some_and(is_stamp)
+}
+
+/// The backup of `path` made at `ms` milliseconds since the Unix epoch.
+pub(crate) fn backup_path(path: &Path, ms: u64) -> PathBuf {
+    let mut name = OsString::from(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.bak", stamp(ms)));
+    path.with_file_name(name)
+}
+
+/// Format `ms` milliseconds since the Unix epoch as "YYYYMMDD-HHMMSS.mmm" in UTC.
+fn stamp(ms: u64) -> String {
+    let [year, month, day, hour, min, sec, ms] = utc(ms);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}.{:03}",
+        year, month, day, hour, min, sec, ms
+    )
+}
+
+/// Split `ms` milliseconds since the Unix epoch into the UTC year, month, day, hour, minute,
+/// second and millisecond.
+pub(crate) fn utc(ms: u64) -> [u64; 7] {
+    let secs = ms / 1000;
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Convert days since the epoch to a civil date, after Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let (era, doe) = (z / 146097, z % 146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    [
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        ms % 1000,
+    ]
+}
+
+/// Check that `s` has the form of a timestamp made by `stamp`.
+fn is_stamp(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 19
+        && b.iter().enumerate().all(|(n, c)| match n {
+            8 => *c == b'-',
+            15 => *c == b'.',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps() {
+        assert_eq!(stamp(0), "19700101-000000.000");
+        assert_eq!(stamp(951_782_400_000), "20000229-000000.000");
+        assert_eq!(stamp(1_706_745_599_123), "20240131-235959.123");
+        assert!(is_stamp(&stamp(1_706_745_599_123)));
+        assert!(!is_stamp("20240131-235959"));
+    }
+}
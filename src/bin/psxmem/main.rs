@@ -0,0 +1,420 @@
+//! Command line memory card manager built on the `psxmem` library.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use psxmem::{MCError, MemCard, OpenOptions, RepairOptions, SourceLang, WipePattern};
+
+#[cfg(feature = "tui")]
+mod tui;
+
+#[derive(Parser)]
+#[command(version, about = "Manage PSX/PS1 memory card images")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the saves on a card
+    Ls { card: PathBuf },
+
+    /// Print the saves on a card as a Markdown table
+    Markdown {
+        card: PathBuf,
+
+        /// Write a thumbnail of every save to this directory and link them from the table
+        #[arg(short, long)]
+        icons: Option<PathBuf>,
+    },
+
+    /// Show the directory, broken frames and usage of a card
+    Info { card: PathBuf },
+
+    /// Export the save in a slot to a .mcs file
+    Export {
+        card: PathBuf,
+        slot: usize,
+        output: PathBuf,
+    },
+
+    /// Import a .mcs file into the free blocks of a card
+    Import {
+        card: PathBuf,
+        input: PathBuf,
+
+        /// Write the result here instead of overwriting the card
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Delete the save in a slot
+    Rm {
+        card: PathBuf,
+        slot: usize,
+
+        /// Write the result here instead of overwriting the card
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Export the icon of the save in a slot as PNG (and GIF if animated) files
+    Icons {
+        card: PathBuf,
+        slot: usize,
+
+        /// The directory to write the images to
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+
+        /// Write the raw 4bpp pixels and CLUT instead, as <filename>.4bpp and <filename>.clut
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// Replace the icon of the save in a slot with raw 4bpp pixels and a 32 byte CLUT
+    SetIcon {
+        card: PathBuf,
+        slot: usize,
+        pixels: PathBuf,
+        clut: PathBuf,
+
+        /// Write the result here instead of overwriting the card
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the icon of the save in a slot as a C header or Rust source, for homebrew
+    IconSource {
+        card: PathBuf,
+        slot: usize,
+
+        #[arg(short, long, value_enum, default_value_t = Lang::C)]
+        lang: Lang,
+
+        /// The name of the arrays, the directory filename of the save by default
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+
+    /// Convert a card image to another format, chosen by the output file extension
+    Convert { input: PathBuf, output: PathBuf },
+
+    /// List the saves that were added, removed or modified between two cards
+    Diff { old: PathBuf, new: PathBuf },
+
+    /// Erase every save and overwrite the whole card, before giving it away
+    Wipe {
+        card: PathBuf,
+
+        /// What to overwrite the data blocks with
+        #[arg(short, long, value_enum, default_value_t = Pattern::Zeros)]
+        pattern: Pattern,
+    },
+
+    /// Search the saves on a card for a byte pattern, such as "de ad ?? ef"
+    Grep { card: PathBuf, pattern: String },
+
+    /// Check a card for checksum, chain and title problems
+    Fsck {
+        card: PathBuf,
+
+        /// Print the findings as a JSON report
+        #[arg(long)]
+        json: bool,
+
+        /// Repair the directory, and write the card back if anything was changed
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Browse one or two cards in a terminal UI, and copy, delete or export saves
+    #[cfg(feature = "tui")]
+    Tui {
+        card: PathBuf,
+        other: Option<PathBuf>,
+    },
+
+    /// Mount a card as a directory of save files until it is unmounted
+    #[cfg(feature = "fuse")]
+    Mount { card: PathBuf, mountpoint: PathBuf },
+
+    /// Dump a physical card to an image over a serial cable to a console running Unirom
+    #[cfg(feature = "unirom")]
+    Dump {
+        /// The serial port, such as /dev/ttyUSB0 or COM3
+        port: String,
+        output: PathBuf,
+
+        /// The memory card port of the console, 0 or 1
+        #[arg(short, long, default_value_t = 0)]
+        slot: u32,
+    },
+
+    /// Restore an image to a physical card over a serial cable to a console running Unirom
+    #[cfg(feature = "unirom")]
+    Restore {
+        /// The serial port, such as /dev/ttyUSB0 or COM3
+        port: String,
+        card: PathBuf,
+
+        /// The memory card port of the console, 0 or 1
+        #[arg(short, long, default_value_t = 0)]
+        slot: u32,
+    },
+}
+
+/// The fill of the `wipe` command, see `WipePattern`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Pattern {
+    Zeros,
+    Ones,
+    Random,
+}
+
+impl From<Pattern> for WipePattern {
+    fn from(p: Pattern) -> Self {
+        match p {
+            Pattern::Zeros => WipePattern::Zeros,
+            Pattern::Ones => WipePattern::Ones,
+            Pattern::Random => WipePattern::Random,
+        }
+    }
+}
+
+/// The language of the `icon-source` command, see `SourceLang`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Lang {
+    C,
+    Rust,
+}
+
+impl From<Lang> for SourceLang {
+    fn from(l: Lang) -> Self {
+        match l {
+            Lang::C => SourceLang::C,
+            Lang::Rust => SourceLang::Rust,
+        }
+    }
+}
+
+/// The raw image extensions that are read and written as-is, including the ".VM1" images of
+/// PS3 internal storage.
+const RAW_EXTENSIONS: [&str; 6] = ["mcr", "mcd", "mc", "srm", "bin", "vm1"];
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("psxmem: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, MCError> {
+    match command {
+        Command::Ls { card } => println!("{}", MemCard::open(card)?),
+        Command::Markdown { card, icons } => {
+            let m = MemCard::open(card)?;
+            if let Some(dir) = &icons {
+                fs::create_dir_all(dir)?;
+                m.write_thumbnails(dir)?;
+            }
+            let icons = icons.as_ref().map(|d| d.to_string_lossy());
+            m.export_markdown(&mut std::io::stdout().lock(), icons.as_deref())?;
+        }
+        Command::Info { card } => {
+            let m = MemCard::open(card)?;
+            println!("{}", m.info);
+
+            let s = m.summary();
+            println!(
+                "\n Saves: {}\n Used blocks: {}\n Free blocks: {}\n Save bytes: {}",
+                s.saves, s.used_blocks, s.free_blocks, s.payload_bytes
+            );
+        }
+        Command::Export { card, slot, output } => {
+            let m = MemCard::open(card)?;
+            let index = slot_index(slot)?;
+            let save = m
+                .saves()
+                .find(|s| s.index == index)
+                .ok_or_else(|| MCError::NotFound(format!("no save in slot {}", slot)))?;
+            fs::write(output, save.to_mcs()?)?;
+        }
+        Command::Import {
+            card,
+            input,
+            output,
+        } => {
+            let mut m = MemCard::open(&card)?;
+            let index = m.import_mcs(&fs::read(input)?)?;
+            m.write(output.as_ref().unwrap_or(&card))?;
+            println!("Imported into slot {}", index + 1);
+        }
+        Command::Rm { card, slot, output } => {
+            let mut m = MemCard::open(&card)?;
+            m.delete_save(slot_index(slot)?)?;
+            m.write(output.as_ref().unwrap_or(&card))?;
+        }
+        Command::Icons {
+            card,
+            slot,
+            dir,
+            raw,
+        } => {
+            let m = MemCard::open(card)?;
+            let index = slot_index(slot)?;
+            if raw {
+                let name = m.info.dir_frames[index].get_filename();
+                fs::write(dir.join(format!("{}.4bpp", name)), m[index].icon_pixels())?;
+                fs::write(dir.join(format!("{}.clut", name)), m[index].icon_clut())?;
+            } else {
+                std::env::set_current_dir(dir)?;
+                m[index].export_all_images()?;
+            }
+        }
+        Command::SetIcon {
+            card,
+            slot,
+            pixels,
+            clut,
+            output,
+        } => {
+            let mut m = MemCard::open(&card)?;
+            m.set_icon_raw(slot_index(slot)?, &fs::read(pixels)?, &fs::read(clut)?)?;
+            m.write(output.as_ref().unwrap_or(&card))?;
+        }
+        Command::IconSource {
+            card,
+            slot,
+            lang,
+            name,
+        } => {
+            let m = MemCard::open(card)?;
+            let index = slot_index(slot)?;
+            let name = name.unwrap_or_else(|| m.info.dir_frames[index].get_filename());
+            m[index].write_icon_source(lang.into(), &name, &mut std::io::stdout().lock())?;
+        }
+        Command::Convert { input, output } => {
+            check_format(&input)?;
+            check_format(&output)?;
+            MemCard::open(input)?.write(output)?;
+        }
+        Command::Diff { old, new } => {
+            let d = MemCard::open(old)?.diff(&MemCard::open(new)?)?;
+            print!("{}", d);
+            if !d.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Wipe { card, pattern } => {
+            let mut m = MemCard::open_with(
+                &card,
+                OpenOptions {
+                    skip_checksums: true,
+                    ..Default::default()
+                },
+            )?;
+            m.wipe(pattern.into())?;
+            m.write(&card)?;
+        }
+        Command::Grep { card, pattern } => {
+            let found = MemCard::open(card)?.grep(&pattern.parse()?)?;
+            for m in &found {
+                println!("{}", m);
+            }
+            if found.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Fsck { card, json, repair } => {
+            let opts = OpenOptions {
+                skip_checksums: true,
+                ..Default::default()
+            };
+            let mut m = MemCard::open_with(&card, opts)?;
+
+            if repair {
+                let log = m.repair(RepairOptions::default())?;
+                for action in &log {
+                    eprintln!("{}", action);
+                }
+                if !log.is_empty() {
+                    m.write(&card)?;
+                }
+            }
+
+            let report = m.validate();
+            if json {
+                let out = serde_json::to_string_pretty(&report)
+                    .map_err(|e| MCError::InvalidValue(e.to_string()))?;
+                println!("{}", out);
+            } else {
+                for f in &report.findings {
+                    println!("{:?} {}: {:?}", f.severity(), f.code(), f);
+                }
+                if report.is_clean() {
+                    println!("No problems found");
+                }
+            }
+
+            if !report.is_clean() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui { card, other } => tui::run(card, other)?,
+        #[cfg(feature = "fuse")]
+        Command::Mount { card, mountpoint } => psxmem::CardFs::open(card)?.mount(mountpoint)?,
+        #[cfg(feature = "unirom")]
+        Command::Dump { port, output, slot } => {
+            let opts = OpenOptions {
+                skip_checksums: true,
+                ..Default::default()
+            };
+            psxmem::Unirom::open(&port)?
+                .read_card_with(slot, opts)?
+                .write(output)?;
+        }
+        #[cfg(feature = "unirom")]
+        Command::Restore { port, card, slot } => {
+            psxmem::Unirom::open(&port)?.write_card(slot, &MemCard::open(card)?)?
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Convert a 1-based slot number, as shown by `ls`, to a directory index.
+fn slot_index(slot: usize) -> Result<usize, MCError> {
+    match slot {
+        1..=15 => Ok(slot - 1),
+        _ => Err(MCError::InvalidValue(format!(
+            "slot {} is not 1 to 15",
+            slot
+        ))),
+    }
+}
+
+/// Check that a card image path has an extension of a supported format.
+fn check_format(path: &Path) -> Result<(), MCError> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !RAW_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(MCError::InvalidValue(format!(
+            "{} is not a supported card image format",
+            path.display()
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! Terminal UI for browsing memory cards side by side.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use psxmem::{MCError, MemCard, Save};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+/// How long each icon frame is shown for when animating.
+const ICON_FRAME_TIME: Duration = Duration::from_millis(300);
+
+/// A card open in one of the panes.
+struct Pane {
+    path: PathBuf,
+    card: MemCard,
+    state: ListState,
+    modified: bool,
+}
+
+impl Pane {
+    fn open(path: PathBuf) -> Result<Self, MCError> {
+        let card = MemCard::open(&path)?;
+        let mut state = ListState::default();
+        state.select_first();
+
+        Ok(Pane {
+            path,
+            card,
+            state,
+            modified: false,
+        })
+    }
+
+    /// The save that is selected in the list, if any.
+    fn selected(&self) -> Option<Save<'_>> {
+        self.card.saves().nth(self.state.selected()?)
+    }
+
+    /// Keep the selection within the list after saves were added or removed.
+    fn clamp(&mut self) {
+        let count = self.card.saves().count();
+        match self.state.selected() {
+            _ if count == 0 => self.state.select(None),
+            Some(n) if n >= count => self.state.select(Some(count - 1)),
+            None => self.state.select_first(),
+            _ => (),
+        }
+    }
+}
+
+struct App {
+    panes: Vec<Pane>,
+    focus: usize,
+    status: String,
+    start: Instant,
+}
+
+/// Run the browser on `card`, and optionally `other` in a second pane.
+pub fn run(card: PathBuf, other: Option<PathBuf>) -> Result<(), MCError> {
+    let mut panes = vec![Pane::open(card)?];
+    if let Some(o) = other {
+        panes.push(Pane::open(o)?);
+    }
+
+    let mut app = App {
+        panes,
+        focus: 0,
+        status: "Tab: switch  c: copy  d: delete  e: export  w: write  q: quit".to_string(),
+        start: Instant::now(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+impl App {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), MCError> {
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+
+            if !event::poll(ICON_FRAME_TIME)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let pane = &mut self.panes[self.focus];
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab => self.focus = (self.focus + 1) % self.panes.len(),
+                KeyCode::Up | KeyCode::Char('k') => pane.state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    pane.state.select_next();
+                    pane.clamp();
+                }
+                KeyCode::Char('c') => self.status = self.copy(),
+                KeyCode::Char('d') => self.status = self.delete(),
+                KeyCode::Char('e') => self.status = self.export(),
+                KeyCode::Char('w') => self.status = self.write(),
+                _ => (),
+            }
+        }
+    }
+
+    /// Copy the selected save into the other pane.
+    fn copy(&mut self) -> String {
+        if self.panes.len() < 2 {
+            return "Open a second card to copy saves to".to_string();
+        }
+
+        let mcs = match self.panes[self.focus].selected().map(|s| s.to_mcs()) {
+            Some(Ok(mcs)) => mcs,
+            Some(Err(e)) => return format!("Copy failed: {}", e),
+            None => return "No save selected".to_string(),
+        };
+
+        let target = &mut self.panes[1 - self.focus];
+        match target.card.import_mcs(&mcs) {
+            Ok(n) => {
+                target.modified = true;
+                target.clamp();
+                format!("Copied to slot {} of {}", n + 1, target.path.display())
+            }
+            Err(e) => format!("Copy failed: {}", e),
+        }
+    }
+
+    /// Delete the selected save.
+    fn delete(&mut self) -> String {
+        let pane = &mut self.panes[self.focus];
+        let Some(index) = pane.selected().map(|s| s.index) else {
+            return "No save selected".to_string();
+        };
+
+        match pane.card.delete_save(index) {
+            Ok(()) => {
+                pane.modified = true;
+                pane.clamp();
+                format!("Deleted slot {}", index + 1)
+            }
+            Err(e) => format!("Delete failed: {}", e),
+        }
+    }
+
+    /// Export the selected save to a `.mcs` file in the current directory.
+    fn export(&self) -> String {
+        let Some(save) = self.panes[self.focus].selected() else {
+            return "No save selected".to_string();
+        };
+
+        let filename = format!("{}.mcs", save.serial());
+        match save
+            .to_mcs()
+            .and_then(|mcs| fs::write(&filename, mcs).map_err(MCError::from))
+        {
+            Ok(()) => format!("Exported to {}", filename),
+            Err(e) => format!("Export failed: {}", e),
+        }
+    }
+
+    /// Write the focused card back to its file.
+    fn write(&mut self) -> String {
+        let pane = &mut self.panes[self.focus];
+        match pane.card.write(&pane.path) {
+            Ok(()) => {
+                pane.modified = false;
+                format!("Wrote {}", pane.path.display())
+            }
+            Err(e) => format!("Write failed: {}", e),
+        }
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let [lists, preview, status] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(f.area());
+
+        let columns = Layout::horizontal(vec![Constraint::Fill(1); self.panes.len()]).split(lists);
+        for (n, area) in columns.iter().enumerate() {
+            self.draw_pane(f, n, *area);
+        }
+
+        self.draw_preview(f, preview);
+        f.render_widget(Paragraph::new(self.status.as_str()), status);
+    }
+
+    fn draw_pane(&mut self, f: &mut Frame, n: usize, area: Rect) {
+        let focused = n == self.focus;
+        let pane = &mut self.panes[n];
+
+        let items: Vec<ListItem> = pane
+            .card
+            .saves()
+            .map(|s| {
+                let title = s.title().unwrap_or_else(|_| "Unknown".to_string());
+                ListItem::new(format!(
+                    "{:>2}  {:<32} {:>2}",
+                    s.index + 1,
+                    title,
+                    s.blocks.len()
+                ))
+            })
+            .collect();
+
+        let mut title = format!(
+            " {} ({} free) ",
+            pane.path.display(),
+            pane.card.summary().free_blocks
+        );
+        if pane.modified {
+            title.push_str("[modified] ");
+        }
+
+        let border = if focused {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title(title),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, area, &mut pane.state);
+    }
+
+    fn draw_preview(&self, f: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title(" Preview ");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let Some(save) = self.panes[self.focus].selected() else {
+            return;
+        };
+
+        let [icon, details] =
+            Layout::horizontal([Constraint::Length(18), Constraint::Fill(1)]).areas(inner);
+
+        let frames = save.icon_frames().len().max(1);
+        let tick = (self.start.elapsed().as_millis() / ICON_FRAME_TIME.as_millis()) as usize;
+        if let Some(rgba) = save.data[0].icon_rgba(tick % frames) {
+            f.render_widget(Paragraph::new(icon_lines(&rgba)), icon);
+        }
+
+        let region = match save.region_info() {
+            Ok(r) => format!("{:?} / {:?}", r.region, r.license),
+            Err(_) => "Unknown".to_string(),
+        };
+        let text = vec![
+            Line::from(save.title().unwrap_or_else(|_| "Unknown".to_string())),
+            Line::from(format!("Serial: {}", save.serial())),
+            Line::from(format!("Region: {}", region)),
+            Line::from(format!(
+                "Blocks: {} ({} bytes)",
+                save.blocks.len(),
+                save.size()
+            )),
+            Line::from(format!("Icon frames: {}", save.icon_frames().len())),
+        ];
+        f.render_widget(Paragraph::new(text), details);
+    }
+}
+
+/// Render a 16x16 RGBA icon as 8 lines of half blocks, two pixel rows per line.
+fn icon_lines(rgba: &[u8]) -> Vec<Line<'static>> {
+    let pixel = |x: usize, y: usize| {
+        let p = &rgba[(y * 16 + x) * 4..];
+        Color::Rgb(p[0], p[1], p[2])
+    };
+
+    (0..8)
+        .map(|row| {
+            let spans: Vec<Span> = (0..16)
+                .map(|x| {
+                    Span::styled(
+                        "▀",
+                        Style::default()
+                            .fg(pixel(x, row * 2))
+                            .bg(pixel(x, row * 2 + 1)),
+                    )
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
@@ -0,0 +1,326 @@
+//! Builders for constructing valid frames from their fields, and cards from their saves.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use deku::prelude::*;
+
+use crate::{
+    calc_checksum, mcs, BAState, DataBlock, DirectoryFrame, Frame, MCError, MemCard, TitleFrame,
+    BLOCK_SIZE, CHAIN_END, FRAME_SIZE, TITLE_MAGIC,
+};
+
+/// DirectoryFrameBuilder
+///
+/// Builds a `DirectoryFrame` with zeroed padding and a valid checksum. Created by
+/// `DirectoryFrame::builder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectoryFrameBuilder {
+    state: BAState,
+    filesize: u32,
+    next_block: u16,
+    filename: String,
+}
+
+impl Default for DirectoryFrameBuilder {
+    fn default() -> Self {
+        DirectoryFrameBuilder {
+            state: BAState::Free,
+            filesize: 0,
+            next_block: CHAIN_END,
+            filename: String::new(),
+        }
+    }
+}
+
+impl DirectoryFrameBuilder {
+    /// Set the allocation state. Defaults to `BAState::Free`.
+    pub fn state(mut self, state: BAState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Set the size of the save in bytes. Defaults to 0.
+    pub fn filesize(mut self, filesize: u32) -> Self {
+        self.filesize = filesize;
+        self
+    }
+
+    /// Set the directory index of the next block in the chain. Defaults to `CHAIN_END`.
+    pub fn next_block(mut self, next_block: u16) -> Self {
+        self.next_block = next_block;
+        self
+    }
+
+    /// Set the filename, such as "BASLUS-00123SAVE01". Defaults to empty.
+    pub fn filename(mut self, filename: &str) -> Self {
+        self.filename = filename.to_string();
+        self
+    }
+
+    /// Validate the fields and build the `DirectoryFrame`.
+    pub fn build(self) -> Result<DirectoryFrame, MCError> {
+        if self.state == BAState::UNKNOWN {
+            return Err(MCError::InvalidValue(
+                "state can not be UNKNOWN".to_string(),
+            ));
+        }
+
+        if self.filesize as usize > BLOCK_SIZE * 15
+            || !(self.filesize as usize).is_multiple_of(BLOCK_SIZE)
+        {
+            return Err(MCError::InvalidValue(format!(
+                "filesize {} is not a multiple of the block size up to 15 blocks",
+                self.filesize
+            )));
+        }
+
+        if self.next_block != CHAIN_END && self.next_block >= 15 {
+            return Err(MCError::InvalidValue(format!(
+                "next_block {} is out of range",
+                self.next_block
+            )));
+        }
+
+        if self.filename.len() > 20 || !self.filename.is_ascii() {
+            return Err(MCError::InvalidValue(format!(
+                "filename {:?} is not up to 20 ASCII characters",
+                self.filename
+            )));
+        }
+
+        let mut filename = [0u8; 21];
+        filename[..self.filename.len()].copy_from_slice(self.filename.as_bytes());
+
+        let mut d = DirectoryFrame {
+            state: self.state as u32,
+            filesize: self.filesize,
+            next_block: self.next_block,
+            filename,
+            pad: [0u8; 96],
+            checksum: 0,
+        };
+        d.checksum = calc_checksum(&d.to_bytes()?);
+
+        Ok(d)
+    }
+}
+
+/// TitleFrameBuilder
+///
+/// Builds a `TitleFrame` with the "SC" magic set. Created by `TitleFrame::builder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TitleFrameBuilder {
+    title: String,
+    icon_frames: u8,
+    block_num: u8,
+    icon_palette: [u16; 16],
+}
+
+impl Default for TitleFrameBuilder {
+    fn default() -> Self {
+        TitleFrameBuilder {
+            title: String::new(),
+            icon_frames: 1,
+            block_num: 1,
+            icon_palette: [0u16; 16],
+        }
+    }
+}
+
+impl TitleFrameBuilder {
+    /// Set the title. Only ASCII letters, digits and spaces can be encoded, up to 32 characters.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Set the number of icon frames, 0 to 3, where 0 makes a save without an icon. Defaults
+    /// to 1.
+    pub fn icon_frames(mut self, icon_frames: u8) -> Self {
+        self.icon_frames = icon_frames;
+        self
+    }
+
+    /// Set the block number, 1 to 15. Defaults to 1.
+    pub fn block_num(mut self, block_num: u8) -> Self {
+        self.block_num = block_num;
+        self
+    }
+
+    /// Set the 16 color icon palette. Defaults to all black.
+    pub fn icon_palette(mut self, icon_palette: [u16; 16]) -> Self {
+        self.icon_palette = icon_palette;
+        self
+    }
+
+    /// Validate the fields and build the `TitleFrame`.
+    pub fn build(self) -> Result<TitleFrame, MCError> {
+        if self.icon_frames > 3 {
+            return Err(MCError::InvalidValue(format!(
+                "icon_frames {} is not 0 to 3",
+                self.icon_frames
+            )));
+        }
+
+        if !(1..=15).contains(&self.block_num) {
+            return Err(MCError::InvalidValue(format!(
+                "block_num {} is not 1 to 15",
+                self.block_num
+            )));
+        }
+
+        let encodable = |c: char| c.is_ascii_alphanumeric() || c == ' ';
+        if self.title.len() > 32 || !self.title.chars().all(encodable) {
+            return Err(MCError::InvalidValue(format!(
+                "title {:?} is not up to 32 ASCII letters, digits and spaces",
+                self.title
+            )));
+        }
+
+        Ok(TitleFrame {
+            id: TITLE_MAGIC,
+            display: match self.icon_frames {
+                0 => 0x00,
+                n => 0x10 + n,
+            },
+            block_num: self.block_num,
+            title: TitleFrame::encode_title(&self.title),
+            reserved: [0u8; 28],
+            icon_palette: self.icon_palette,
+        })
+    }
+}
+
+/// A save added to a `MemCardBuilder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SaveAsset {
+    Mcs(Vec<u8>),
+    McsFile(PathBuf),
+    Payload {
+        filename: String,
+        title_frame: TitleFrame,
+        icon_frames: Vec<Frame>,
+        payload: Vec<u8>,
+    },
+}
+
+/// MemCardBuilder
+///
+/// Builds a freshly formatted `MemCard` holding a list of saves, which are laid out in the
+/// order they were added, each in consecutive blocks. Created by `MemCard::builder`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemCardBuilder {
+    saves: Vec<SaveAsset>,
+}
+
+impl MemCardBuilder {
+    /// Add a save from the contents of a `.mcs` file.
+    pub fn mcs(mut self, mcs: &[u8]) -> Self {
+        self.saves.push(SaveAsset::Mcs(mcs.to_vec()));
+        self
+    }
+
+    /// Add a save from a `.mcs` file, which is read by `build`.
+    pub fn mcs_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.saves
+            .push(SaveAsset::McsFile(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Add a save from its parts: the directory `filename`, such as "BASLUS-00123SAVE01", the
+    /// title frame, the icon frames the title frame calls for, and the payload. The payload is
+    /// padded with zeros to fill the last block.
+    pub fn save(
+        mut self,
+        filename: &str,
+        title_frame: TitleFrame,
+        icon_frames: &[Frame],
+        payload: &[u8],
+    ) -> Self {
+        self.saves.push(SaveAsset::Payload {
+            filename: filename.to_string(),
+            title_frame,
+            icon_frames: icon_frames.to_vec(),
+            payload: payload.to_vec(),
+        });
+        self
+    }
+
+    /// Validate the saves and build the `MemCard`. Returns `MCError::CardFull` if the saves need
+    /// more than 15 blocks, and `MCError::InvalidValue` if a save is malformed or two saves have
+    /// the same filename.
+    pub fn build(self) -> Result<MemCard, MCError> {
+        let mut images = Vec::with_capacity(self.saves.len());
+        for asset in self.saves {
+            images.push(match asset {
+                SaveAsset::Mcs(mcs) => mcs,
+                SaveAsset::McsFile(path) => fs::read(&path).map_err(|e| {
+                    MCError::from(e).context(format!("Unable to read {}", path.display()))
+                })?,
+                SaveAsset::Payload {
+                    filename,
+                    title_frame,
+                    icon_frames,
+                    payload,
+                } => assemble(&filename, &title_frame, &icon_frames, &payload)?,
+            });
+        }
+
+        let blocks: usize = images
+            .iter()
+            .map(|mcs| mcs.len().saturating_sub(FRAME_SIZE) / BLOCK_SIZE)
+            .sum();
+        if blocks > 15 {
+            return Err(MCError::CardFull);
+        }
+
+        let mut card = MemCard::new();
+        let mut names = HashSet::new();
+        for mcs in &images {
+            let index = mcs::import(&mut card, mcs)?;
+            let filename = card.info.dir_frames[index].get_filename();
+            if !names.insert(filename.clone()) {
+                return Err(MCError::InvalidValue(format!(
+                    "more than one save is named {}",
+                    filename
+                )));
+            }
+        }
+
+        Ok(card)
+    }
+}
+
+/// Lay out a save from its parts as a `.mcs` file.
+fn assemble(
+    filename: &str,
+    title_frame: &TitleFrame,
+    icon_frames: &[Frame],
+    payload: &[u8],
+) -> Result<Vec<u8>, MCError> {
+    let room = BLOCK_SIZE.saturating_sub((1 + icon_frames.len()) * FRAME_SIZE);
+    let (head, rest) = payload.split_at(payload.len().min(room));
+    let mut first = [0u8; BLOCK_SIZE];
+    DataBlock::new(*title_frame, icon_frames, head)?.write_raw(&mut first)?;
+
+    let mut data = first.to_vec();
+    data.extend_from_slice(rest);
+    let blocks = data.len().div_ceil(BLOCK_SIZE);
+    if blocks > 15 {
+        return Err(MCError::CardFull);
+    }
+    data.resize(blocks * BLOCK_SIZE, 0);
+
+    let dir = DirectoryFrame::builder()
+        .state(BAState::AllocFirst)
+        .filesize((blocks * BLOCK_SIZE) as u32)
+        .filename(filename)
+        .build()?;
+
+    let mut mcs = dir.to_raw().to_vec();
+    mcs.extend_from_slice(&data);
+
+    Ok(mcs)
+}
@@ -0,0 +1,102 @@
+//! Integrity checks of fixed size units of data, such as the frames of a memory card.
+
+use std::fmt;
+
+use crate::{MCError, FRAME_SIZE};
+
+/// FrameChecksum
+///
+/// An integrity scheme for fixed size units of data that store their own checksum, such as the
+/// XOR checksum of memory card frames, or the checksums and signatures of other save containers.
+/// Implementations provide how the checksum is calculated, read and stored, and share the
+/// validation and update plumbing.
+pub trait FrameChecksum {
+    /// The checksum value, such as `u8` or a `[u8; 20]` digest.
+    type Value: Copy + Eq + fmt::Debug;
+
+    /// The size in bytes of a unit of data.
+    fn unit_size(&self) -> usize;
+
+    /// Calculate the checksum of the unit `d`, leaving out the stored checksum.
+    fn calc(&self, d: &[u8]) -> Self::Value;
+
+    /// Read the checksum stored in the unit `d`.
+    fn stored(&self, d: &[u8]) -> Self::Value;
+
+    /// Store the checksum `v` in the unit `d`.
+    fn store(&self, d: &mut [u8], v: Self::Value);
+
+    /// Validate that the stored checksum of the unit `d` matches its contents. Returns
+    /// `MCError::BadChecksum` if it does not.
+    fn validate(&self, d: &[u8]) -> Result<(), MCError> {
+        match self.calc(d) == self.stored(d) {
+            true => Ok(()),
+            false => Err(MCError::BadChecksum),
+        }
+    }
+
+    /// Calculate and store the checksum of the unit `d` after making edits.
+    fn update(&self, d: &mut [u8]) {
+        let v = self.calc(d);
+        self.store(d, v);
+    }
+
+    /// Validate every whole unit in `d`, returning the indices of the units whose checksum does
+    /// not match.
+    fn invalid_units(&self, d: &[u8]) -> Vec<usize> {
+        d.chunks_exact(self.unit_size())
+            .enumerate()
+            .filter(|(_, u)| self.validate(u).is_err())
+            .map(|(n, _)| n)
+            .collect()
+    }
+}
+
+/// XorFrame
+///
+/// The checksum of memory card frames: the last byte of a frame is the XOR of all of the bytes
+/// before it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XorFrame;
+
+impl FrameChecksum for XorFrame {
+    type Value = u8;
+
+    fn unit_size(&self) -> usize {
+        FRAME_SIZE
+    }
+
+    fn calc(&self, d: &[u8]) -> u8 {
+        match d.first_chunk::<FRAME_SIZE>() {
+            // XOR the whole frame, then cancel out the checksum byte
+            Some(f) => xor_frame(f) ^ f[FRAME_SIZE - 1],
+            None => d.iter().fold(0, |c, b| c ^ b),
+        }
+    }
+
+    fn stored(&self, d: &[u8]) -> u8 {
+        d[FRAME_SIZE - 1]
+    }
+
+    fn store(&self, d: &mut [u8], v: u8) {
+        d[FRAME_SIZE - 1] = v;
+    }
+
+    fn invalid_units(&self, d: &[u8]) -> Vec<usize> {
+        // A frame with a valid checksum XORs to zero, checksum byte included
+        let (frames, _) = d.as_chunks::<FRAME_SIZE>();
+        frames
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| xor_frame(f) != 0)
+            .map(|(n, _)| n)
+            .collect()
+    }
+}
+
+/// XOR every byte of a frame together, 8 bytes at a time.
+fn xor_frame(f: &[u8; FRAME_SIZE]) -> u8 {
+    let (words, _) = f.as_chunks::<8>();
+    let w = words.iter().fold(0, |w, c| w ^ u64::from_ne_bytes(*c));
+    w.to_ne_bytes().iter().fold(0, |c, b| c ^ b)
+}
@@ -0,0 +1,117 @@
+//! Game specific save decoders, which turn the payload of a save into named fields that can be
+//! edited, such as the gil in a Final Fantasy VII save.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{MCError, MemCard, Save};
+
+/// FieldValue
+///
+/// The value of a field decoded from a save.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldValue {
+    Int(i64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// The fields decoded from a save, by name.
+pub type SaveFields = BTreeMap<String, FieldValue>;
+
+/// GameSaveCodec
+///
+/// Decodes and re-encodes the saves of a specific game. Codecs work on the payload of a save, see
+/// `Save::payload`, and are looked up by the product code of the save in a `CodecRegistry`.
+pub trait GameSaveCodec: Send + Sync {
+    /// A short name for the codec, such as "ff7".
+    fn name(&self) -> &str;
+
+    /// Whether the codec handles saves with the product code `serial`, such as "SCUS-94163".
+    fn matches(&self, serial: &str) -> bool;
+
+    /// Decode the fields from a payload.
+    fn decode(&self, payload: &[u8]) -> Result<SaveFields, MCError>;
+
+    /// Write `fields` into a payload. Fields that are not given are left as they are.
+    fn encode(&self, fields: &SaveFields, payload: &mut [u8]) -> Result<(), MCError>;
+
+    /// Update any checksums the game keeps inside of the payload, after it was modified. The
+    /// default does nothing, for games without them.
+    fn fix_checksums(&self, _payload: &mut [u8]) -> Result<(), MCError> {
+        Ok(())
+    }
+}
+
+/// CodecRegistry
+///
+/// A list of `GameSaveCodec`s. The first registered codec that matches a save is used.
+#[derive(Default)]
+pub struct CodecRegistry {
+    codecs: Vec<Box<dyn GameSaveCodec>>,
+}
+
+impl CodecRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a codec to the registry.
+    pub fn register<C: GameSaveCodec + 'static>(&mut self, codec: C) -> &mut Self {
+        self.codecs.push(Box::new(codec));
+        self
+    }
+
+    /// The registered codecs, in the order they were registered.
+    pub fn codecs(&self) -> impl Iterator<Item = &dyn GameSaveCodec> {
+        self.codecs.iter().map(|c| c.as_ref())
+    }
+
+    /// Find the codec for saves with the product code `serial`.
+    pub fn find(&self, serial: &str) -> Option<&dyn GameSaveCodec> {
+        self.codecs().find(|c| c.matches(serial))
+    }
+
+    /// Decode `save` with its codec. Returns `None` if no codec matches the save.
+    pub fn decode(&self, save: &Save) -> Result<Option<SaveFields>, MCError> {
+        match self.find(&save.serial()) {
+            Some(c) => c.decode(&save.payload()?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Edit the fields of the save that starts at directory index `index` with its codec: the
+    /// fields are decoded, passed to `f`, encoded back, and the in-save checksums are fixed.
+    /// Returns `MCError::NotFound` if there is no such save, or no codec matches it.
+    pub fn edit<F: FnOnce(&mut SaveFields)>(
+        &self,
+        card: &mut MemCard,
+        index: usize,
+        f: F,
+    ) -> Result<(), MCError> {
+        let serial = card
+            .saves()
+            .find(|s| s.index == index)
+            .map(|s| s.serial())
+            .ok_or_else(|| MCError::NotFound(format!("no save starts at block {}", index)))?;
+        let codec = self
+            .find(&serial)
+            .ok_or_else(|| MCError::NotFound(format!("no codec for {}", serial)))?;
+
+        let mut cursor = card.save_cursor(index)?;
+        let mut payload = Vec::new();
+        cursor.read_to_end(&mut payload)?;
+
+        let mut fields = codec.decode(&payload)?;
+        f(&mut fields);
+        codec.encode(&fields, &mut payload)?;
+        codec.fix_checksums(&mut payload)?;
+
+        cursor.seek(SeekFrom::Start(0))?;
+        cursor.write_all(&payload)?;
+        cursor.flush()?;
+
+        Ok(())
+    }
+}
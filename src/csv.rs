@@ -0,0 +1,54 @@
+//! Exporting the contents of a memory card as CSV, for cataloging collections in spreadsheets.
+
+use std::io::Write;
+
+use crate::{MCError, MemCard, Save};
+
+/// The header row of the CSV export.
+const HEADER: [&str; 7] = [
+    "slot", "serial", "title", "region", "blocks", "size", "state",
+];
+
+/// Write one row per save on `card` to `out`, live saves first and then deleted saves, each in
+/// directory order.
+pub(crate) fn export<W: Write>(card: &MemCard, out: &mut W) -> Result<(), MCError> {
+    write_row(out, &HEADER.map(String::from))?;
+
+    for s in card.saves().chain(card.deleted_saves()) {
+        write_row(out, &row(&s))?;
+    }
+
+    Ok(())
+}
+
+fn row(s: &Save) -> [String; 7] {
+    let region = s
+        .region_info()
+        .map(|r| format!("{:?}", r.region))
+        .unwrap_or_default();
+
+    [
+        (s.index + 1).to_string(),
+        s.serial(),
+        s.title().unwrap_or_default(),
+        region,
+        s.blocks.len().to_string(),
+        s.size().to_string(),
+        format!("{:?}", s.dir_frame.get_alloc_state()),
+    ]
+}
+
+/// Write `fields` as a CSV record, quoting fields that contain separators, quotes or line
+/// breaks as described in RFC 4180.
+fn write_row<W: Write>(out: &mut W, fields: &[String]) -> Result<(), MCError> {
+    let line: Vec<String> = fields
+        .iter()
+        .map(|f| match f.contains([',', '"', '\r', '\n']) {
+            true => format!("\"{}\"", f.replace('"', "\"\"")),
+            false => f.clone(),
+        })
+        .collect();
+    write!(out, "{}\r\n", line.join(","))?;
+
+    Ok(())
+}
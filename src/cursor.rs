@@ -0,0 +1,149 @@
+//! A file-like cursor over the payload of a save.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::{DataBlock, MCError, MemCard, BLOCK_SIZE, FRAME_SIZE};
+
+/// SaveCursor
+///
+/// Reads and writes the payload of a save as if it were a file. The payload is everything after
+/// the title and icon frames: the rest of the first block, followed by the following blocks of
+/// the save in chain order. Created by `MemCard::save_cursor`.
+///
+/// Writes go to a copy of the save, which is stored back on the card by `flush`, or when the
+/// cursor is dropped. Flushing also sets the filesize in the directory to the size of the
+/// allocated blocks, and updates the checksum of the directory frame. The payload has a fixed
+/// size, writes past the end fail with `WriteZero`.
+#[derive(Debug)]
+pub struct SaveCursor<'a> {
+    card: &'a mut MemCard,
+    index: usize,
+    blocks: Vec<usize>,
+    image: Vec<u8>,
+    start: usize,
+    pos: u64,
+    dirty: bool,
+}
+
+impl<'a> SaveCursor<'a> {
+    pub(crate) fn new(card: &'a mut MemCard, index: usize) -> Result<Self, MCError> {
+        let save = card
+            .saves()
+            .find(|s| s.index == index)
+            .ok_or_else(|| MCError::NotFound(format!("no save starts at block {}", index)))?;
+        let blocks = save.blocks.clone();
+        let start = FRAME_SIZE * (1 + save.icon_frames().len());
+
+        let mut image = vec![0u8; blocks.len() * BLOCK_SIZE];
+        for (d, b) in save.data.iter().zip(image.as_chunks_mut::<BLOCK_SIZE>().0) {
+            d.write_raw(b)?;
+        }
+
+        Ok(SaveCursor {
+            card,
+            index,
+            blocks,
+            image,
+            start,
+            pos: 0,
+            dirty: false,
+        })
+    }
+
+    /// The size of the payload in bytes.
+    pub fn len(&self) -> u64 {
+        (self.image.len() - self.start) as u64
+    }
+
+    /// Whether the payload is empty, which is never the case for a valid save.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The directory index of the first block of the save.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Store the written data back on the card.
+    fn store(&mut self) -> Result<(), MCError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        for (n, b) in self
+            .blocks
+            .iter()
+            .zip(self.image.as_chunks::<BLOCK_SIZE>().0)
+        {
+            self.card.data[*n] = DataBlock::from_raw(b);
+        }
+
+        let size = (self.blocks.len() * BLOCK_SIZE) as u32;
+        self.card.info.dir_frames[self.index].edit(|d| d.filesize = size)?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    /// The remaining payload from the current position.
+    fn remaining(&self) -> &[u8] {
+        let pos = (self.start as u64)
+            .saturating_add(self.pos)
+            .min(self.image.len() as u64);
+        &self.image[pos as usize..]
+    }
+}
+
+impl Read for SaveCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining().read(buf)?;
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Write for SaveCursor<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let pos = (self.start as u64)
+            .saturating_add(self.pos)
+            .min(self.image.len() as u64) as usize;
+        let n = buf.len().min(self.image.len() - pos);
+        self.image[pos..pos + n].copy_from_slice(&buf[..n]);
+        self.pos += n as u64;
+        self.dirty |= n > 0;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.store().map_err(io::Error::other)
+    }
+}
+
+impl Seek for SaveCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(n) => self.len().checked_add_signed(n),
+        };
+
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.pos)
+    }
+}
+
+impl Drop for SaveCursor<'_> {
+    fn drop(&mut self) {
+        // Like `BufWriter`, errors on drop are ignored, call `flush` to handle them
+        let _ = self.store();
+    }
+}
@@ -0,0 +1,93 @@
+//! Compact `Debug` implementations for the frame types, which summarize padding and raw data
+//! instead of dumping every byte.
+
+use std::fmt;
+
+use crate::{Block, BrokenFrame, DirectoryFrame, Frame, Header, TitleFrame};
+
+/// Summary
+///
+/// Formats a byte slice as its length plus either the repeated fill byte, or an FNV-1a hash of
+/// the contents.
+struct Summary<'a>(&'a [u8]);
+
+impl fmt::Debug for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.first() {
+            Some(b) if self.0.iter().all(|v| v == b) => write!(f, "[{:#04x}; {}]", b, self.0.len()),
+            _ => {
+                let hash = self.0.iter().fold(0x811c9dc5_u32, |h, b| {
+                    (h ^ *b as u32).wrapping_mul(0x01000193)
+                });
+                write!(f, "[u8; {}] fnv={:08x}", self.0.len(), hash)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Header")
+            .field("id", &String::from_utf8_lossy(&self.id))
+            .field("pad", &Summary(&self.pad))
+            .field("checksum", &self.checksum)
+            .finish()
+    }
+}
+
+impl fmt::Debug for DirectoryFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let filename = String::from_utf8_lossy(&self.filename);
+        f.debug_struct("DirectoryFrame")
+            .field(
+                "state",
+                &format_args!("{:?} ({:#04x})", self.get_alloc_state(), self.state),
+            )
+            .field("filesize", &self.filesize)
+            .field("next_block", &self.next_block)
+            .field("filename", &filename.trim_end_matches('\0'))
+            .field("pad", &Summary(&self.pad))
+            .field("checksum", &self.checksum)
+            .finish()
+    }
+}
+
+impl fmt::Debug for BrokenFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BrokenFrame")
+            .field("broken_frame", &format_args!("{:#010x}", self.broken_frame))
+            .field("pad", &Summary(&self.pad))
+            .field("checksum", &self.checksum)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("data", &Summary(&self.data))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Block")
+            .field("data", &Summary(&self.data))
+            .finish()
+    }
+}
+
+impl fmt::Debug for TitleFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let title = self.decode_title().unwrap_or_default();
+        f.debug_struct("TitleFrame")
+            .field("id", &String::from_utf8_lossy(&self.id))
+            .field("display", &format_args!("{:#04x}", self.display))
+            .field("block_num", &self.block_num)
+            .field("title", &title)
+            .field("reserved", &Summary(&self.reserved))
+            .field("icon_palette", &format_args!("{:04x?}", self.icon_palette))
+            .finish()
+    }
+}
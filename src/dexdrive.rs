@@ -0,0 +1,250 @@
+//! Reading and writing physical memory cards with a DexDrive attached to a serial port.
+
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::{CardGeometry, MCError, MemCard, OpenOptions, FRAME_SIZE};
+
+/// The baud rate of the DexDrive.
+pub const BAUD_RATE: u32 = 38_400;
+
+/// The prefix of every command and response.
+const PREFIX: [u8; 3] = *b"IAI";
+
+/// The key sent with `INIT`.
+const INIT_KEY: [u8; 17] = [
+    0x10, 0x29, 0x23, 0xbe, 0x84, 0xe1, 0x6c, 0xd6, 0xae, 0x52, 0x90, 0x49, 0xf1, 0xf1, 0xbb, 0xe9,
+    0xeb,
+];
+
+/// How many times a frame is read again after a checksum mismatch.
+const RETRIES: usize = 3;
+
+/// Commands.
+const INIT: u8 = 0x00;
+const READ: u8 = 0x02;
+const WRITE: u8 = 0x04;
+const LIGHT: u8 = 0x07;
+const MAGIC_HANDSHAKE: u8 = 0x27;
+
+/// Responses.
+const ERROR: u8 = 0x21;
+const NOCARD: u8 = 0x22;
+const WRITE_SAME: u8 = 0x27;
+const WRITE_OK: u8 = 0x28;
+const ID: u8 = 0x40;
+const DATA: u8 = 0x41;
+
+/// DexDrive
+///
+/// A connection to a DexDrive. Cards are read with `read_card` and written with `write_card`,
+/// one frame at a time. Frames are sent with an XOR checksum over the frame number and data;
+/// frames that are read with a bad checksum are read again.
+#[derive(Debug)]
+pub struct DexDrive<T> {
+    port: T,
+    firmware: u8,
+}
+
+impl DexDrive<Box<dyn serialport::SerialPort>> {
+    /// Open and wake up the DexDrive on the serial port at `path`, such as "/dev/ttyUSB0" or
+    /// "COM1".
+    pub fn open(path: &str) -> Result<Self, MCError> {
+        let mut port = serialport::new(path, BAUD_RATE)
+            .timeout(Duration::from_secs(2))
+            .open()
+            .map_err(io::Error::from)?;
+
+        // The DexDrive is powered through the control lines, and woken up by any input
+        port.write_data_terminal_ready(true)
+            .map_err(io::Error::from)?;
+        port.write_request_to_send(true).map_err(io::Error::from)?;
+        port.write_all(b"XXXXX")?;
+        thread::sleep(Duration::from_millis(20));
+        port.clear(serialport::ClearBuffer::Input)
+            .map_err(io::Error::from)?;
+
+        DexDrive::new(port)
+    }
+}
+
+impl<T: Read + Write> DexDrive<T> {
+    /// Initialize the DexDrive on `port`, which must already be set up for `BAUD_RATE` 8N1.
+    /// Returns an error if the device does not identify itself as a PlayStation DexDrive.
+    pub fn new(port: T) -> Result<Self, MCError> {
+        let mut dex = DexDrive { port, firmware: 0 };
+
+        dex.send(INIT, &INIT_KEY)?;
+        let mut id = [0u8; 5];
+        match dex.response()? {
+            ID => dex.port.read_exact(&mut id)?,
+            r => {
+                return Err(dex_error(&format!(
+                    "unexpected response {:#04x} to INIT",
+                    r
+                )))
+            }
+        }
+        if &id[1..4] != b"PSX" {
+            return Err(dex_error("not a PlayStation DexDrive"));
+        }
+        dex.firmware = id[4];
+        dex.send(MAGIC_HANDSHAKE, &[])?;
+
+        Ok(dex)
+    }
+
+    /// The firmware version reported by the device.
+    pub fn firmware(&self) -> u8 {
+        self.firmware
+    }
+
+    /// Turn the activity light on or off.
+    pub fn light(&mut self, on: bool) -> Result<(), MCError> {
+        self.send(LIGHT, &[on as u8])
+    }
+
+    /// Read the frame `n` of the card.
+    pub fn read_frame(&mut self, n: u16) -> Result<[u8; FRAME_SIZE], MCError> {
+        let [msb, lsb] = n.to_be_bytes();
+        for _ in 0..=RETRIES {
+            self.send(READ, &[msb, lsb])?;
+            self.expect(DATA)?;
+
+            let mut frame = [0u8; FRAME_SIZE];
+            let mut xor = [0u8; 1];
+            self.port.read_exact(&mut frame)?;
+            self.port.read_exact(&mut xor)?;
+            if xor[0] == frame.iter().fold(msb ^ lsb, |c, b| c ^ b) {
+                return Ok(frame);
+            }
+        }
+
+        Err(MCError::BadChecksum)
+    }
+
+    /// Write `frame` to the frame `n` of the card.
+    pub fn write_frame(&mut self, n: u16, frame: &[u8; FRAME_SIZE]) -> Result<(), MCError> {
+        let [msb, lsb] = n.to_be_bytes();
+        let (rmsb, rlsb) = (msb.reverse_bits(), lsb.reverse_bits());
+        let xor = frame.iter().fold(msb ^ lsb ^ rmsb ^ rlsb, |c, b| c ^ b);
+
+        let mut payload = Vec::with_capacity(FRAME_SIZE + 5);
+        payload.extend_from_slice(&[msb, lsb, rlsb, rmsb]);
+        payload.extend_from_slice(frame);
+        payload.push(xor);
+        self.send(WRITE, &payload)?;
+
+        match self.response()? {
+            WRITE_OK | WRITE_SAME => Ok(()),
+            r => Err(dex_error(&format!(
+                "unexpected response {:#04x} to WRITE",
+                r
+            ))),
+        }
+    }
+
+    /// Read the raw image of the card.
+    pub fn read_raw(&mut self) -> Result<Vec<u8>, MCError> {
+        let frames = CardGeometry::STANDARD.size() / FRAME_SIZE;
+        self.with_light(|dex| {
+            let mut data = Vec::with_capacity(CardGeometry::STANDARD.size());
+            for n in 0..frames {
+                data.extend_from_slice(&dex.read_frame(n as u16)?);
+            }
+            Ok(data)
+        })
+    }
+
+    /// Read the card.
+    pub fn read_card(&mut self) -> Result<MemCard, MCError> {
+        self.read_card_with(OpenOptions::default())
+    }
+
+    /// Read the card, parsing it with the provided `OpenOptions`.
+    pub fn read_card_with(&mut self, opts: OpenOptions) -> Result<MemCard, MCError> {
+        MemCard::from_reader_with(self.read_raw()?.as_slice(), opts)
+    }
+
+    /// Write the raw image `data` to the card.
+    pub fn write_raw(&mut self, data: &[u8]) -> Result<(), MCError> {
+        if data.len() != CardGeometry::STANDARD.size() {
+            return Err(MCError::InvalidValue(format!(
+                "{} bytes is not a card image size",
+                data.len()
+            )));
+        }
+
+        self.with_light(|dex| {
+            for (n, frame) in data.as_chunks::<FRAME_SIZE>().0.iter().enumerate() {
+                dex.write_frame(n as u16, frame)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Write `card` to the card.
+    pub fn write_card(&mut self, card: &MemCard) -> Result<(), MCError> {
+        let mut data = Vec::with_capacity(CardGeometry::STANDARD.size());
+        card.write_to(&mut data)?;
+
+        self.write_raw(&data)
+    }
+
+    /// Release the port.
+    pub fn into_inner(self) -> T {
+        self.port
+    }
+
+    /// Run `f` with the activity light on.
+    fn with_light<R, F>(&mut self, f: F) -> Result<R, MCError>
+    where
+        F: FnOnce(&mut Self) -> Result<R, MCError>,
+    {
+        self.light(true)?;
+        let r = f(self);
+        self.light(false)?;
+        r
+    }
+
+    fn send(&mut self, cmd: u8, payload: &[u8]) -> Result<(), MCError> {
+        let mut buf = Vec::with_capacity(PREFIX.len() + 1 + payload.len());
+        buf.extend_from_slice(&PREFIX);
+        buf.push(cmd);
+        buf.extend_from_slice(payload);
+        self.port.write_all(&buf)?;
+        self.port.flush()?;
+
+        Ok(())
+    }
+
+    /// Read up to and including the type of the next response.
+    fn response(&mut self) -> Result<u8, MCError> {
+        let mut last = [0u8; 3];
+        while last != PREFIX {
+            let mut b = [0u8; 1];
+            self.port.read_exact(&mut b)?;
+            last.rotate_left(1);
+            last[2] = b[0];
+        }
+
+        let mut r = [0u8; 1];
+        self.port.read_exact(&mut r)?;
+
+        Ok(r[0])
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), MCError> {
+        match self.response()? {
+            r if r == expected => Ok(()),
+            NOCARD => Err(dex_error("no card inserted")),
+            ERROR => Err(dex_error("the device reported an error")),
+            r => Err(dex_error(&format!("unexpected response {:#04x}", r))),
+        }
+    }
+}
+
+fn dex_error(msg: &str) -> MCError {
+    io::Error::other(format!("DexDrive: {}", msg)).into()
+}
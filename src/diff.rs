@@ -0,0 +1,146 @@
+//! Comparing two memory cards save by save, such as a card before and after a play session.
+
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::fmt;
+
+use crate::{MCError, MemCard, Save, BLOCK_SIZE, FRAME_SIZE};
+
+/// ChangedFrame
+///
+/// A frame that differs between two copies of a save. `block` is the position of the block in
+/// the allocation chain of the save, and `frame` the frame (0..64) within that block, so the
+/// position does not depend on where the save is stored on the card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChangedFrame {
+    pub block: usize,
+    pub frame: usize,
+}
+
+/// SaveChange
+///
+/// How a save differs between two cards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveChange {
+    /// The save is only on the other card.
+    Added,
+
+    /// The save is only on this card.
+    Removed,
+
+    /// The save is on both cards, but its size or contents differ. The frames of blocks that
+    /// only one of the copies has are all listed as changed.
+    Modified { frames: Vec<ChangedFrame> },
+}
+
+/// SaveDiff
+///
+/// The difference of one save, identified by its directory filename.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveDiff {
+    pub filename: String,
+    pub change: SaveChange,
+}
+
+/// CardDiff
+///
+/// The differences between two cards, as returned by `MemCard::diff`, with one entry per save
+/// that differs, sorted by filename.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CardDiff {
+    pub saves: Vec<SaveDiff>,
+}
+
+impl CardDiff {
+    /// Whether the cards hold the same saves.
+    pub fn is_empty(&self) -> bool {
+        self.saves.is_empty()
+    }
+}
+
+impl fmt::Display for SaveDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.change {
+            SaveChange::Added => write!(f, "+ {}", self.filename),
+            SaveChange::Removed => write!(f, "- {}", self.filename),
+            SaveChange::Modified { frames } => {
+                write!(f, "~ {} ({} frames changed)", self.filename, frames.len())
+            }
+        }
+    }
+}
+
+impl fmt::Display for CardDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for s in &self.saves {
+            writeln!(f, "{}", s)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare the live saves of `old` and `new`. Saves are matched by their directory filename.
+/// If several saves have the same filename, the first one is used.
+pub(crate) fn diff(old: &MemCard, new: &MemCard) -> Result<CardDiff, MCError> {
+    let old_saves = raw_saves(old)?;
+    let mut new_saves = raw_saves(new)?;
+
+    let mut out = CardDiff::default();
+    for (filename, a) in old_saves {
+        let change = match new_saves.remove(&filename) {
+            None => SaveChange::Removed,
+            Some(b) if a == b => continue,
+            Some(b) => SaveChange::Modified {
+                frames: changed_frames(&a, &b),
+            },
+        };
+        out.saves.push(SaveDiff { filename, change });
+    }
+
+    out.saves
+        .extend(new_saves.into_keys().map(|filename| SaveDiff {
+            filename,
+            change: SaveChange::Added,
+        }));
+    out.saves.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Ok(out)
+}
+
+/// The live saves of `card` as the raw bytes of their data blocks, in chain order, by filename.
+fn raw_saves(card: &MemCard) -> Result<BTreeMap<String, Vec<u8>>, MCError> {
+    let mut saves = BTreeMap::new();
+    for s in card.saves() {
+        if let Entry::Vacant(e) = saves.entry(s.filename()) {
+            e.insert(raw(&s)?);
+        }
+    }
+
+    Ok(saves)
+}
+
+fn raw(save: &Save) -> Result<Vec<u8>, MCError> {
+    let mut out = Vec::with_capacity(save.data.len() * BLOCK_SIZE);
+    let mut block = [0u8; BLOCK_SIZE];
+    for d in &save.data {
+        d.write_raw(&mut block)?;
+        out.extend_from_slice(&block);
+    }
+
+    Ok(out)
+}
+
+/// The frames that differ between the raw saves `a` and `b`.
+fn changed_frames(a: &[u8], b: &[u8]) -> Vec<ChangedFrame> {
+    let frames = a.len().max(b.len()) / FRAME_SIZE;
+    (0..frames)
+        .filter(|n| {
+            let range = n * FRAME_SIZE..(n + 1) * FRAME_SIZE;
+            a.get(range.clone()) != b.get(range)
+        })
+        .map(|n| ChangedFrame {
+            block: n * FRAME_SIZE / BLOCK_SIZE,
+            frame: n % (BLOCK_SIZE / FRAME_SIZE),
+        })
+        .collect()
+}
@@ -0,0 +1,34 @@
+//! SHA-256 digests of cards and saves, for integrity manifests and for finding identical saves
+//! across collections of card images.
+
+use sha2::{Digest, Sha256};
+
+use crate::{MCError, MemCard, Save, BLOCK_SIZE};
+
+impl MemCard {
+    /// The SHA-256 digest of the card image, as written by `write_to`. Call `canonicalize`
+    /// first to also match cards that only differ outside of their saves.
+    pub fn digest(&self) -> Result<[u8; 32], MCError> {
+        let mut image = Vec::new();
+        self.write_to(&mut image)?;
+
+        Ok(Sha256::digest(&image).into())
+    }
+}
+
+impl Save<'_> {
+    /// The SHA-256 digest of the directory filename and the data blocks of the save. The links
+    /// of the directory frames are left out, so the same save has the same digest on every card,
+    /// wherever it is stored.
+    pub fn digest(&self) -> Result<[u8; 32], MCError> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.dir_frame.filename);
+        let mut block = [0u8; BLOCK_SIZE];
+        for d in &self.data {
+            d.write_raw(&mut block)?;
+            hasher.update(block);
+        }
+
+        Ok(hasher.finalize().into())
+    }
+}
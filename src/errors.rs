@@ -5,7 +5,37 @@ use std::str::Utf8Error;
 
 use thiserror::Error;
 
+/// ErrorKind
+///
+/// A broad classification of an `MCError`, so callers can handle categories of errors without
+/// matching on every variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Reading or writing the underlying file or stream failed.
+    Io,
+
+    /// The memory card data is damaged or malformed.
+    Corrupt,
+
+    /// The data is not in a format this library can handle.
+    Unsupported,
+
+    /// There is not enough free space on the memory card.
+    Full,
+
+    /// The requested item does not exist.
+    NotFound,
+
+    /// A value passed in by the caller is out of range.
+    InvalidInput,
+
+    /// The memory card was opened read-only.
+    ReadOnly,
+}
+
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum MCError {
     #[error("DekuError: {0}")]
     Deku(#[from] deku::DekuError),
@@ -24,4 +54,65 @@ pub enum MCError {
 
     #[error("Checksum does not match expected value")]
     BadChecksum,
+
+    #[error("Header magic is not \"MC\", this is not a memory card")]
+    NotAMemoryCard,
+
+    #[error("Title frame magic is not \"SC\", this block does not start a save")]
+    NotASave,
+
+    #[error("Not enough free blocks on the memory card")]
+    CardFull,
+
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("The memory card was opened read-only")]
+    ReadOnly,
+
+    #[cfg(feature = "watch")]
+    #[error("Unable to watch file: {0}")]
+    Watch(#[from] notify::Error),
+
+    #[error("{context}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<MCError>,
+    },
+}
+
+impl MCError {
+    /// Classify the error into an `ErrorKind`. Errors with added context are classified by the
+    /// error they wrap.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            MCError::Io(_) => ErrorKind::Io,
+            MCError::PngEncodingError(_) => ErrorKind::Io,
+            MCError::GifEncodingError(_) => ErrorKind::Io,
+            MCError::Deku(_) => ErrorKind::Corrupt,
+            MCError::Utf8Error(_) => ErrorKind::Corrupt,
+            MCError::BadChecksum => ErrorKind::Corrupt,
+            MCError::NotAMemoryCard => ErrorKind::Unsupported,
+            MCError::NotASave => ErrorKind::Corrupt,
+            MCError::CardFull => ErrorKind::Full,
+            MCError::InvalidValue(_) => ErrorKind::InvalidInput,
+            MCError::NotFound(_) => ErrorKind::NotFound,
+            MCError::ReadOnly => ErrorKind::ReadOnly,
+            #[cfg(feature = "watch")]
+            MCError::Watch(_) => ErrorKind::Io,
+            MCError::Context { source, .. } => source.kind(),
+        }
+    }
+
+    /// Wrap the error with a message describing what was being done when it happened.
+    pub fn context<C: Into<String>>(self, context: C) -> Self {
+        MCError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
 }
@@ -0,0 +1,305 @@
+//! C API for using memory cards from C and C++, such as from emulator frontends. The header for
+//! these functions is `include/psxmem.h`, generated with `cbindgen` from this file.
+//!
+//! Functions that can fail return a `PsxStatus`. The message of the last error on the calling
+//! thread can be fetched with `psxmem_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::path::Path;
+use std::{ptr, slice};
+
+use crate::{ErrorKind, MCError, MemCard, Save};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// PsxCard
+///
+/// An opaque handle to a memory card, created by `psxmem_open` or `psxmem_open_bytes` and
+/// released with `psxmem_free`.
+pub struct PsxCard {
+    card: MemCard,
+}
+
+/// PsxStatus
+///
+/// The result of a C API call. The error values mirror `ErrorKind`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PsxStatus {
+    Ok = 0,
+    Io = -1,
+    Corrupt = -2,
+    Unsupported = -3,
+    Full = -4,
+    NotFound = -5,
+    InvalidInput = -6,
+}
+
+impl From<ErrorKind> for PsxStatus {
+    fn from(k: ErrorKind) -> Self {
+        match k {
+            ErrorKind::Io => PsxStatus::Io,
+            ErrorKind::Corrupt => PsxStatus::Corrupt,
+            ErrorKind::Unsupported => PsxStatus::Unsupported,
+            ErrorKind::Full => PsxStatus::Full,
+            ErrorKind::NotFound => PsxStatus::NotFound,
+            _ => PsxStatus::InvalidInput,
+        }
+    }
+}
+
+/// PsxSaveInfo
+///
+/// The details of a save, filled in by `psxmem_save_info`. Strings are NUL terminated UTF-8, and
+/// truncated if they do not fit.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PsxSaveInfo {
+    /// The 1-based slot of the first block of the save.
+    pub slot: usize,
+
+    /// The number of blocks in the save.
+    pub blocks: usize,
+
+    /// The size of the save in bytes, as recorded in the directory.
+    pub size: u32,
+
+    /// The number of icon frames.
+    pub icon_frames: usize,
+
+    /// The decoded title, or an empty string if the save has no title frame.
+    pub title: [c_char; 128],
+
+    /// The product code of the game, such as "SLUS-00123".
+    pub serial: [c_char; 16],
+
+    /// The directory filename of the save.
+    pub filename: [c_char; 24],
+}
+
+/// Record `e` as the last error and return its status.
+fn fail(e: MCError) -> PsxStatus {
+    let status = PsxStatus::from(e.kind());
+    let msg = CString::new(e.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|l| *l.borrow_mut() = Some(msg));
+
+    status
+}
+
+/// Convert a `Result` into a `PsxStatus`, recording any error.
+fn status(r: Result<(), MCError>) -> PsxStatus {
+    match r {
+        Ok(()) => PsxStatus::Ok,
+        Err(e) => fail(e),
+    }
+}
+
+/// Copy `s` into the C string buffer `out`, truncating it to fit.
+fn copy_str(s: &str, out: &mut [c_char]) {
+    let n = s.len().min(out.len() - 1);
+    for (o, b) in out.iter_mut().zip(&s.as_bytes()[..n]) {
+        *o = *b as c_char;
+    }
+    out[n] = 0;
+}
+
+/// Convert the C string `path` to a `Path`.
+///
+/// # Safety
+///
+/// `path` must be null or a valid NUL terminated string.
+unsafe fn to_path<'a>(path: *const c_char) -> Result<&'a Path, MCError> {
+    if path.is_null() {
+        return Err(MCError::InvalidValue("path is null".to_string()));
+    }
+
+    let s = CStr::from_ptr(path).to_str()?;
+    Ok(Path::new(s))
+}
+
+/// Find the live save whose first block is in the 1-based `slot`.
+fn find_save(card: &MemCard, slot: usize) -> Result<Save<'_>, MCError> {
+    card.saves()
+        .find(|s| s.index + 1 == slot)
+        .ok_or_else(|| MCError::NotFound(format!("no save in slot {}", slot)))
+}
+
+/// Get the message of the last error on this thread, or null if there was none. The string is
+/// valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn psxmem_last_error() -> *const c_char {
+    LAST_ERROR.with(|l| l.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Open the raw card image at `path`. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_open(path: *const c_char) -> *mut PsxCard {
+    match to_path(path).and_then(MemCard::open) {
+        Ok(card) => Box::into_raw(Box::new(PsxCard { card })),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Open a card from the `len` bytes of a raw image at `data`. Returns null on failure.
+///
+/// # Safety
+///
+/// `data` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_open_bytes(data: *const u8, len: usize) -> *mut PsxCard {
+    if data.is_null() {
+        fail(MCError::InvalidValue("data is null".to_string()));
+        return ptr::null_mut();
+    }
+
+    match MemCard::from_reader(slice::from_raw_parts(data, len)) {
+        Ok(card) => Box::into_raw(Box::new(PsxCard { card })),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Release a card. Passing null does nothing.
+///
+/// # Safety
+///
+/// `card` must be null or a handle returned by `psxmem_open` or `psxmem_open_bytes` that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_free(card: *mut PsxCard) {
+    if !card.is_null() {
+        drop(Box::from_raw(card));
+    }
+}
+
+/// Get the number of live saves on the card.
+///
+/// # Safety
+///
+/// `card` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_save_count(card: *const PsxCard) -> usize {
+    (*card).card.saves().count()
+}
+
+/// Fill in `out` with the details of the `n`th live save on the card.
+///
+/// # Safety
+///
+/// `card` must be a valid handle, and `out` must point to a writable `PsxSaveInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_save_info(
+    card: *const PsxCard,
+    n: usize,
+    out: *mut PsxSaveInfo,
+) -> PsxStatus {
+    let Some(s) = (*card).card.saves().nth(n) else {
+        return fail(MCError::NotFound(format!("no save {}", n)));
+    };
+
+    let mut info = PsxSaveInfo {
+        slot: s.index + 1,
+        blocks: s.blocks.len(),
+        size: s.size(),
+        icon_frames: s.icon_frames().len(),
+        title: [0; 128],
+        serial: [0; 16],
+        filename: [0; 24],
+    };
+    copy_str(&s.title().unwrap_or_default(), &mut info.title);
+    copy_str(&s.serial(), &mut info.serial);
+    copy_str(
+        String::from_utf8_lossy(&s.dir_frame.filename).trim_end_matches('\0'),
+        &mut info.filename,
+    );
+    out.write(info);
+
+    PsxStatus::Ok
+}
+
+/// Export the save in the 1-based `slot` as a `.mcs` file. On success `*out` and `*len` are set
+/// to a buffer that must be released with `psxmem_bytes_free`.
+///
+/// # Safety
+///
+/// `card` must be a valid handle, and `out` and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_export_save(
+    card: *const PsxCard,
+    slot: usize,
+    out: *mut *mut u8,
+    len: *mut usize,
+) -> PsxStatus {
+    match find_save(&(*card).card, slot).and_then(|s| s.to_mcs()) {
+        Ok(mcs) => {
+            let mcs = mcs.into_boxed_slice();
+            len.write(mcs.len());
+            out.write(Box::into_raw(mcs) as *mut u8);
+            PsxStatus::Ok
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Release a buffer returned by `psxmem_export_save`. Passing null does nothing.
+///
+/// # Safety
+///
+/// `data` and `len` must be exactly as returned by `psxmem_export_save`, and not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_bytes_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Import the `len` bytes of a `.mcs` file at `mcs` into free blocks of the card. On success, if
+/// `slot` is not null it is set to the 1-based slot of the first block of the save.
+///
+/// # Safety
+///
+/// `card` must be a valid handle, `mcs` must point to at least `len` readable bytes, and `slot`
+/// must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_import_save(
+    card: *mut PsxCard,
+    mcs: *const u8,
+    len: usize,
+    slot: *mut usize,
+) -> PsxStatus {
+    if mcs.is_null() {
+        return fail(MCError::InvalidValue("mcs is null".to_string()));
+    }
+
+    match (*card).card.import_mcs(slice::from_raw_parts(mcs, len)) {
+        Ok(index) => {
+            if !slot.is_null() {
+                slot.write(index + 1);
+            }
+            PsxStatus::Ok
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Write the card out as a raw image to `path`.
+///
+/// # Safety
+///
+/// `card` must be a valid handle, and `path` must be null or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn psxmem_write(card: *const PsxCard, path: *const c_char) -> PsxStatus {
+    status(to_path(path).and_then(|p| (*card).card.write(p)))
+}
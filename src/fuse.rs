@@ -0,0 +1,416 @@
+//! Mounting a memory card image as a directory with FUSE.
+//!
+//! Every live save shows up as a file named after its directory filename, such as
+//! "BASLUS-00123SAVE01", holding the raw data of its blocks. Files can be read, written, created
+//! and deleted. Written files are padded to whole blocks and stored on the card when they are
+//! closed, and the card image is written back after every change.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory,
+    ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow,
+};
+
+use crate::{BAState, DirectoryFrame, ErrorKind, MCError, MemCard, Save, BLOCK_SIZE};
+
+/// How long the kernel may cache attributes and lookups.
+const TTL: Duration = Duration::from_secs(1);
+
+/// The inode of the mount point.
+const ROOT_INO: u64 = 1;
+
+/// Saves use the inode of their first directory index plus this offset.
+const SAVE_INO: u64 = 2;
+
+/// Files that were created but not stored on the card yet use inodes from here on.
+const PENDING_INO: u64 = 0x100;
+
+/// A file that has been written to, but not stored on the card yet.
+struct Pending {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// CardFs
+///
+/// A FUSE filesystem backed by a memory card image. Created with `CardFs::open` and mounted with
+/// `CardFs::mount`, which blocks until the filesystem is unmounted.
+pub struct CardFs {
+    path: PathBuf,
+    card: MemCard,
+    pending: HashMap<u64, Pending>,
+    next_ino: u64,
+}
+
+impl CardFs {
+    /// Open the card image at `filename`.
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        let path = filename.as_ref().to_path_buf();
+        let card = MemCard::open(&path)?;
+
+        Ok(CardFs {
+            path,
+            card,
+            pending: HashMap::new(),
+            next_ino: PENDING_INO,
+        })
+    }
+
+    /// Mount the card at `mountpoint`, and serve requests until it is unmounted.
+    pub fn mount<P: AsRef<Path>>(self, mountpoint: P) -> Result<(), MCError> {
+        let options = [
+            MountOption::FSName("psxmem".to_string()),
+            MountOption::DefaultPermissions,
+        ];
+        fuser::mount2(self, mountpoint, &options)?;
+
+        Ok(())
+    }
+
+    fn save_by_ino(&self, ino: u64) -> Option<Save<'_>> {
+        let index = ino.checked_sub(SAVE_INO)? as usize;
+        self.card.saves().find(|s| s.index == index)
+    }
+
+    fn save_by_name(&self, name: &OsStr) -> Option<Save<'_>> {
+        let name = name.to_str()?;
+        self.card.saves().find(|s| s.filename() == name)
+    }
+
+    /// The contents of the save at `ino`, either pending or as stored on the card.
+    fn contents(&self, ino: u64) -> Result<Vec<u8>, MCError> {
+        if let Some(p) = self.pending.get(&ino) {
+            return Ok(p.data.clone());
+        }
+
+        let save = self
+            .save_by_ino(ino)
+            .ok_or_else(|| MCError::NotFound(format!("no file with inode {}", ino)))?;
+        let mut out = vec![0u8; save.data.len() * BLOCK_SIZE];
+        for (d, b) in save.data.iter().zip(out.as_chunks_mut::<BLOCK_SIZE>().0) {
+            d.write_raw(b)?;
+        }
+
+        Ok(out)
+    }
+
+    /// Get the pending contents of `ino`, starting from what is on the card.
+    fn pending_mut(&mut self, ino: u64) -> Result<&mut Pending, MCError> {
+        if !self.pending.contains_key(&ino) {
+            let data = self.contents(ino)?;
+            let name = self
+                .save_by_ino(ino)
+                .map(|s| s.filename())
+                .unwrap_or_default();
+            self.pending.insert(ino, Pending { name, data });
+        }
+
+        Ok(self.pending.get_mut(&ino).expect("inserted above"))
+    }
+
+    /// Store the pending contents of `ino` on the card, replacing any save with the same name,
+    /// and write the card image back.
+    fn commit(&mut self, ino: u64) -> Result<(), MCError> {
+        let Some(p) = self.pending.remove(&ino) else {
+            return Ok(());
+        };
+        if p.data.is_empty() {
+            return Ok(());
+        }
+
+        let blocks = p.data.len().div_ceil(BLOCK_SIZE);
+        let dir = DirectoryFrame::builder()
+            .state(BAState::AllocFirst)
+            .filesize((blocks * BLOCK_SIZE) as u32)
+            .filename(&p.name)
+            .build()?;
+        let mut mcs = dir.to_raw().to_vec();
+        mcs.extend_from_slice(&p.data);
+        mcs.resize(mcs.len() + blocks * BLOCK_SIZE - p.data.len(), 0);
+
+        // Only replace the old save once the new one is known to fit
+        let mut card = self.card.clone();
+        let old = card
+            .saves()
+            .find(|s| s.filename() == p.name)
+            .map(|s| s.index);
+        if let Some(index) = old {
+            card.delete_save(index)?;
+        }
+        card.import_mcs(&mcs)?;
+
+        card.write(&self.path)?;
+        self.card = card;
+
+        Ok(())
+    }
+
+    fn attr(&self, ino: u64) -> Option<FileAttr> {
+        if ino == ROOT_INO {
+            return Some(file_attr(ino, 0, FileType::Directory));
+        }
+
+        let size = match self.pending.get(&ino) {
+            Some(p) => p.data.len(),
+            None => self.save_by_ino(ino)?.data.len() * BLOCK_SIZE,
+        };
+
+        Some(file_attr(ino, size as u64, FileType::RegularFile))
+    }
+}
+
+fn file_attr(ino: u64, size: u64, kind: FileType) -> FileAttr {
+    let (perm, nlink) = match kind {
+        FileType::Directory => (0o755, 2),
+        _ => (0o644, 1),
+    };
+
+    FileAttr {
+        ino,
+        size,
+        blocks: size.div_ceil(512),
+        atime: UNIX_EPOCH,
+        mtime: UNIX_EPOCH,
+        ctime: UNIX_EPOCH,
+        crtime: UNIX_EPOCH,
+        kind,
+        perm,
+        nlink,
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+        rdev: 0,
+        blksize: BLOCK_SIZE as u32,
+        flags: 0,
+    }
+}
+
+/// Map an `MCError` to the errno reported to the kernel.
+fn errno(e: &MCError) -> i32 {
+    match e.kind() {
+        ErrorKind::Full => libc::ENOSPC,
+        ErrorKind::Io => libc::EIO,
+        ErrorKind::NotFound => libc::ENOENT,
+        ErrorKind::ReadOnly => libc::EROFS,
+        _ => libc::EINVAL,
+    }
+}
+
+impl Filesystem for CardFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        if parent != ROOT_INO {
+            return reply.error(libc::ENOENT);
+        }
+
+        let ino = self
+            .pending
+            .iter()
+            .find(|(_, p)| OsStr::new(&p.name) == name)
+            .map(|(ino, _)| *ino)
+            .or_else(|| self.save_by_name(name).map(|s| s.index as u64 + SAVE_INO));
+        match ino.and_then(|ino| self.attr(ino)) {
+            Some(attr) => reply.entry(&TTL, &attr, 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.attr(ino) {
+            Some(attr) => reply.attr(&TTL, &attr),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        if let Some(size) = size {
+            match self.pending_mut(ino) {
+                Ok(p) => p.data.resize(size as usize, 0),
+                Err(e) => return reply.error(errno(&e)),
+            }
+        }
+
+        match self.attr(ino) {
+            Some(attr) => reply.attr(&TTL, &attr),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        if ino != ROOT_INO {
+            return reply.error(libc::ENOTDIR);
+        }
+
+        let mut entries = vec![
+            (ROOT_INO, FileType::Directory, ".".to_string()),
+            (ROOT_INO, FileType::Directory, "..".to_string()),
+        ];
+        entries.extend(self.card.saves().map(|s| {
+            (
+                s.index as u64 + SAVE_INO,
+                FileType::RegularFile,
+                s.filename(),
+            )
+        }));
+        entries.extend(
+            self.pending
+                .iter()
+                .filter(|(ino, _)| **ino >= PENDING_INO)
+                .map(|(ino, p)| (*ino, FileType::RegularFile, p.name.clone())),
+        );
+
+        for (n, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, n as i64 + 1, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
+        match self.attr(ino) {
+            Some(_) => reply.opened(0, 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self.contents(ino) {
+            Ok(data) => {
+                let start = (offset as usize).min(data.len());
+                let end = (start + size as usize).min(data.len());
+                reply.data(&data[start..end]);
+            }
+            Err(_) => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let end = offset as usize + data.len();
+        if end > BLOCK_SIZE * 15 {
+            return reply.error(libc::EFBIG);
+        }
+
+        match self.pending_mut(ino) {
+            Ok(p) => {
+                if p.data.len() < end {
+                    p.data.resize(end, 0);
+                }
+                p.data[offset as usize..end].copy_from_slice(data);
+                reply.written(data.len() as u32);
+            }
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let Some(name) = name.to_str().filter(|n| n.len() <= 20) else {
+            return reply.error(libc::EINVAL);
+        };
+        if parent != ROOT_INO {
+            return reply.error(libc::ENOENT);
+        }
+
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.pending.insert(
+            ino,
+            Pending {
+                name: name.to_string(),
+                data: Vec::new(),
+            },
+        );
+
+        reply.created(&TTL, &file_attr(ino, 0, FileType::RegularFile), 0, 0, 0);
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.commit(ino) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        if parent != ROOT_INO {
+            return reply.error(libc::ENOENT);
+        }
+
+        let Some(index) = self.save_by_name(name).map(|s| s.index) else {
+            return reply.error(libc::ENOENT);
+        };
+        let r = self
+            .card
+            .delete_save(index)
+            .and_then(|_| self.card.write(&self.path));
+        match r {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+}
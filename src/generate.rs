@@ -0,0 +1,231 @@
+//! Generation of synthetic memory card images, for tests and tooling.
+
+use deku::prelude::*;
+
+use crate::{
+    update_checksum, BrokenFrame, DirectoryFrame, Header, MCError, TitleFrame, BLOCKS_PER_CARD,
+    BLOCK_SIZE, CHAIN_END, FRAMES_PER_BLOCK, FRAME_SIZE,
+};
+
+/// The icon palette used for generated saves: a ramp of greys.
+const PALETTE: [u16; 16] = [
+    0x0000, 0x0842, 0x1084, 0x18c6, 0x2108, 0x294a, 0x318c, 0x39ce, 0x4210, 0x4a52, 0x5294, 0x5ad6,
+    0x6318, 0x6b5a, 0x739c, 0x7fff,
+];
+
+/// SaveSpec
+///
+/// Describes a single save to place on a generated memory card.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveSpec {
+    /// The title of the save. Only ASCII letters, digits and spaces can be encoded.
+    pub title: String,
+
+    /// The number of blocks the save uses.
+    pub blocks: usize,
+
+    /// The number of icon frames, 0 to 3.
+    pub icon_frames: u8,
+
+    /// The directory filename. If `None`, a filename is generated from the save index.
+    pub filename: Option<String>,
+}
+
+impl SaveSpec {
+    /// Create a `SaveSpec` with a single icon frame and a generated filename.
+    pub fn new(title: &str, blocks: usize) -> Self {
+        SaveSpec {
+            title: title.to_string(),
+            blocks,
+            icon_frames: 1,
+            filename: None,
+        }
+    }
+}
+
+/// Defect
+///
+/// A deliberate defect to introduce into a generated memory card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Defect {
+    /// Corrupt the checksum of frame `frame` in card block `block`.
+    BadChecksum { block: usize, frame: usize },
+
+    /// Point the first block of save `save` at a directory index that does not exist.
+    BrokenChain { save: usize },
+
+    /// Point the last block of save `save` back at its first block, and mark it as a middle
+    /// block so the chain never ends.
+    ChainCycle { save: usize },
+}
+
+/// CardGenerator
+///
+/// Builds raw memory card images that contain a configurable set of saves and defects.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CardGenerator {
+    saves: Vec<SaveSpec>,
+    defects: Vec<Defect>,
+}
+
+impl CardGenerator {
+    /// Create a generator for an empty memory card.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a save to the memory card. Saves are laid out in order, in contiguous blocks.
+    pub fn save(mut self, spec: SaveSpec) -> Self {
+        self.saves.push(spec);
+        self
+    }
+
+    /// Add a defect to the memory card.
+    pub fn defect(mut self, defect: Defect) -> Self {
+        self.defects.push(defect);
+        self
+    }
+
+    /// Generate the raw 128KB memory card image. Returns `MCError::CardFull` if the saves do not
+    /// fit in the 15 data blocks, or `MCError::InvalidValue` if a defect names a save, block or
+    /// frame that does not exist.
+    pub fn generate(&self) -> Result<Vec<u8>, MCError> {
+        let used: usize = self.saves.iter().map(|s| s.blocks).sum();
+        if used > 15 || self.saves.iter().any(|s| s.blocks == 0) {
+            return Err(MCError::CardFull);
+        }
+
+        for defect in &self.defects {
+            let exists = match *defect {
+                Defect::BadChecksum { block, frame } => {
+                    block < BLOCKS_PER_CARD && frame < FRAMES_PER_BLOCK
+                }
+                Defect::BrokenChain { save } | Defect::ChainCycle { save } => {
+                    save < self.saves.len()
+                }
+            };
+            if !exists {
+                return Err(MCError::InvalidValue(format!(
+                    "{:?} is not on a card of {} saves",
+                    defect,
+                    self.saves.len()
+                )));
+            }
+        }
+
+        let mut dir = vec![DirectoryFrame::free(); 15];
+        let mut blocks = vec![[0u8; BLOCK_SIZE]; 15];
+        let mut starts = Vec::<usize>::new();
+
+        let mut next = 0;
+        for (n, spec) in self.saves.iter().enumerate() {
+            starts.push(next);
+            for i in 0..spec.blocks {
+                let d = &mut dir[next + i];
+                d.state = match i {
+                    0 => 0x51,
+                    _ if i == spec.blocks - 1 => 0x53,
+                    _ => 0x52,
+                };
+                d.next_block = if i == spec.blocks - 1 {
+                    CHAIN_END
+                } else {
+                    (next + i + 1) as u16
+                };
+            }
+
+            let filename = match &spec.filename {
+                Some(f) => f.clone(),
+                None => format!("BASLUS-{:05}SAVE{:02}", n, n),
+            };
+            let first = &mut dir[next];
+            first.filesize = (spec.blocks * BLOCK_SIZE) as u32;
+            for (dst, src) in first.filename.iter_mut().zip(filename.bytes().take(20)) {
+                *dst = src;
+            }
+
+            write_save(&mut blocks[next..next + spec.blocks], n, spec)?;
+            next += spec.blocks;
+        }
+
+        for defect in &self.defects {
+            match *defect {
+                Defect::BrokenChain { save } => dir[starts[save]].next_block = 0x00ff,
+                Defect::ChainCycle { save } => {
+                    let last = starts[save] + self.saves[save].blocks - 1;
+                    if last != starts[save] {
+                        dir[last].state = 0x52;
+                    }
+                    dir[last].next_block = starts[save] as u16;
+                }
+                Defect::BadChecksum { .. } => (),
+            }
+        }
+
+        let mut card = Vec::<u8>::with_capacity(BLOCK_SIZE * BLOCKS_PER_CARD);
+
+        let h = Header::new().to_bytes()?;
+        card.extend_from_slice(&h);
+
+        for d in &dir {
+            let mut f = d.to_bytes()?;
+            card.extend_from_slice(update_checksum(&mut f)?);
+        }
+
+        let broken = BrokenFrame::none().to_bytes()?;
+        for _ in 0..20 {
+            card.extend_from_slice(&broken);
+        }
+
+        // Unused frames are 0xff filled, which is also a valid checksum
+        card.resize(card.len() + 27 * FRAME_SIZE, 0xff);
+
+        // Write test frame
+        card.extend_from_slice(&h);
+
+        for b in &blocks {
+            card.extend_from_slice(b);
+        }
+
+        for defect in &self.defects {
+            if let Defect::BadChecksum { block, frame } = *defect {
+                card[block * BLOCK_SIZE + frame * FRAME_SIZE + FRAME_SIZE - 1] ^= 0xff;
+            }
+        }
+
+        Ok(card)
+    }
+}
+
+/// Fill the blocks of save number `n` with a title frame, icon frames and data.
+fn write_save(blocks: &mut [[u8; BLOCK_SIZE]], n: usize, spec: &SaveSpec) -> Result<(), MCError> {
+    let title = TitleFrame::builder()
+        .title(&spec.title)
+        .icon_frames(spec.icon_frames)
+        .block_num(spec.blocks as u8)
+        .icon_palette(PALETTE)
+        .build()?;
+    blocks[0][..FRAME_SIZE].copy_from_slice(&title.to_bytes()?);
+
+    // Each icon is a diagonal gradient, shifted by one per animation frame
+    for i in 0..spec.icon_frames as usize {
+        let icon = &mut blocks[0][FRAME_SIZE * (i + 1)..FRAME_SIZE * (i + 2)];
+        for (p, v) in icon.iter_mut().enumerate() {
+            let (x, y) = ((p % 8) * 2, p / 8);
+            let lo = (x + y + i) & 0x0f;
+            let hi = (x + 1 + y + i) & 0x0f;
+            *v = (lo | (hi << 4)) as u8;
+        }
+    }
+
+    // The remaining frames get a pattern unique to this save
+    let data_start = FRAME_SIZE * (spec.icon_frames as usize + 1);
+    for (b, block) in blocks.iter_mut().enumerate() {
+        let start = if b == 0 { data_start } else { 0 };
+        for (p, v) in block[start..].iter_mut().enumerate() {
+            *v = (n * 31 + b * 7 + p) as u8;
+        }
+    }
+
+    Ok(())
+}
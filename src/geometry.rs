@@ -0,0 +1,92 @@
+//! The physical layout of a memory card.
+
+/// The size of a `Frame` in bytes. A frame is the unit the memory card reads and writes.
+pub const FRAME_SIZE: usize = 0x80;
+
+/// The size of a `Block` in bytes. Save files are allocated in whole blocks.
+pub const BLOCK_SIZE: usize = 0x2000;
+
+/// The number of `Frame`s in a `Block`.
+pub const FRAMES_PER_BLOCK: usize = BLOCK_SIZE / FRAME_SIZE;
+
+/// The number of `Block`s on a standard 128KB memory card, including the `InfoBlock`.
+pub const BLOCKS_PER_CARD: usize = 16;
+
+/// The largest number of pages in a multi-page card image.
+const MAX_PAGES: usize = 16;
+
+/// CardGeometry
+///
+/// Describes the layout of a memory card image, and converts between (block, frame)
+/// coordinates and byte offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CardGeometry {
+    /// The number of blocks on the card, including the `InfoBlock`.
+    pub blocks: usize,
+}
+
+impl CardGeometry {
+    /// The geometry of a standard 128KB memory card.
+    pub const STANDARD: CardGeometry = CardGeometry {
+        blocks: BLOCKS_PER_CARD,
+    };
+
+    /// Get the geometry of a card image that is `size` bytes long. Besides standard 128KB cards,
+    /// this accepts the multi-page images of third-party cards, which are up to 16 standard
+    /// cards (pages) back to back. Returns `None` for any other size.
+    pub fn from_size(size: usize) -> Option<CardGeometry> {
+        let page = CardGeometry::STANDARD.size();
+        if size == 0 || !size.is_multiple_of(page) || size / page > MAX_PAGES {
+            return None;
+        }
+
+        Some(CardGeometry {
+            blocks: size / BLOCK_SIZE,
+        })
+    }
+
+    /// The number of standard 128KB cards (pages) in the image.
+    pub const fn pages(&self) -> usize {
+        self.blocks / BLOCKS_PER_CARD
+    }
+
+    /// The size of the card image in bytes.
+    pub const fn size(&self) -> usize {
+        self.blocks * BLOCK_SIZE
+    }
+
+    /// The number of blocks available for save data, over all pages.
+    pub const fn data_blocks(&self) -> usize {
+        self.blocks - self.pages()
+    }
+
+    /// The number of frames on the card.
+    pub const fn frames(&self) -> usize {
+        self.blocks * FRAMES_PER_BLOCK
+    }
+
+    /// The byte offset of frame `frame` in block `block`, or `None` if either is out of range.
+    pub fn offset(&self, block: usize, frame: usize) -> Option<usize> {
+        if block >= self.blocks || frame >= FRAMES_PER_BLOCK {
+            return None;
+        }
+
+        Some(block * BLOCK_SIZE + frame * FRAME_SIZE)
+    }
+
+    /// The (block, frame) coordinates of byte offset `offset`, or `None` if it is past the end
+    /// of the card.
+    pub fn locate(&self, offset: usize) -> Option<(usize, usize)> {
+        if offset >= self.size() {
+            return None;
+        }
+
+        Some((offset / BLOCK_SIZE, offset % BLOCK_SIZE / FRAME_SIZE))
+    }
+}
+
+impl Default for CardGeometry {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
@@ -0,0 +1,90 @@
+//! Exchanging icons with PS1 graphics tools as raw 4bpp pixel data and a 16 color CLUT, exactly
+//! as they are stored on the card.
+
+use crate::{DataBlock, MCError, MemCard, FRAME_SIZE};
+
+/// The size of an icon CLUT: 16 colors of 15-bit BGR, little endian.
+pub const CLUT_SIZE: usize = 32;
+
+impl DataBlock {
+    /// The icon palette as a raw CLUT.
+    pub fn icon_clut(&self) -> [u8; CLUT_SIZE] {
+        let mut clut = [0u8; CLUT_SIZE];
+        for (c, raw) in self
+            .title_frame
+            .icon_palette
+            .iter()
+            .zip(clut.chunks_exact_mut(2))
+        {
+            raw.copy_from_slice(&c.to_le_bytes());
+        }
+
+        clut
+    }
+
+    /// The raw 4bpp pixels of every icon frame, one after the other. Each frame is 16x16 pixels
+    /// in 128 bytes, two pixels per byte with the left pixel in the low nibble.
+    pub fn icon_pixels(&self) -> Vec<u8> {
+        self.icon_frames.iter().flat_map(|f| f.data).collect()
+    }
+
+    /// Replace the icon with raw 4bpp `pixels` and a raw `clut`, as returned by `icon_pixels` and
+    /// `icon_clut`. The icon must keep its number of frames, since the frames after it hold the
+    /// save data. Returns `MCError::InvalidValue` if the sizes do not match.
+    pub fn set_icon_raw(&mut self, pixels: &[u8], clut: &[u8]) -> Result<(), MCError> {
+        if clut.len() != CLUT_SIZE {
+            return Err(MCError::InvalidValue(format!(
+                "a CLUT of {} bytes is not {} bytes",
+                clut.len(),
+                CLUT_SIZE
+            )));
+        }
+        let frames = self.icon_frames.len();
+        if frames == 0 || pixels.len() != frames * FRAME_SIZE {
+            return Err(MCError::InvalidValue(format!(
+                "{} bytes of pixels for an icon of {} frames",
+                pixels.len(),
+                frames
+            )));
+        }
+
+        for (c, raw) in self
+            .title_frame
+            .icon_palette
+            .iter_mut()
+            .zip(clut.chunks_exact(2))
+        {
+            *c = u16::from_le_bytes([raw[0], raw[1]]);
+        }
+        for (f, raw) in self
+            .icon_frames
+            .iter_mut()
+            .zip(pixels.chunks_exact(FRAME_SIZE))
+        {
+            f.data.copy_from_slice(raw);
+        }
+
+        Ok(())
+    }
+}
+
+impl MemCard {
+    /// Replace the icon of the save that starts at directory index `index`, see
+    /// `DataBlock::set_icon_raw`.
+    pub fn set_icon_raw(
+        &mut self,
+        index: usize,
+        pixels: &[u8],
+        clut: &[u8],
+    ) -> Result<(), MCError> {
+        self.check_writable()?;
+        if !self.saves().any(|s| s.index == index) {
+            return Err(MCError::NotFound(format!(
+                "no save starts at block {}",
+                index
+            )));
+        }
+
+        self.data[index].set_icon_raw(pixels, clut)
+    }
+}
@@ -0,0 +1,135 @@
+//! Exporting the icon of a save as source code, so PS1 homebrew can embed existing icons in the
+//! title frames of its own saves.
+
+use std::io::Write;
+
+use crate::{DataBlock, MCError};
+
+/// SourceLang
+///
+/// The language of the source written by `DataBlock::write_icon_source`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceLang {
+    /// A C header with `static const` arrays.
+    C,
+
+    /// A Rust module with `pub const` arrays.
+    Rust,
+}
+
+impl DataBlock {
+    /// Write the icon palette (CLUT) and the 4bpp icon frames of the save to `out` as source in
+    /// `lang`, naming the arrays after `name`. The data is written exactly as it is stored on the
+    /// card: the palette as 15-bit BGR colors, and two pixels per byte, left pixel in the low
+    /// nibble. Returns `MCError::InvalidValue` if the save has no icon.
+    pub fn write_icon_source<W: Write>(
+        &self,
+        lang: SourceLang,
+        name: &str,
+        out: &mut W,
+    ) -> Result<(), MCError> {
+        if self.icon_frames.is_empty() {
+            return Err(MCError::InvalidValue("the save has no icon".to_string()));
+        }
+
+        let ident = identifier(name);
+        let title = self.title_frame.decode_title().unwrap_or_default();
+        let frames = self.icon_frames.len();
+        let clut = self
+            .title_frame
+            .icon_palette
+            .iter()
+            .map(|c| format!("{:#06x}", c))
+            .collect::<Vec<_>>();
+
+        match lang {
+            SourceLang::C => {
+                let guard = format!("{}_ICON_H", ident.to_ascii_uppercase());
+                writeln!(
+                    out,
+                    "/* Icon of \"{}\", 16x16 4bpp */",
+                    title.replace("*/", "* /")
+                )?;
+                writeln!(out, "#ifndef {}", guard)?;
+                writeln!(out, "#define {}", guard)?;
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "#define {}_ICON_FRAMES {}",
+                    ident.to_ascii_uppercase(),
+                    frames
+                )?;
+                writeln!(out)?;
+                writeln!(out, "static const unsigned short {}_clut[16] = {{", ident)?;
+                write_rows(out, &clut, 8, "    ")?;
+                writeln!(out, "}};")?;
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "static const unsigned char {}_icon[{}][128] = {{",
+                    ident, frames
+                )?;
+                for f in &self.icon_frames {
+                    writeln!(out, "    {{")?;
+                    write_rows(out, &hex_bytes(f), 16, "        ")?;
+                    writeln!(out, "    }},")?;
+                }
+                writeln!(out, "}};")?;
+                writeln!(out)?;
+                writeln!(out, "#endif")?;
+            }
+            SourceLang::Rust => {
+                let ident = ident.to_ascii_uppercase();
+                writeln!(out, "// Icon of {:?}, 16x16 4bpp", title)?;
+                writeln!(out)?;
+                writeln!(out, "pub const {}_CLUT: [u16; 16] = [", ident)?;
+                write_rows(out, &clut, 8, "    ")?;
+                writeln!(out, "];")?;
+                writeln!(out)?;
+                writeln!(out, "pub const {}_ICON: [[u8; 128]; {}] = [", ident, frames)?;
+                for f in &self.icon_frames {
+                    writeln!(out, "    [")?;
+                    write_rows(out, &hex_bytes(f), 16, "        ")?;
+                    writeln!(out, "    ],")?;
+                }
+                writeln!(out, "];")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hex_bytes(data: &[u8]) -> Vec<String> {
+    data.iter().map(|b| format!("{:#04x}", b)).collect()
+}
+
+/// Write `items` as comma separated rows of `per_row` items.
+fn write_rows<W: Write>(
+    out: &mut W,
+    items: &[String],
+    per_row: usize,
+    indent: &str,
+) -> Result<(), MCError> {
+    for row in items.chunks(per_row) {
+        writeln!(out, "{}{},", indent, row.join(", "))?;
+    }
+
+    Ok(())
+}
+
+/// Turn `name` into an identifier that is valid in both C and Rust.
+fn identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert(0, 'x');
+    }
+
+    ident
+}
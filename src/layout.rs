@@ -0,0 +1,63 @@
+//! The allocation of the data blocks of a memory card, for drawing the layout of a card.
+
+use crate::{BAState, DataBlock, MemCard};
+
+/// BlockInfo
+///
+/// A data block of a memory card along with its allocation, as returned by
+/// `MemCard::iter_blocks`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockInfo<'a> {
+    /// The directory index of the block.
+    pub index: usize,
+
+    /// The allocation state of the block from its directory entry.
+    pub state: BAState,
+
+    /// The directory index of the first block of the save the block belongs to, or `None` if
+    /// the block is not part of any chain. Blocks of deleted saves that were not reused yet
+    /// still belong to their save.
+    pub owner: Option<usize>,
+
+    /// The position of the block in the chain of its save, starting at 0.
+    pub position: Option<usize>,
+
+    /// The data block.
+    pub block: &'a DataBlock,
+}
+
+impl BlockInfo<'_> {
+    /// Whether the block belongs to a save that was deleted.
+    pub fn is_deleted(&self) -> bool {
+        self.owner.is_some()
+            && matches!(
+                self.state,
+                BAState::FreeFirst | BAState::FreeMid | BAState::FreeLast
+            )
+    }
+}
+
+/// Annotate every data block of `card` with its allocation.
+pub(crate) fn blocks(card: &MemCard) -> Vec<BlockInfo<'_>> {
+    let mut chains = vec![None; card.data.len()];
+
+    // Live saves are walked last, so they win over deleted saves whose blocks were reused
+    for s in card.deleted_saves().into_iter().chain(card.saves()) {
+        for (position, n) in s.blocks.iter().enumerate() {
+            chains[*n] = Some((s.index, position));
+        }
+    }
+
+    card.data
+        .iter()
+        .zip(chains)
+        .enumerate()
+        .map(|(index, (block, chain))| BlockInfo {
+            index,
+            state: card.info.dir_frames[index].get_alloc_state(),
+            owner: chain.map(|(owner, _)| owner),
+            position: chain.map(|(_, position)| position),
+            block,
+        })
+        .collect()
+}
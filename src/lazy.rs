@@ -0,0 +1,97 @@
+//! A memory card whose data blocks are only parsed when they are accessed.
+
+use std::cell::OnceCell;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{DataBlock, InfoBlock, MCError, MemCard, OpenOptions, BLOCKS_PER_CARD, BLOCK_SIZE};
+
+/// LazyMemCard
+///
+/// A memory card that parses the `InfoBlock` when it is opened, but keeps the data blocks raw
+/// until they are accessed. Listing the directory of a card therefore never decodes the icon
+/// and data frames of its saves.
+#[derive(Clone, Debug)]
+pub struct LazyMemCard {
+    /// The initial block of data on the memory card.
+    pub info: InfoBlock,
+
+    raw: Vec<u8>,
+    data: Vec<OnceCell<DataBlock>>,
+    read_only: bool,
+}
+
+impl LazyMemCard {
+    /// Open the memory card file from a filename. Only the `InfoBlock` is parsed.
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        Self::open_with(filename, OpenOptions::default())
+    }
+
+    /// Open the memory card file from a filename using the provided `OpenOptions`.
+    pub fn open_with<P: AsRef<Path>>(filename: P, opts: OpenOptions) -> Result<Self, MCError> {
+        let path = filename.as_ref();
+        let file = File::open(path)?;
+        Self::from_reader_with(file, opts)
+            .map_err(|e| e.context(format!("Unable to open {}", path.display())))
+    }
+
+    /// Read a memory card from anything that implements `Read`. Only the `InfoBlock` is parsed.
+    pub fn from_reader<R: Read>(input: R) -> Result<Self, MCError> {
+        Self::from_reader_with(input, OpenOptions::default())
+    }
+
+    /// Read a memory card from anything that implements `Read` using the provided
+    /// `OpenOptions`.
+    pub fn from_reader_with<R: Read>(input: R, opts: OpenOptions) -> Result<Self, MCError> {
+        let (info, raw) = InfoBlock::read_image(input, opts)?;
+
+        Ok(LazyMemCard {
+            info,
+            raw,
+            data: vec![OnceCell::new(); BLOCKS_PER_CARD - 1],
+            read_only: opts.read_only,
+        })
+    }
+
+    /// Get the data block at directory index `n` (0..15), parsing it on first access.
+    pub fn data_block(&self, n: usize) -> Result<&DataBlock, MCError> {
+        let cell = self
+            .data
+            .get(n)
+            .ok_or_else(|| MCError::InvalidValue(format!("block {} is out of range", n)))?;
+
+        if let Some(d) = cell.get() {
+            return Ok(d);
+        }
+
+        let start = (n + 1) * BLOCK_SIZE;
+        let d = DataBlock::parse_block(n + 1, &self.raw[start..start + BLOCK_SIZE])?;
+        Ok(cell.get_or_init(|| d))
+    }
+
+    /// Check if the data block at directory index `n` has been parsed yet.
+    pub fn is_parsed(&self, n: usize) -> bool {
+        self.data.get(n).is_some_and(|c| c.get().is_some())
+    }
+
+    /// Parse the remaining data blocks and convert into a `MemCard`.
+    pub fn into_memcard(self) -> Result<MemCard, MCError> {
+        let mut data = Vec::<DataBlock>::with_capacity(self.data.len());
+        for (n, cell) in self.data.into_iter().enumerate() {
+            let d = match cell.into_inner() {
+                Some(d) => d,
+                None => {
+                    let start = (n + 1) * BLOCK_SIZE;
+                    DataBlock::parse_block(n + 1, &self.raw[start..start + BLOCK_SIZE])?
+                }
+            };
+            data.push(d);
+        }
+
+        let mut card = MemCard::from_parts(self.info, data);
+        card.set_read_only(self.read_only);
+
+        Ok(card)
+    }
+}
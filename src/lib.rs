@@ -4,21 +4,197 @@
 //! including raw *.mcr formats that some emulators use.
 
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
-use std::{fmt, str};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, Index, IndexMut};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, iter, slice, str};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use deku::prelude::*;
 use gif::{Encoder as GifEncoder, Frame as GifFrame, Repeat};
 use png::Encoder;
 
+/// Emit a `tracing` event when the `tracing` feature is enabled, and nothing otherwise.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+#[cfg(feature = "tokio")]
+mod async_io;
+
+mod atomic;
+
+mod audit;
+use crate::audit::AuditLog;
+pub use crate::audit::{AuditAction, AuditEntry};
+
+mod backup;
+
+mod builder;
+pub use crate::builder::{DirectoryFrameBuilder, MemCardBuilder, TitleFrameBuilder};
+
+mod checksum;
+pub use crate::checksum::{FrameChecksum, XorFrame};
+
+mod codec;
+pub use crate::codec::{CodecRegistry, FieldValue, GameSaveCodec, SaveFields};
+
+mod csv;
+
+mod cursor;
+pub use crate::cursor::SaveCursor;
+
+mod debug;
+
+#[cfg(feature = "dexdrive")]
+pub mod dexdrive;
+#[cfg(feature = "dexdrive")]
+pub use crate::dexdrive::DexDrive;
+
+mod diff;
+#[cfg(feature = "digest")]
+mod digest;
+pub use crate::diff::{CardDiff, ChangedFrame, SaveChange, SaveDiff};
+
 mod errors;
-pub use crate::errors::MCError;
+pub use crate::errors::{ErrorKind, MCError};
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "fuse")]
+mod fuse;
+#[cfg(feature = "fuse")]
+pub use crate::fuse::CardFs;
+
+mod generate;
+pub use crate::generate::{CardGenerator, Defect, SaveSpec};
+
+mod geometry;
+pub use crate::geometry::{
+    CardGeometry, BLOCKS_PER_CARD, BLOCK_SIZE, FRAMES_PER_BLOCK, FRAME_SIZE,
+};
+
+mod icon_raw;
+pub use crate::icon_raw::CLUT_SIZE;
+
+mod icon_source;
+pub use crate::icon_source::SourceLang;
+
+mod layout;
+pub use crate::layout::BlockInfo;
+
+mod lazy;
+pub use crate::lazy::LazyMemCard;
+
+pub mod locator;
+
+mod markdown;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "mmap")]
+pub use crate::mmap::MappedCard;
+
+mod mcs;
+
+#[cfg(feature = "ps3mca")]
+pub mod ps3mca;
+#[cfg(feature = "ps3mca")]
+pub use crate::ps3mca::Ps3Adaptor;
+
+mod offsets;
+pub use crate::offsets::{OffsetRegion, RegionKind};
+
+mod pocketstation;
+pub use crate::pocketstation::PocketStationInfo;
+
+mod raw;
+
+mod recover;
+pub use crate::recover::{OrphanSave, RebuiltSave};
+
+mod relocate;
+pub use crate::relocate::Relocation;
 
-const BLOCK: usize = 0x2000;
-const FRAME: usize = 0x80;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "remote")]
+pub use crate::remote::RemoteCards;
 
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+mod repair;
+pub use crate::repair::{RepairAction, RepairOptions};
+
+mod save;
+use crate::save::TitleCache;
+pub use crate::save::{Save, SaveHandle, SaveMetadata};
+
+#[cfg(feature = "schema")]
+mod schema;
+#[cfg(feature = "schema")]
+pub use crate::schema::{Endian, FieldType, SaveSchema, SchemaField};
+
+#[cfg(feature = "sidecar")]
+mod sidecar;
+#[cfg(feature = "sidecar")]
+pub use crate::sidecar::{SaveNotes, Sidecar};
+
+mod search;
+pub use crate::search::{BytePattern, SearchMatch};
+
+mod sio;
+pub use crate::sio::{SioCard, SioReply, SIO_ADDRESS};
+
+mod slots;
+pub use crate::slots::CardSlots;
+
+mod stream;
+pub use crate::stream::CardStream;
+
+mod summary;
+pub use crate::summary::{CardSummary, RegionStats};
+
+pub mod sync;
+
+mod tracked;
+pub use crate::tracked::TrackedCard;
+
+#[cfg(feature = "unirom")]
+pub mod unirom;
+#[cfg(feature = "unirom")]
+pub use crate::unirom::Unirom;
+
+mod validate;
+pub use crate::validate::{ChainWalker, Finding, Severity, ValidationReport, CHAIN_END};
+
+mod view;
+pub use crate::view::{DirEntryRef, MemCardRef};
+
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "watch")]
+pub use crate::watch::{CardEvent, WatchedCard};
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use crate::wasm::{WasmCard, WasmSave};
+
+mod wipe;
+pub use crate::wipe::WipePattern;
+
+/// The magic bytes that identify the first frame of a memory card.
+pub const HEADER_MAGIC: [u8; 2] = *b"MC";
+
+/// The magic bytes that identify the title frame of a save file.
+pub const TITLE_MAGIC: [u8; 2] = *b"SC";
+
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Copy, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct Header {
     id: [u8; 2],
@@ -26,7 +202,29 @@ pub struct Header {
     checksum: u8,
 }
 
+impl Header {
+    /// Create a `Header` with the "MC" magic and a valid checksum.
+    pub fn new() -> Self {
+        Header {
+            id: HEADER_MAGIC,
+            pad: [0u8; 125],
+            checksum: calc_checksum(&HEADER_MAGIC),
+        }
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 #[repr(u32)]
 pub enum BAState {
     AllocFirst = 0x51,
@@ -36,10 +234,98 @@ pub enum BAState {
     FreeFirst = 0xa1,
     FreeMid = 0xa2,
     FreeLast = 0xa3,
+    #[cfg_attr(feature = "serde", serde(rename = "unknown"))]
     UNKNOWN,
 }
 
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+impl BAState {
+    /// Map a raw directory state to a `BAState`.
+    pub(crate) fn from_raw(state: u32) -> Self {
+        match state {
+            0x51 => BAState::AllocFirst,
+            0x52 => BAState::AllocMid,
+            0x53 => BAState::AllocLast,
+            0xa0 => BAState::Free,
+            0xa1 => BAState::FreeFirst,
+            0xa2 => BAState::FreeMid,
+            0xa3 => BAState::FreeLast,
+            _ => BAState::UNKNOWN,
+        }
+    }
+
+    /// The raw directory state, or `None` for `BAState::UNKNOWN`, which has no single value.
+    pub fn to_raw(self) -> Option<u32> {
+        (self != BAState::UNKNOWN).then_some(self as u32)
+    }
+}
+
+impl From<u32> for BAState {
+    fn from(state: u32) -> Self {
+        BAState::from_raw(state)
+    }
+}
+
+impl fmt::Display for BAState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            BAState::AllocFirst => "first block",
+            BAState::AllocMid => "middle block",
+            BAState::AllocLast => "last block",
+            BAState::Free => "free",
+            BAState::FreeFirst => "deleted first block",
+            BAState::FreeMid => "deleted middle block",
+            BAState::FreeLast => "deleted last block",
+            BAState::UNKNOWN => "unknown",
+        };
+        f.pad(s)
+    }
+}
+
+impl str::FromStr for BAState {
+    type Err = MCError;
+
+    /// Parse the `Display` text of a state, a variant name such as "AllocFirst" or
+    /// "alloc-first", or a raw value such as "0x51". Case, spaces, dashes and underscores are
+    /// ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(raw) = parse_hex(s) {
+            return match BAState::from_raw(raw) {
+                BAState::UNKNOWN => Err(MCError::InvalidValue(format!("block state {}", s))),
+                state => Ok(state),
+            };
+        }
+
+        match normalize(s).as_str() {
+            "firstblock" | "allocfirst" => Ok(BAState::AllocFirst),
+            "middleblock" | "allocmid" => Ok(BAState::AllocMid),
+            "lastblock" | "alloclast" => Ok(BAState::AllocLast),
+            "free" => Ok(BAState::Free),
+            "deletedfirstblock" | "freefirst" => Ok(BAState::FreeFirst),
+            "deletedmiddleblock" | "freemid" => Ok(BAState::FreeMid),
+            "deletedlastblock" | "freelast" => Ok(BAState::FreeLast),
+            "unknown" => Ok(BAState::UNKNOWN),
+            _ => Err(MCError::InvalidValue(format!("block state {}", s))),
+        }
+    }
+}
+
+/// Lowercase `s` and drop spaces, dashes and underscores, to compare names loosely.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Parse a hexadecimal value with a "0x" prefix.
+fn parse_hex(s: &str) -> Option<u32> {
+    let s = s.trim();
+    let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+    u32::from_str_radix(hex, 16).ok()
+}
+
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Copy, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct DirectoryFrame {
     pub state: u32,
@@ -73,36 +359,56 @@ pub struct RegionInfo {
 }
 
 impl DirectoryFrame {
-    fn load(input: &[u8], n: usize) -> Result<Vec<Self>, MCError> {
-        let mut frame = Vec::<Self>::new();
-        validate_checksum(input)?;
-        let (mut next, mut df) = Self::from_bytes((input, 0))?;
-        frame.push(df);
-        loop {
-            if frame.len() == n {
-                break;
-            }
-            let (input, _) = next;
-            validate_checksum(input)?;
-            (next, df) = Self::from_bytes(next)?;
-            frame.push(df);
-        }
-        Ok(frame)
+    /// Create a `DirectoryFrame` for a free block, with a valid checksum.
+    pub fn free() -> Self {
+        let state = BAState::Free as u32;
+        let mut d = DirectoryFrame {
+            state,
+            filesize: 0,
+            next_block: CHAIN_END,
+            filename: [0u8; 21],
+            pad: [0u8; 96],
+            checksum: 0,
+        };
+        d.checksum = calc_checksum(&state.to_le_bytes()) ^ calc_checksum(&CHAIN_END.to_le_bytes());
+        d
     }
 
-    fn get_alloc_state(&self) -> BAState {
-        match self.state {
-            0x51 => BAState::AllocFirst,
-            0x52 => BAState::AllocMid,
-            0x53 => BAState::AllocLast,
-            0xa0 => BAState::Free,
-            0xa1 => BAState::FreeFirst,
-            0xa2 => BAState::FreeMid,
-            0xa3 => BAState::FreeLast,
-            _ => BAState::UNKNOWN,
+    /// Create a `DirectoryFrameBuilder` to construct a new `DirectoryFrame`.
+    pub fn builder() -> DirectoryFrameBuilder {
+        DirectoryFrameBuilder::default()
+    }
+
+    /// Set the allocation state. Setting `BAState::UNKNOWN` leaves the raw `state` value
+    /// untouched, so nonstandard values read from a card are written back unchanged.
+    pub fn set_alloc_state(&mut self, s: BAState) {
+        if s != BAState::UNKNOWN {
+            self.state = s as u32;
         }
     }
 
+    /// Get the allocation state. Nonstandard values are reported as `BAState::UNKNOWN`, while
+    /// the original value is still kept in `state`.
+    pub fn get_alloc_state(&self) -> BAState {
+        BAState::from_raw(self.state)
+    }
+
+    /// Get the whole filename, such as "BASLUS-00123SAVE01". Filename bytes that are not valid
+    /// UTF-8 are replaced.
+    pub fn get_filename(&self) -> String {
+        String::from_utf8_lossy(&self.filename)
+            .trim_end_matches('\0')
+            .to_string()
+    }
+
+    /// Get the product code from the filename, such as "SLUS-00123". Filename bytes that are
+    /// not valid UTF-8 are replaced.
+    pub fn get_serial(&self) -> String {
+        String::from_utf8_lossy(&self.filename[2..12])
+            .trim_end_matches('\0')
+            .to_string()
+    }
+
     fn get_region_info(&self) -> Result<RegionInfo, MCError> {
         let region = match self.filename[1] {
             b'I' => Region::Japan,
@@ -131,8 +437,9 @@ impl fmt::Display for DirectoryFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "\n State: {:?}\n Filesize: {}\n Next block: {}\n Region Info: {:?}\n Checksum: {}",
+            "\n State: {} ({:#04x})\n Filesize: {}\n Next block: {}\n Region Info: {:?}\n Checksum: {}",
             self.get_alloc_state(),
+            self.state,
             self.filesize,
             self.next_block,
             self.get_region_info(),
@@ -141,7 +448,7 @@ impl fmt::Display for DirectoryFrame {
     }
 }
 
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+#[derive(Clone, Copy, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct BrokenFrame {
     broken_frame: u32,
@@ -150,21 +457,14 @@ pub struct BrokenFrame {
 }
 
 impl BrokenFrame {
-    fn load(input: &[u8], n: usize) -> Result<Vec<Self>, MCError> {
-        let mut frame = Vec::<Self>::new();
-        validate_checksum(input)?;
-        let (mut next, mut df) = Self::from_bytes((input, 0))?;
-        frame.push(df);
-        loop {
-            if frame.len() == n {
-                break;
-            }
-            let (input, _) = next;
-            validate_checksum(input)?;
-            (next, df) = Self::from_bytes(next)?;
-            frame.push(df);
+    /// Create a `BrokenFrame` entry that does not mark any frame as broken.
+    pub fn none() -> Self {
+        let broken_frame = 0xffffffff_u32;
+        BrokenFrame {
+            broken_frame,
+            pad: [0u8; 123],
+            checksum: calc_checksum(&broken_frame.to_le_bytes()),
         }
-        Ok(frame)
     }
 }
 
@@ -172,31 +472,79 @@ impl BrokenFrame {
 ///
 /// A `Frame` is 128 bytes of data. Typically the final byte of data is a checksum, but several
 /// `Frame` types do not follow that convention.
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+#[derive(Clone, Copy, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct Frame {
     /// The data contained in the `Frame`.
-    pub data: [u8; FRAME],
+    pub data: [u8; FRAME_SIZE],
 }
 
 impl Frame {
+    /// Create a `Frame` filled with zeros, which also has a valid checksum.
+    pub fn zeroed() -> Self {
+        Frame {
+            data: [0u8; FRAME_SIZE],
+        }
+    }
+
+    /// Copy exactly `FRAME_SIZE` bytes into a `Frame`. Returns `MCError::InvalidValue` if `s`
+    /// has any other length.
+    pub fn try_from_slice(s: &[u8]) -> Result<Self, MCError> {
+        let data = s.try_into().map_err(|_| {
+            MCError::InvalidValue(format!(
+                "{} bytes is not a {} byte frame",
+                s.len(),
+                FRAME_SIZE
+            ))
+        })?;
+
+        Ok(Frame { data })
+    }
+
     /// `load` will read in `n` x `Frame`s worth of data and return a `Result` of a `Vec<Frame>`
     /// and will also validate the checksum of the frames.
     pub fn load(input: &[u8], n: usize) -> Result<Vec<Self>, MCError> {
-        let mut frame = Vec::<Self>::new();
-        validate_checksum(input)?;
-        let (mut next, mut df) = Self::from_bytes((input, 0))?;
-        frame.push(df);
-        loop {
-            if frame.len() == n {
-                break;
-            }
-            let (input, _) = next;
-            validate_checksum(input)?;
-            (next, df) = Self::from_bytes(next)?;
-            frame.push(df);
+        Self::check_len(input, n)?;
+
+        let mut frames = Vec::<Self>::with_capacity(n);
+        for c in input.chunks_exact(FRAME_SIZE).take(n) {
+            validate_checksum(c)?;
+            frames.push(Self::from_chunk(c));
+        }
+
+        Ok(frames)
+    }
+
+    /// `load_unchecked` will read in `n` x `Frame`s worth of data and return a `Result` of a
+    /// `Vec<Frame>` without validating the checksum of the frames.
+    pub fn load_unchecked(input: &[u8], n: usize) -> Result<Vec<Self>, MCError> {
+        Self::check_len(input, n)?;
+
+        Ok(input
+            .chunks_exact(FRAME_SIZE)
+            .take(n)
+            .map(Self::from_chunk)
+            .collect())
+    }
+
+    /// Check that `input` holds at least `n` frames.
+    fn check_len(input: &[u8], n: usize) -> Result<(), MCError> {
+        if input.len() < n * FRAME_SIZE {
+            return Err(MCError::InvalidValue(format!(
+                "{} bytes is too short for {} frames",
+                input.len(),
+                n
+            )));
         }
-        Ok(frame)
+
+        Ok(())
+    }
+
+    /// Copy a `FRAME_SIZE` chunk of raw data into a `Frame`.
+    fn from_chunk(c: &[u8]) -> Self {
+        let mut f = Frame::zeroed();
+        f.data.copy_from_slice(c);
+        f
     }
 
     pub fn print_strings(f: &Frame) {
@@ -265,131 +613,253 @@ impl Frame {
 
 /// Block
 ///
-/// A `Block` is 8KB of data, or 64 `Frame`s.
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+/// A `Block` is 8KB of data, or 64 `Frame`s. It is deliberately not `Copy`, so blocks are
+/// passed by reference rather than implicitly copied.
+#[derive(Clone, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct Block {
     /// The data contained in the `Block`.
-    pub data: [u8; BLOCK],
+    pub data: [u8; BLOCK_SIZE],
+}
+
+impl Block {
+    /// Create a `Block` filled with zeros.
+    pub fn zeroed() -> Self {
+        Block {
+            data: [0u8; BLOCK_SIZE],
+        }
+    }
+
+    /// Copy exactly `BLOCK_SIZE` bytes into a `Block`. Returns `MCError::InvalidValue` if `s`
+    /// has any other length.
+    pub fn try_from_slice(s: &[u8]) -> Result<Self, MCError> {
+        let data = s.try_into().map_err(|_| {
+            MCError::InvalidValue(format!(
+                "{} bytes is not a {} byte block",
+                s.len(),
+                BLOCK_SIZE
+            ))
+        })?;
+
+        Ok(Block { data })
+    }
+}
+
+impl AsRef<[u8]> for Frame {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Deref for Frame {
+    type Target = [u8];
+
+    /// The raw bytes of the `Frame`.
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for Block {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Deref for Block {
+    type Target = [u8];
+
+    /// The raw bytes of the `Block`.
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<'a> IntoIterator for &'a Block {
+    type Item = Frame;
+    type IntoIter = iter::Map<slice::ChunksExact<'a, u8>, fn(&[u8]) -> Frame>;
+
+    /// Iterate over the 64 `Frame`s of the `Block`.
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.chunks_exact(FRAME_SIZE).map(Frame::from_chunk)
+    }
 }
 
 /// DataBlock
 ///
 /// A `DataBlock` is a `Block` that is a game save block.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
 pub struct DataBlock {
     /// The frame that contains the Title information.
     pub title_frame: TitleFrame,
     /// The frame(s) that contain the Icon information. This is the static or animated
     /// image that is displayed when viewing the memory card management. There can be
     /// 1 to 3 frames per save file.
+    #[deku(count = "title_frame.icon_frame_count()")]
     pub icon_frames: Vec<Frame>,
 
-    /// The actual save data is stored here.
+    /// The 32x32 monochrome icon frames of a PocketStation save, shown on the PocketStation
+    /// screen. Empty for every other block.
+    #[deku(count = "title_frame.pocketstation_frame_count()")]
+    pub pocket_frames: Vec<Frame>,
+
+    /// The actual save data is stored here. For a PocketStation save this starts with the
+    /// executable.
+    #[deku(count = "FRAMES_PER_BLOCK - 1 - icon_frames.len() - pocket_frames.len()")]
     pub data_frames: Vec<Frame>,
 }
 
 impl DataBlock {
+    /// Create an empty `DataBlock`, as found in the free blocks of a freshly formatted card.
+    pub fn zeroed() -> Self {
+        DataBlock {
+            title_frame: TitleFrame {
+                id: [0u8; 2],
+                display: 0,
+                block_num: 0,
+                title: [0u8; 64],
+                reserved: [0u8; 28],
+                icon_palette: [0u16; 16],
+            },
+            icon_frames: Vec::new(),
+            pocket_frames: Vec::new(),
+            data_frames: vec![Frame::zeroed(); FRAMES_PER_BLOCK - 1],
+        }
+    }
+
+    /// Create the first block of a save from its `title_frame`, the `icon_frames` the title frame
+    /// calls for, and the `payload`, which is padded with zeros to fill the block. Returns
+    /// `MCError::InvalidValue` if the number of icon frames does not match the title frame, or
+    /// the payload does not fit in the block.
+    pub fn new(
+        title_frame: TitleFrame,
+        icon_frames: &[Frame],
+        payload: &[u8],
+    ) -> Result<Self, MCError> {
+        title_frame.validate()?;
+        if icon_frames.len() != title_frame.icon_frame_count() {
+            return Err(MCError::InvalidValue(format!(
+                "{} icon frames for a title frame of {}",
+                icon_frames.len(),
+                title_frame.icon_frame_count()
+            )));
+        }
+
+        let start = (1 + icon_frames.len()) * FRAME_SIZE;
+        if payload.len() > BLOCK_SIZE - start {
+            return Err(MCError::InvalidValue(format!(
+                "payload of {} bytes does not fit in the {} bytes after the icon",
+                payload.len(),
+                BLOCK_SIZE - start
+            )));
+        }
+
+        let mut b = [0u8; BLOCK_SIZE];
+        b[..FRAME_SIZE].copy_from_slice(&title_frame.to_raw());
+        for (f, raw) in icon_frames
+            .iter()
+            .zip(b[FRAME_SIZE..].chunks_exact_mut(FRAME_SIZE))
+        {
+            raw.copy_from_slice(f);
+        }
+        b[start..start + payload.len()].copy_from_slice(payload);
+
+        Ok(Self::from_raw(&b))
+    }
+
     /// Parse a raw `Block` into a `DataBlock`.
-    pub fn load_data_block(b: Block) -> Result<Self, MCError> {
-        // Read title frame
-        let (_, title_frame) = TitleFrame::from_bytes((&b.data, 0))?;
-
-        // Read icon frame(s)
-        let num_frames = title_frame.display as usize & 0x03;
-        let icon_frames = DataBlock::read_n_frames(&b.data[FRAME..], num_frames)?;
-
-        // Read data frame
-        // title_frame len + (icon_frame len * num icon_frames)
-        let next = FRAME + (FRAME * icon_frames.len());
-        let num_frames = b.data[next..].len() / FRAME;
-        let data_frames = DataBlock::read_n_frames(&b.data[next..], num_frames)?;
-
-        Ok(DataBlock {
-            title_frame,
-            icon_frames,
-            data_frames,
-        })
+    pub fn load_data_block(b: &Block) -> Result<Self, MCError> {
+        let (_, d) = Self::from_bytes((&b.data, 0))?;
+
+        Ok(d)
+    }
+
+    /// Parse the raw bytes of card block `n` into a `DataBlock`, adding the block number to
+    /// any error.
+    pub(crate) fn parse_block(n: usize, b: &[u8]) -> Result<Self, MCError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("parse_block", block = n).entered();
+
+        let b = b.first_chunk::<BLOCK_SIZE>().ok_or_else(|| {
+            MCError::InvalidValue(format!("block {} is only {} bytes", n, b.len()))
+        })?;
+        let d = Self::from_raw(b);
+        trace_event!(trace, icon_frames = d.icon_frames.len(), "parsed block");
+
+        Ok(d)
     }
 
     /// Parse all `Block`s into `DataBlock`s.
     pub fn load_all_data_blocks(v: &[Block]) -> Result<Vec<Self>, MCError> {
-        let mut out = Vec::<Self>::new();
+        let mut out = Vec::<Self>::with_capacity(v.len());
         for i in v {
-            out.push(Self::load_data_block(*i)?);
+            out.push(Self::load_data_block(i)?);
         }
 
         Ok(out)
     }
 
-    fn read_n_frames(input: &[u8], num_frames: usize) -> Result<Vec<Frame>, MCError> {
-        let mut frame = Vec::<Frame>::new();
-        let (mut next, mut f) = Frame::from_bytes((input, 0))?;
-        frame.push(f);
-        loop {
-            if frame.len() == num_frames {
-                break;
-            }
-            (next, f) = Frame::from_bytes(next)?;
-            frame.push(f);
-        }
-        Ok(frame)
-    }
-
     /// Write all `DataBlock` data to `out`.
     pub fn write<T: std::io::Write>(&self, out: &mut T) -> Result<(), MCError> {
-        let t = self.title_frame.to_bytes()?;
-        out.write_all(&t)?;
-
-        for ic in &self.icon_frames {
-            let i = ic.to_bytes()?;
-            out.write_all(&i)?;
-        }
-
-        for df in &self.data_frames {
-            let d = df.to_bytes()?;
-            out.write_all(&d)?;
-        }
+        out.write_all(&self.to_bytes()?)?;
 
         Ok(())
     }
 
     /// Export all image frames to separate `.png` image files. If there are more than 1 frames,
-    /// then also export them as a combined `.gif`.
+    /// then also export them as a combined `.gif`. A save without an icon is exported as a
+    /// single placeholder frame.
     pub fn export_all_images(&self) -> Result<(), MCError> {
         // Extract out individual frames
-        for (n, i) in self.icon_frames.iter().enumerate() {
+        for n in 0..self.icon_frames.len().max(1) {
             let filename = format!("{}_frame{}.png", self.title_frame.decode_title()?, n);
             let file = File::create(filename)?;
-            let mut w = BufWriter::new(file);
-            let mut enc = Encoder::new(&mut w, 16, 16);
-            enc.set_color(png::ColorType::Rgba);
-            enc.set_depth(png::BitDepth::Eight);
-
-            let mut writer = enc.write_header()?;
-
-            let pixel_data = self.translate_bmp_to_rgba(i)?;
-
-            writer.write_image_data(&pixel_data)?;
+            self.write_icon_png(n, &mut BufWriter::new(file))?;
         }
 
         // If > 1 frame, extract it out as a gif too
         if self.icon_frames.len() > 1 {
-            self.export_gif()?;
+            let filename = format!("{}.gif", self.title_frame.decode_title()?);
+            let mut file = File::create(filename)?;
+            self.write_icon_gif(&mut file)?;
         }
 
         Ok(())
     }
 
-    fn export_gif(&self) -> Result<(), MCError> {
+    /// Encode icon frame `n` as a `.png` image and write it to `out`. Frame 0 of a save without
+    /// an icon is a placeholder.
+    pub fn write_icon_png<T: std::io::Write>(&self, n: usize, out: &mut T) -> Result<(), MCError> {
+        let pixel_data = self.frame_rgba(n)?;
+
+        let mut enc = Encoder::new(out, 16, 16);
+        enc.set_color(png::ColorType::Rgba);
+        enc.set_depth(png::BitDepth::Eight);
+
+        let mut writer = enc.write_header()?;
+
+        writer.write_image_data(&pixel_data)?;
+
+        Ok(())
+    }
+
+    /// Encode icon frame `n` as a `.png` image in memory.
+    pub fn icon_png(&self, n: usize) -> Result<Vec<u8>, MCError> {
+        let mut out = Vec::<u8>::new();
+        self.write_icon_png(n, &mut out)?;
+
+        Ok(out)
+    }
+
+    /// Encode all icon frames as an endlessly repeating animated `.gif` and write it to `out`.
+    /// A save without an icon is encoded as a single placeholder frame.
+    pub fn write_icon_gif<T: std::io::Write>(&self, out: &mut T) -> Result<(), MCError> {
         let w = 16;
         let h = 16;
-        let filename = format!("{}.gif", self.title_frame.decode_title()?);
-        let mut file = File::create(filename)?;
-        let mut enc = GifEncoder::new(&mut file, w, h, &[])?;
+        let mut enc = GifEncoder::new(out, w, h, &[])?;
         enc.set_repeat(Repeat::Infinite)?;
-        for i in self.icon_frames.iter() {
-            let mut pixels = self.translate_bmp_to_rgba(i)?;
+        for n in 0..self.icon_frames.len().max(1) {
+            let mut pixels = self.frame_rgba(n)?;
             let gifframe = GifFrame::from_rgba(w, h, &mut *pixels);
             enc.write_frame(&gifframe)?;
         }
@@ -397,6 +867,24 @@ impl DataBlock {
         Ok(())
     }
 
+    /// Decode icon frame `n` into 16x16 RGBA pixels, or `None` if the save has no such frame.
+    /// Frame 0 of a save without an icon is a placeholder.
+    pub fn icon_rgba(&self, n: usize) -> Option<Vec<u8>> {
+        self.frame_rgba(n).ok()
+    }
+
+    /// Decode icon frame `n`, or the placeholder if `n` is 0 and the save has no icon.
+    fn frame_rgba(&self, n: usize) -> Result<Vec<u8>, MCError> {
+        if self.icon_frames.is_empty() && n == 0 {
+            return Ok(placeholder_icon());
+        }
+
+        let f = self.icon_frames.get(n).ok_or_else(|| {
+            MCError::InvalidValue(format!("icon frame {} of {}", n, self.icon_frames.len()))
+        })?;
+        self.translate_bmp_to_rgba(f)
+    }
+
     fn translate_bmp_to_rgba(&self, f: &Frame) -> Result<Vec<u8>, MCError> {
         let mut rgba = Vec::<u8>::new();
 
@@ -422,19 +910,147 @@ impl DataBlock {
     }
 }
 
+/// The 16x16 RGBA image shown for saves without an icon: a gray box with a cross through it, on
+/// a transparent background.
+fn placeholder_icon() -> Vec<u8> {
+    let mut rgba = Vec::<u8>::with_capacity(16 * 16 * 4);
+    for y in 0..16 {
+        for x in 0..16 {
+            let edge = x == 0 || y == 0 || x == 15 || y == 15;
+            let cross = x == y || x + y == 15;
+            match edge || cross {
+                true => rgba.extend_from_slice(&[0x80, 0x80, 0x80, 0xff]),
+                false => rgba.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+
+    rgba
+}
+
+impl<'a> IntoIterator for &'a DataBlock {
+    type Item = &'a Frame;
+    type IntoIter = iter::Chain<
+        iter::Chain<slice::Iter<'a, Frame>, slice::Iter<'a, Frame>>,
+        slice::Iter<'a, Frame>,
+    >;
+
+    /// Iterate over the icon `Frame`s, the PocketStation icon `Frame`s and the data `Frame`s of
+    /// the `DataBlock`.
+    fn into_iter(self) -> Self::IntoIter {
+        self.icon_frames
+            .iter()
+            .chain(self.pocket_frames.iter())
+            .chain(self.data_frames.iter())
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum IconDisplay {
+    /// The save has no icon, as set by some homebrew. The title frame is followed directly by
+    /// the save data.
+    NoIcon,
     OneFrame,
     TwoFrames,
     ThreeFrames,
-    UNKNOWNFrames,
+
+    /// A display flag other than 0x00 and 0x11 to 0x13, as used by a few games, such as 0x14 to
+    /// 0x17 or values with high bits set. Only the low nibble is used: 0 is no icon, 1 to 3 is
+    /// that many frames, and anything larger is 3 frames, the most the BIOS animates.
+    Nonstandard {
+        display: u8,
+        frames: usize,
+    },
+}
+
+impl IconDisplay {
+    /// The raw display flag of the `TitleFrame`.
+    pub fn to_raw(self) -> u8 {
+        match self {
+            IconDisplay::NoIcon => 0x00,
+            IconDisplay::OneFrame => 0x11,
+            IconDisplay::TwoFrames => 0x12,
+            IconDisplay::ThreeFrames => 0x13,
+            IconDisplay::Nonstandard { display, .. } => display,
+        }
+    }
+
+    /// The number of icon frames that follow the title frame, 0 to 3.
+    pub fn frames(self) -> usize {
+        match self {
+            IconDisplay::NoIcon => 0,
+            IconDisplay::OneFrame => 1,
+            IconDisplay::TwoFrames => 2,
+            IconDisplay::ThreeFrames => 3,
+            IconDisplay::Nonstandard { frames, .. } => frames,
+        }
+    }
+
+    /// Whether the display flag is one the BIOS does not document, and the frame count is a
+    /// best guess.
+    pub fn is_nonstandard(self) -> bool {
+        matches!(self, IconDisplay::Nonstandard { .. })
+    }
+}
+
+impl From<u8> for IconDisplay {
+    fn from(display: u8) -> Self {
+        match display {
+            0x00 => IconDisplay::NoIcon,
+            0x11 => IconDisplay::OneFrame,
+            0x12 => IconDisplay::TwoFrames,
+            0x13 => IconDisplay::ThreeFrames,
+            _ => IconDisplay::Nonstandard {
+                display,
+                frames: usize::from(display & 0x0f).min(3),
+            },
+        }
+    }
+}
+
+impl fmt::Display for IconDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            IconDisplay::NoIcon => "no icon".to_string(),
+            IconDisplay::OneFrame => "1 frame".to_string(),
+            IconDisplay::TwoFrames => "2 frames".to_string(),
+            IconDisplay::ThreeFrames => "3 frames".to_string(),
+            IconDisplay::Nonstandard { display, frames } => {
+                format!("{} frames (nonstandard {:#04x})", frames, display)
+            }
+        };
+        f.pad(&s)
+    }
+}
+
+impl str::FromStr for IconDisplay {
+    type Err = MCError;
+
+    /// Parse the `Display` text of a standard icon display, a frame count such as "2", a variant
+    /// name such as "TwoFrames", or a raw value such as "0x12", which may be nonstandard. Case,
+    /// spaces, dashes and underscores are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let display = match parse_hex(s) {
+            Some(raw) => u8::try_from(raw).map(IconDisplay::from).ok(),
+            None => match normalize(s).as_str() {
+                "0" | "0frames" | "none" | "noicon" => Some(IconDisplay::NoIcon),
+                "1" | "1frame" | "1frames" | "oneframe" => Some(IconDisplay::OneFrame),
+                "2" | "2frames" | "twoframes" => Some(IconDisplay::TwoFrames),
+                "3" | "3frames" | "threeframes" => Some(IconDisplay::ThreeFrames),
+                _ => None,
+            },
+        };
+
+        display.ok_or_else(|| MCError::InvalidValue(format!("icon display {}", s)))
+    }
 }
 
 /// TitleFrame
 ///
 /// The `TitleFrame` contains the Title of the game save file, as well as other info on
 /// how many frames are in the image, as well as block number and the icon palette.
-#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Copy, DekuRead, DekuWrite, PartialEq, Eq)]
 #[deku(endian = "little")]
 pub struct TitleFrame {
     pub id: [u8; 2],
@@ -446,6 +1062,21 @@ pub struct TitleFrame {
 }
 
 impl TitleFrame {
+    /// Create a `TitleFrameBuilder` to construct a new `TitleFrame`.
+    pub fn builder() -> TitleFrameBuilder {
+        TitleFrameBuilder::default()
+    }
+
+    /// Check that the frame starts with the "SC" magic. Returns `MCError::NotASave` otherwise,
+    /// as the rest of the frame is then not a title.
+    pub fn validate(&self) -> Result<(), MCError> {
+        if self.id != TITLE_MAGIC {
+            return Err(MCError::NotASave);
+        }
+
+        Ok(())
+    }
+
     /// Decode the Title from Shift-JIS into ASCII
     pub fn decode_title(self) -> Result<String, MCError> {
         // Shift JIS decode the Title
@@ -483,13 +1114,35 @@ impl TitleFrame {
         Ok(s)
     }
 
-    fn get_icon_display(&self) -> IconDisplay {
-        match self.display {
-            0x11 => IconDisplay::OneFrame,
-            0x12 => IconDisplay::TwoFrames,
-            0x13 => IconDisplay::ThreeFrames,
-            _ => IconDisplay::UNKNOWNFrames,
+    /// Encode an ASCII title into Shift-JIS. Only the characters that `decode_title` can
+    /// translate back are kept, anything else is encoded as a space. Titles longer than 32
+    /// characters are truncated.
+    pub(crate) fn encode_title(title: &str) -> [u8; 64] {
+        let mut out = [0u8; 64];
+
+        for (n, c) in title.bytes().take(out.len() / 2).enumerate() {
+            let sjis = match c {
+                b'0'..=b'9' | b'A'..=b'Z' => [0x82, c + 0x1f],
+                b'a'..=b'z' => [0x82, c + 0x20],
+                _ => [0x81, 0x40],
+            };
+            out[n * 2..n * 2 + 2].copy_from_slice(&sjis);
         }
+
+        out
+    }
+
+    /// Get the icon display flag. Check `IconDisplay::is_nonstandard` to find the saves whose
+    /// frame count is a guess.
+    pub fn get_icon_display(&self) -> IconDisplay {
+        IconDisplay::from(self.display)
+    }
+
+    /// The number of icon frames that follow the title frame, 0 to 3. A display flag of 0x00
+    /// means the save has no icon, and its data starts right after the title frame. See
+    /// `IconDisplay::Nonstandard` for how other nonstandard flags are counted.
+    pub fn icon_frame_count(&self) -> usize {
+        self.get_icon_display().frames()
     }
 }
 
@@ -501,7 +1154,7 @@ impl fmt::Display for TitleFrame {
         };
         write!(
             f,
-            "\n Filename: {}\n Icon: {:?}\n Block Number: {}",
+            "\n Filename: {}\n Icon: {}\n Block Number: {}",
             name,
             self.get_icon_display(),
             self.block_num
@@ -513,142 +1166,559 @@ impl fmt::Display for TitleFrame {
 ///
 /// The `InfoBlock` is the first block in the memory card and contains the directory info
 /// for the locations of all the data / save file blocks, as well as any broken frame info.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, DekuRead, DekuWrite, PartialEq, Eq)]
 pub struct InfoBlock {
     /// The header info that identifies this as PSX/PS1 memory card data.
     pub header: Header,
 
     /// The directory `Frame`s that detail the save file info and `Block` locations. There are
     /// 15 `dir_frames`.
+    #[deku(count = "15")]
     pub dir_frames: Vec<DirectoryFrame>,
 
     /// The broken frames identify bad `Frame`s in the memory card. There are 20 `broken_frames`.
+    #[deku(count = "20")]
     pub broken_frames: Vec<BrokenFrame>,
 
+    #[deku(count = "27")]
     unused_frames: Vec<Frame>,
     wr_test_frame: Header,
 }
 
+/// OpenOptions
+///
+/// Options that control how strictly a memory card is parsed when it is opened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Fail to open the memory card if any of the unused frames in the `InfoBlock` has a bad
+    /// checksum. Real cards often have 0xFF fill or garbage in this region, so by default bad
+    /// checksums there are only reported by `InfoBlock::validate_unused_frames`.
+    pub strict_unused_frames: bool,
+
+    /// Skip checksum validation of the `InfoBlock` entirely, for fast read-only browsing. The
+    /// header magic is still checked. Use `InfoBlock::validate_checksums` to check the
+    /// checksums later.
+    pub skip_checksums: bool,
+
+    /// Open the memory card read-only, for archival masters that must not be changed. Every
+    /// method that modifies the card, or writes it to a file, returns `MCError::ReadOnly`. The
+    /// public fields can still be changed directly. See `MemCard::set_read_only`.
+    pub read_only: bool,
+}
+
+/// WriteOptions
+///
+/// Options that control how a memory card is written to a file. Files are always replaced
+/// atomically, by writing a temporary file next to them and renaming it over the original.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Flush the new file and its directory to disk before returning, so that the write also
+    /// survives a power loss. This is slower, especially on flash media.
+    pub sync: bool,
+
+    /// Before overwriting an existing file, copy it to a timestamped backup next to it, such as
+    /// "card.mcr.20240131-235959.123.bak", keeping this many of the newest backups. The default
+    /// of 0 makes no backups.
+    pub backups: usize,
+}
+
 impl InfoBlock {
-    /// Open and parse the first block of the memory card.
-    pub fn open(b: Block) -> Result<Self, MCError> {
-        // Validate and load header
-        validate_checksum(&b.data)?;
-        let (_, header) = Header::from_bytes((&b.data, 0))?;
-
-        // Read directory frames
-        let dir_frames = DirectoryFrame::load(&b.data[FRAME..], 15)?;
-
-        // Read broken frames
-        let mut offset = (dir_frames.len() * FRAME) + FRAME;
-        let broken_frames = BrokenFrame::load(&b.data[offset..], 20)?;
-
-        offset += broken_frames.len() * FRAME;
-        let unused_frames = Frame::load(&b.data[offset..], 27)?;
-
-        offset += unused_frames.len() * FRAME;
-        validate_checksum(&b.data[offset..])?;
-        let (_, wr_test_frame) = Header::from_bytes((&b.data[offset..], 0))?;
-
-        Ok(InfoBlock {
-            header,
-            dir_frames,
-            broken_frames,
-            unused_frames,
-            wr_test_frame,
-        })
+    /// Create the `InfoBlock` of a freshly formatted memory card: every directory entry is free
+    /// and no frames are marked as broken.
+    pub fn new() -> Self {
+        InfoBlock {
+            header: Header::new(),
+            dir_frames: vec![DirectoryFrame::free(); 15],
+            broken_frames: vec![BrokenFrame::none(); 20],
+            unused_frames: vec![Frame::zeroed(); 27],
+            wr_test_frame: Header::new(),
+        }
     }
 
-    /// Write the contents of the `InfoBlock` to `out`.
-    pub fn write<T: std::io::Write>(&self, out: &mut T) -> Result<(), MCError> {
-        let mut h = self.header.to_bytes()?;
-        out.write_all(update_checksum(&mut h)?)?;
+    /// Open and parse the first block of the memory card. Returns `MCError::NotAMemoryCard` if
+    /// the header does not start with the "MC" magic.
+    pub fn open(b: &Block) -> Result<Self, MCError> {
+        Self::open_with(b, OpenOptions::default())
+    }
+
+    /// Open and parse the first block of the memory card using the provided `OpenOptions`.
+    pub fn open_with(b: &Block, opts: OpenOptions) -> Result<Self, MCError> {
+        Self::validate_block(&b.data, opts)?;
+        let (_, info) = Self::from_bytes((&b.data, 0))?;
+
+        Ok(info)
+    }
+
+    /// Read a whole raw card image from `input`, then validate and parse its `InfoBlock`.
+    /// Returns the `InfoBlock` along with the raw image.
+    pub(crate) fn read_image<R: Read>(
+        mut input: R,
+        opts: OpenOptions,
+    ) -> Result<(Self, Vec<u8>), MCError> {
+        let mut data = vec![0u8; BLOCK_SIZE * BLOCKS_PER_CARD];
+        input.read_exact(&mut data)?;
+
+        // Validate the Info Block before parsing the whole card
+        Self::validate_block(&data[..BLOCK_SIZE], opts)?;
+        let info = Self::from_raw(data.first_chunk().expect("image holds a whole card"));
+
+        Ok((info, data))
+    }
+
+    /// Validate the checksums and header magic of a raw `InfoBlock`, before it is parsed.
+    fn validate_block(data: &[u8], opts: OpenOptions) -> Result<(), MCError> {
+        // Check the magic first, so a file that is not a card is not reported as a corrupt one
+        if data[..2] != HEADER_MAGIC {
+            trace_event!(warn, "header magic is not \"MC\"");
+            return Err(MCError::NotAMemoryCard);
+        }
 
-        for df in &self.dir_frames {
-            let mut d = df.to_bytes()?;
-            out.write_all(update_checksum(&mut d)?)?;
+        let checked = if opts.skip_checksums { 0 } else { BLOCK_SIZE };
+        for (n, f) in data[..checked].chunks(FRAME_SIZE).enumerate() {
+            // Frames 36..63 are the unused frames
+            if !opts.strict_unused_frames && (36..63).contains(&n) {
+                continue;
+            }
+            validate_checksum(f).inspect_err(|_| {
+                trace_event!(warn, frame = n, "bad checksum in info block");
+            })?;
         }
 
-        for bf in &self.broken_frames {
-            let mut b = bf.to_bytes()?;
-            out.write_all(update_checksum(&mut b)?)?;
+        Ok(())
+    }
+
+    /// Write the contents of the `InfoBlock` to `out`. The checksum of every frame is updated
+    /// as it is written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn write<T: std::io::Write>(&self, out: &mut T) -> Result<(), MCError> {
+        let mut b = self.to_bytes()?;
+        for f in b.chunks_mut(FRAME_SIZE) {
+            update_checksum(f)?;
         }
+        out.write_all(&b)?;
+
+        Ok(())
+    }
+
+    /// The 27 unused frames that follow the broken frames. Real cards usually hold 0xFF fill
+    /// here, but some tools stash their own data in this region.
+    pub fn unused_frames(&self) -> &[Frame] {
+        &self.unused_frames
+    }
 
-        for uf in &self.unused_frames {
-            let mut f = uf.to_bytes()?;
-            out.write_all(update_checksum(&mut f)?)?;
+    /// Replace unused frame `n` (0..27). Note that `write` updates the last byte of every frame
+    /// in the `InfoBlock` to its checksum.
+    pub fn set_unused_frame(&mut self, n: usize, f: Frame) -> Result<(), MCError> {
+        match self.unused_frames.get_mut(n) {
+            Some(u) => {
+                *u = f;
+                Ok(())
+            }
+            None => Err(MCError::InvalidValue(format!(
+                "unused frame {} is out of range",
+                n
+            ))),
         }
+    }
+
+    /// The write test frame, the last frame of the `InfoBlock`. It normally mirrors the header.
+    pub fn wr_test_frame(&self) -> &Header {
+        &self.wr_test_frame
+    }
 
-        let mut wrt = self.wr_test_frame.to_bytes()?;
-        out.write_all(update_checksum(&mut wrt)?)?;
+    /// Replace the write test frame. Returns `MCError::NotAMemoryCard` if it does not start with
+    /// the "MC" magic, as the console expects.
+    pub fn set_wr_test_frame(&mut self, h: Header) -> Result<(), MCError> {
+        if h.id != HEADER_MAGIC {
+            return Err(MCError::NotAMemoryCard);
+        }
+        self.wr_test_frame = h;
 
         Ok(())
     }
+
+    /// Report every frame of the `InfoBlock` that has a bad checksum, including the unused
+    /// frames.
+    pub fn validate_checksums(&self) -> Vec<Finding> {
+        let mut b = [0u8; BLOCK_SIZE];
+        self.write_raw(&mut b);
+        validate_block_checksums(&b)
+            .into_iter()
+            .map(|frame| Finding::BadChecksum { block: 0, frame })
+            .collect()
+    }
+
+    /// Report every unused frame that has a bad checksum.
+    pub fn validate_unused_frames(&self) -> Vec<Finding> {
+        let first = 1 + self.dir_frames.len() + self.broken_frames.len();
+        self.unused_frames
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| validate_checksum(f).is_err())
+            .map(|(n, _)| Finding::BadChecksum {
+                block: 0,
+                frame: first + n,
+            })
+            .collect()
+    }
+
+    /// Walk every `next_block` allocation chain in the directory and report any cycles,
+    /// out-of-range links, bad terminations, or orphaned blocks.
+    pub fn validate_chains(&self) -> Vec<Finding> {
+        validate::validate_chains(&self.dir_frames)
+    }
 }
 
 /// #MemCard
 ///
 /// The entire contents of the memory card are loaded into a `MemCard` struct. From here
 /// the data can be manipulated and written back out.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Like the frame structs, `MemCard` implements `DekuRead`/`DekuWrite`, so `from_bytes` and
+/// `to_bytes` work on the raw image as-is. `open`/`from_reader` additionally validate the
+/// header and checksums, and `write`/`write_to` update the checksums.
+#[derive(Clone, Debug, DekuRead, DekuWrite)]
 pub struct MemCard {
     /// The initial block of data on the memory card.
     pub info: InfoBlock,
 
     /// The save data blocks on the memory card.
+    #[deku(count = "15")]
     pub data: Vec<DataBlock>,
+
+    #[deku(skip, default = "false")]
+    read_only: bool,
+
+    #[deku(skip, default = "AuditLog::default()")]
+    audit: AuditLog,
+
+    #[deku(skip, default = "TitleCache::default()")]
+    titles: TitleCache,
+}
+
+/// Cards are equal if their blocks are, whether they are read-only or not, and whatever their
+/// audit logs and title caches hold.
+impl PartialEq for MemCard {
+    fn eq(&self, other: &Self) -> bool {
+        self.info == other.info && self.data == other.data
+    }
+}
+
+impl Eq for MemCard {}
+
+impl fmt::Display for InfoBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "\n Header: {}\n Checksum: {}",
+            String::from_utf8_lossy(&self.header.id),
+            self.header.checksum
+        )?;
+
+        let used = self
+            .dir_frames
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.get_alloc_state(),
+                    BAState::AllocFirst | BAState::AllocMid | BAState::AllocLast
+                )
+            })
+            .count();
+        write!(
+            f,
+            "\n Directory: {} used, {} free",
+            used,
+            self.dir_frames.len() - used
+        )?;
+        for (n, d) in self.dir_frames.iter().enumerate() {
+            let next = match d.next_block {
+                CHAIN_END => "-".to_string(),
+                b => b.to_string(),
+            };
+            write!(
+                f,
+                "\n  {:>2}: {} ({:#04x}) Next block: {}",
+                n,
+                d.get_alloc_state(),
+                d.state,
+                next
+            )?;
+        }
+
+        let broken: Vec<u32> = self
+            .broken_frames
+            .iter()
+            .map(|b| b.broken_frame)
+            .filter(|b| *b != 0xffffffff)
+            .collect();
+        if broken.is_empty() {
+            write!(f, "\n Broken frames: none")
+        } else {
+            write!(f, "\n Broken frames: {:?}", broken)
+        }
+    }
+}
+
+impl Default for InfoBlock {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemCard {
+    /// Create a freshly formatted, empty memory card.
+    pub fn new() -> Self {
+        Self::from_parts(InfoBlock::new(), vec![DataBlock::zeroed(); 15])
+    }
+
+    /// Create a `MemCardBuilder` to assemble a new card from a list of saves.
+    pub fn builder() -> MemCardBuilder {
+        MemCardBuilder::default()
+    }
+
+    /// Create a `MemCard` from its parsed blocks.
+    pub(crate) fn from_parts(info: InfoBlock, data: Vec<DataBlock>) -> Self {
+        MemCard {
+            info,
+            data,
+            read_only: false,
+            audit: AuditLog::default(),
+            titles: TitleCache::default(),
+        }
+    }
+
+    /// Whether the card is read-only. See `OpenOptions::read_only`.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Make the card read-only, or writable again.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Return `MCError::ReadOnly` if the card is read-only.
+    fn check_writable(&self) -> Result<(), MCError> {
+        match self.read_only {
+            true => Err(MCError::ReadOnly),
+            false => Ok(()),
+        }
+    }
+
     /// Open and parse the memory card file from a filename. Load the data into a `MemCard`
     /// structure.
-    pub fn open(filename: &str) -> Result<Self, MCError> {
-        let mut file = File::open(&filename)?;
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        Self::open_with(filename, OpenOptions::default())
+    }
 
-        // Load Info Block
-        let mut block0 = Block { data: [0u8; BLOCK] };
-        file.read_exact(&mut block0.data)?;
-        let info = InfoBlock::open(block0)?;
+    /// Open and parse the memory card file from a filename using the provided `OpenOptions`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(path = %filename.as_ref().display()))
+    )]
+    pub fn open_with<P: AsRef<Path>>(filename: P, opts: OpenOptions) -> Result<Self, MCError> {
+        let path = filename.as_ref();
+        let file = File::open(path)?;
+        Self::from_reader_with(file, opts)
+            .map_err(|e| e.context(format!("Unable to open {}", path.display())))
+    }
 
-        // Read Data Blocks
-        let mut blocks = Vec::<Block>::new();
-        loop {
-            let mut block = Block { data: [0u8; BLOCK] };
-            file.read_exact(&mut block.data)?;
-            blocks.push(block);
-            if blocks.len() == 15 {
-                break;
-            }
+    /// Parse a memory card from anything that implements `Read`, such as a file inside of an
+    /// archive or a network stream.
+    pub fn from_reader<R: Read>(input: R) -> Result<Self, MCError> {
+        Self::from_reader_with(input, OpenOptions::default())
+    }
+
+    /// Parse a memory card from anything that implements `Read` using the provided
+    /// `OpenOptions`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn from_reader_with<R: Read>(input: R, opts: OpenOptions) -> Result<Self, MCError> {
+        let (info, data) = InfoBlock::read_image(input, opts)?;
+
+        let mut blocks = Vec::<DataBlock>::with_capacity(BLOCKS_PER_CARD - 1);
+        for (n, b) in data[BLOCK_SIZE..].chunks_exact(BLOCK_SIZE).enumerate() {
+            blocks.push(DataBlock::parse_block(n + 1, b)?);
+        }
+        trace_event!(debug, "parsed memory card");
+
+        let mut card = Self::from_parts(info, blocks);
+        card.read_only = opts.read_only;
+
+        Ok(card)
+    }
+
+    /// Parse every page of a card image. Standard images hold a single page, while the images
+    /// of larger third-party cards hold up to 16 standard cards back to back. See
+    /// `CardGeometry::from_size`.
+    pub fn from_image(data: &[u8], opts: OpenOptions) -> Result<Vec<Self>, MCError> {
+        let geometry = CardGeometry::from_size(data.len()).ok_or_else(|| {
+            MCError::InvalidValue(format!("{} bytes is not a card image size", data.len()))
+        })?;
+
+        data.chunks_exact(CardGeometry::STANDARD.size())
+            .take(geometry.pages())
+            .enumerate()
+            .map(|(n, page)| {
+                Self::from_reader_with(page, opts).map_err(|e| e.context(format!("Page {}", n)))
+            })
+            .collect()
+    }
+
+    /// Write out every page of a card image to `out`. The inverse of `from_image`.
+    /// The pages are serialized into a single buffer, which is written in one go.
+    pub fn write_image<T: std::io::Write>(pages: &[Self], out: &mut T) -> Result<(), MCError> {
+        let mut buf = Vec::<u8>::with_capacity(CardGeometry::STANDARD.size() * pages.len());
+        for card in pages {
+            card.write_into(&mut buf)?;
         }
+        out.write_all(&buf)?;
+
+        Ok(())
+    }
+
+    /// Write out the `MemCard` data to a file. The file is replaced atomically, so an emulator
+    /// that has the card open never sees a partially written image.
+    pub fn write<P: AsRef<Path>>(&self, filename: P) -> Result<(), MCError> {
+        self.write_with(filename, WriteOptions::default())
+    }
+
+    /// Write out the `MemCard` data to a file using the provided `WriteOptions`.
+    pub fn write_with<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        opts: WriteOptions,
+    ) -> Result<(), MCError> {
+        self.check_writable()?;
+        let path = filename.as_ref();
+        let write = |path| -> Result<(), MCError> {
+            let mut buf = Vec::<u8>::with_capacity(CardGeometry::STANDARD.size());
+            self.write_into(&mut buf)?;
+            backup::rotate(path, opts.backups)?;
+            atomic::write_atomic(path, &buf, opts.sync)?;
+            Ok(())
+        };
+        write(path).map_err(|e| e.context(format!("Unable to write {}", path.display())))
+    }
+
+    /// Write out the `MemCard` data to `out`. The checksums of the `InfoBlock` frames are
+    /// updated as they are written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn write_to<T: std::io::Write>(&self, out: &mut T) -> Result<(), MCError> {
+        let mut buf = Vec::<u8>::with_capacity(CardGeometry::STANDARD.size());
+        self.write_into(&mut buf)?;
+        out.write_all(&buf)?;
+        trace_event!(debug, bytes = buf.len(), "wrote memory card");
+
+        Ok(())
+    }
 
-        // Load Data Blocks
-        let data = DataBlock::load_all_data_blocks(&blocks)?;
+    /// Serialize the `MemCard` onto the end of `buf`, updating the checksums of the `InfoBlock`
+    /// frames. Reusing `buf` across many cards avoids allocating a new 128KB image per card.
+    pub fn write_into(&self, buf: &mut Vec<u8>) -> Result<(), MCError> {
+        let start = buf.len();
+        buf.resize(start + BLOCK_SIZE * (1 + self.data.len()), 0);
+        let (blocks, _) = buf[start..].as_chunks_mut::<BLOCK_SIZE>();
 
-        Ok(MemCard { info, data })
+        self.info.write_raw(&mut blocks[0]);
+        for f in blocks[0].chunks_mut(FRAME_SIZE) {
+            update_checksum(f)?;
+        }
+        for (n, d) in self.data.iter().enumerate() {
+            d.write_raw(&mut blocks[n + 1])?;
+        }
+
+        Ok(())
     }
 
-    /// Write out the `MemCard` data to a file.
-    pub fn write(&self, filename: &str) -> Result<(), MCError> {
-        let mut file = File::create(&filename)?;
+    /// Get a copy of the raw 8KB card block `n` (0..16), where block 0 is the `InfoBlock` and
+    /// blocks 1..16 are the data blocks. The checksums of the `InfoBlock` frames are updated,
+    /// as they would be by `write`.
+    pub fn block(&self, n: usize) -> Result<Block, MCError> {
+        match n {
+            0 => {
+                let mut v = Vec::<u8>::with_capacity(BLOCK_SIZE);
+                self.info.write(&mut v)?;
+                Block::try_from_slice(&v)
+            }
+            1..=15 => Block::try_from_slice(&self.data[n - 1].to_bytes()?),
+            _ => Err(MCError::InvalidValue(format!(
+                "block {} is out of range",
+                n
+            ))),
+        }
+    }
 
-        self.info.write(&mut file)?;
-        for d in &self.data {
-            d.write(&mut file)?;
+    /// Replace card block `n` (0..16) with the raw `Block`, and parse it back into the
+    /// high-level model. When replacing block 0 the checksums of the `InfoBlock` frames are
+    /// updated first, so only the header magic needs to be valid.
+    pub fn set_block(&mut self, n: usize, mut b: Block) -> Result<(), MCError> {
+        self.check_writable()?;
+        match n {
+            0 => {
+                for f in b.data.chunks_mut(FRAME_SIZE) {
+                    update_checksum(f)?;
+                }
+                self.info = InfoBlock::open(&b)?;
+            }
+            1..=15 => self.data[n - 1] = DataBlock::load_data_block(&b)?,
+            _ => {
+                return Err(MCError::InvalidValue(format!(
+                    "block {} is out of range",
+                    n
+                )))
+            }
         }
 
         Ok(())
     }
 
+    /// Get a copy of frame `frame` (0..64) of card block `block` (0..16), addressed the same way
+    /// the hardware and most documentation refer to locations on the card.
+    pub fn frame(&self, block: usize, frame: usize) -> Result<Frame, MCError> {
+        if frame >= FRAMES_PER_BLOCK {
+            return Err(MCError::InvalidValue(format!(
+                "frame {} is out of range",
+                frame
+            )));
+        }
+
+        let b = self.block(block)?;
+
+        Ok(Frame::from_chunk(
+            &b.data[frame * FRAME_SIZE..(frame + 1) * FRAME_SIZE],
+        ))
+    }
+
+    /// Replace frame `frame` (0..64) of card block `block` (0..16). See `set_block` for how the
+    /// `InfoBlock` checksums are kept consistent.
+    pub fn set_frame(&mut self, block: usize, frame: usize, f: Frame) -> Result<(), MCError> {
+        if frame >= FRAMES_PER_BLOCK {
+            return Err(MCError::InvalidValue(format!(
+                "frame {} is out of range",
+                frame
+            )));
+        }
+
+        let mut b = self.block(block)?;
+        b.data[frame * FRAME_SIZE..(frame + 1) * FRAME_SIZE].copy_from_slice(&f);
+        self.set_block(block, b)
+    }
+
     /// Search for a game save block that matches the `search` term. The search is case
-    /// insensitive.
+    /// insensitive. Blocks without a valid title frame are skipped.
     pub fn find_game(&self, search: &str) -> Result<Vec<DataBlock>, MCError> {
         let mut found = Vec::<DataBlock>::new();
         let mut needle = String::from(search);
         needle.make_ascii_lowercase();
 
         // Find names that match in the data blocks
-        for info in &self.data {
-            let mut haystack = info.title_frame.decode_title()?;
+        for (n, info) in self.data.iter().enumerate() {
+            if info.title_frame.validate().is_err() {
+                continue;
+            }
+
+            let mut haystack = self.titles.get(n, &info.title_frame)?;
             haystack.make_ascii_lowercase();
 
             if haystack.contains(&needle) {
@@ -658,77 +1728,3636 @@ impl MemCard {
 
         Ok(found)
     }
-}
 
-/// Calculate the `Frame` checksum.
-pub fn calc_checksum(d: &[u8]) -> u8 {
-    let mut c = 0;
-    for i in d.iter().take(FRAME - 1) {
-        c ^= *i;
+    /// List the saves that have been deleted, but whose blocks are still intact. These are the
+    /// chains that start on a `FreeFirst` directory entry and can be followed to a `FreeLast`
+    /// entry without running into blocks that have since been reused.
+    pub fn deleted_saves(&self) -> Vec<Save<'_>> {
+        self.info
+            .dir_frames
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.get_alloc_state() == BAState::FreeFirst)
+            .filter_map(|(n, _)| Save::from_chain(self, n, true))
+            .collect()
     }
-    c
-}
 
-/// Calculate the `Frame` checksum and validate that it matches the expected value.
-pub fn validate_checksum(d: &[u8]) -> Result<(), MCError> {
-    let c = calc_checksum(d);
-    if c != d[FRAME - 1] {
-        return Err(MCError::BadChecksum);
+    /// Iterate over the live saves on the memory card. Each save starts on an `AllocFirst`
+    /// directory entry and holds the data blocks its allocation chain points to.
+    pub fn saves(&self) -> impl Iterator<Item = Save<'_>> {
+        self.info
+            .dir_frames
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.get_alloc_state() == BAState::AllocFirst)
+            .filter_map(|(n, _)| Save::from_chain(self, n, false))
     }
 
-    Ok(())
-}
+    /// Walk the allocation chain that starts at directory index `index`, see `ChainWalker`.
+    /// Returns `None` if the index is out of range.
+    pub fn walk_chain(&self, index: usize) -> Option<ChainWalker<'_>> {
+        ChainWalker::new(&self.info.dir_frames, index)
+    }
 
-/// Update the `Frame` checksum after making edits.
-pub fn update_checksum(d: &mut [u8]) -> Result<&[u8], MCError> {
-    let c = calc_checksum(d);
-    d[FRAME - 1] = c;
+    /// Iterate over all 15 data blocks in directory order, annotated with their allocation state
+    /// and the save and chain position they belong to.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = BlockInfo<'_>> {
+        layout::blocks(self).into_iter()
+    }
 
-    validate_checksum(d)?;
+    /// Get the live save whose directory filename is exactly `filename`, such as
+    /// "BASLUS-00123SAVE01". Unlike `find_game` the match is case-sensitive and compares the
+    /// raw bytes of the filename, up to its terminating NUL.
+    pub fn get_save(&self, filename: &str) -> Option<Save<'_>> {
+        self.saves().find(|s| {
+            let raw = &s.dir_frame.filename;
+            let end = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+            &raw[..end] == filename.as_bytes()
+        })
+    }
 
-    Ok(d)
-}
+    /// Import a save from a `.mcs` file into the free blocks of the memory card. Returns the
+    /// directory index of the first block of the imported save, or `MCError::CardFull` if
+    /// there are not enough free blocks.
+    pub fn import_mcs(&mut self, mcs: &[u8]) -> Result<usize, MCError> {
+        self.check_writable()?;
+        let index = mcs::import(self, mcs)?;
+        self.audit.record(AuditAction::ImportSave {
+            index,
+            filename: self.info.dir_frames[index].get_filename(),
+        });
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        Ok(index)
+    }
 
-    #[test]
-    fn memcard_open() {
-        let _ = MemCard::open("epsxe000.mcr").unwrap();
+    /// Write a CSV table of the saves on the card to `out`, with one row per save holding the
+    /// slot, serial, title, region, blocks, size and state. Deleted saves are listed after the
+    /// live ones.
+    pub fn export_csv<W: Write>(&self, out: &mut W) -> Result<(), MCError> {
+        csv::export(self, out)
+    }
 
-        /*
-        // Export images
-        for d in m.data {
-            d.export_all_images().unwrap();
+    /// Write a Markdown table of the live saves on the card to `out`, with one row per save
+    /// holding the slot, title, serial, region and blocks. With `icons`, a directory or URL,
+    /// every row also shows the thumbnail `<icons>/<filename>.png`, as written by
+    /// `write_thumbnails`.
+    pub fn export_markdown<W: Write>(
+        &self,
+        out: &mut W,
+        icons: Option<&str>,
+    ) -> Result<(), MCError> {
+        markdown::export(self, out, icons)
+    }
+
+    /// Write the first icon frame of every live save to `dir` as a PNG named after the directory
+    /// filename of the save, such as "BASLUS-00123GAME.png".
+    pub fn write_thumbnails<P: AsRef<Path>>(&self, dir: P) -> Result<(), MCError> {
+        markdown::write_thumbnails(self, dir.as_ref())
+    }
+
+    /// Search the payloads of the live saves for `pattern`, returning every match in directory
+    /// order.
+    pub fn grep(&self, pattern: &BytePattern) -> Result<Vec<SearchMatch>, MCError> {
+        search::grep(self, pattern)
+    }
+
+    /// Open a `SaveCursor` to read and write the payload of the save that starts at directory
+    /// index `index` like a file.
+    pub fn save_cursor(&mut self, index: usize) -> Result<SaveCursor<'_>, MCError> {
+        self.check_writable()?;
+        SaveCursor::new(self, index)
+    }
+
+    /// Overwrite the payload of the save that starts at directory index `index` with `bytes`,
+    /// starting at `offset`. Offsets are into the payload as returned by `Save::payload`, so
+    /// callers never map them to blocks and frames. The checksum of the directory frame is
+    /// updated, and those of block 0 are updated when the card is written. Returns
+    /// `MCError::InvalidValue`, leaving the save untouched, if the patch reaches past the end of
+    /// the payload.
+    pub fn patch_save(&mut self, index: usize, offset: usize, bytes: &[u8]) -> Result<(), MCError> {
+        {
+            let mut c = self.save_cursor(index)?;
+            save::payload_range(offset, bytes.len(), c.len() as usize)?;
+            c.seek(SeekFrom::Start(offset as u64))?;
+            c.write_all(bytes)?;
+            c.flush()?;
         }
-        */
+        let filename = self.info.dir_frames[index].get_filename();
+        self.audit.record(AuditAction::PatchSave {
+            index,
+            filename,
+            offset,
+            len: bytes.len(),
+        });
+
+        Ok(())
     }
 
-    #[test]
-    fn memcard_write() {
-        let m = MemCard::open("epsxe000.mcr").unwrap();
+    /// Delete the save that starts at directory index `index`, the way the console does: the
+    /// blocks are marked as free, but their contents are kept, so the save still shows up in
+    /// `deleted_saves` until the blocks are reused.
+    pub fn delete_save(&mut self, index: usize) -> Result<(), MCError> {
+        self.check_writable()?;
+        let save = self
+            .saves()
+            .find(|s| s.index == index)
+            .ok_or_else(|| MCError::NotFound(format!("no save starts at block {}", index)))?;
+        let (blocks, filename) = (save.blocks.clone(), save.filename());
 
-        let w = m.find_game("WILD").unwrap();
-        for i in w {
-            println!("{}", i.title_frame);
+        for n in blocks {
+            let state = match self.info.dir_frames[n].get_alloc_state() {
+                BAState::AllocFirst => BAState::FreeFirst,
+                BAState::AllocLast => BAState::FreeLast,
+                _ => BAState::FreeMid,
+            };
+            self.info.dir_frames[n].edit(|d| d.set_alloc_state(state))?;
         }
+        self.audit
+            .record(AuditAction::DeleteSave { index, filename });
 
-        m.write("test.mcr").unwrap();
+        Ok(())
     }
 
-    #[test]
-    fn memcard_modify() {
-        let mut a = MemCard::open("epsxe000.mcr").unwrap();
-        a.info.header.id = [0x11, 0x22];
-        a.write("test.mcr").unwrap();
+    /// Rename the save that starts at directory index `index` to `filename`, such as
+    /// "BASLUS-00123SAVE01". The filename can be up to 20 ASCII characters.
+    pub fn rename_save(&mut self, index: usize, filename: &str) -> Result<(), MCError> {
+        self.check_writable()?;
+        if filename.len() > 20 || !filename.is_ascii() {
+            return Err(MCError::InvalidValue(format!(
+                "filename {:?} is not up to 20 ASCII characters",
+                filename
+            )));
+        }
+        let old = self
+            .saves()
+            .find(|s| s.index == index)
+            .ok_or_else(|| MCError::NotFound(format!("no save starts at block {}", index)))?
+            .filename();
 
-        let mut b = MemCard::open("test.mcr").unwrap();
-        b.info.dir_frames[0].filesize = 4000000;
-        b.write("test.mcr").unwrap();
+        self.info.dir_frames[index].edit(|d| {
+            d.filename = [0u8; 21];
+            d.filename[..filename.len()].copy_from_slice(filename.as_bytes());
+        })?;
+        self.audit.record(AuditAction::RenameSave {
+            index,
+            old,
+            new: filename.to_string(),
+        });
 
-        let mut c = MemCard::open("test.mcr").unwrap();
-        c.info.broken_frames[0].broken_frame = 12345;
-        c.write("test.mcr").unwrap();
+        Ok(())
+    }
+
+    /// The changes made to the card since it was opened, oldest first. Every call of
+    /// `import_mcs`, `delete_save`, `rename_save`, `format`, `wipe`, `canonicalize`, `repair`,
+    /// `relocate_broken` and `rebuild_directory` that succeeds is recorded. Changes made to the
+    /// blocks directly, or through `set_block` and `save_cursor`, are not.
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit.entries
+    }
+
+    /// Append the entries of the audit log that were recorded since the last call to a text
+    /// file next to the card file `card`, named after it, such as "card.mcr.log".
+    pub fn write_audit_log<P: AsRef<Path>>(&mut self, card: P) -> Result<(), MCError> {
+        let path = audit::log_path(card.as_ref());
+        self.audit
+            .append_to(&path)
+            .map_err(|e| e.context(format!("Unable to write {}", path.display())))
+    }
+
+    /// Create a `SaveHandle` for every live save on a shared memory card.
+    pub fn save_handles(self: &Arc<Self>) -> Vec<SaveHandle> {
+        self.saves()
+            .filter_map(|s| SaveHandle::new(Arc::clone(self), s.index))
+            .collect()
+    }
+
+    /// Scan the blocks that are not referenced by the directory for title frames, and
+    /// reconstruct the saves whose directory entries have been lost.
+    pub fn scan_orphans(&self) -> Vec<OrphanSave<'_>> {
+        recover::scan_orphans(self)
+    }
+
+    /// Give every save found by `scan_orphans` a new directory entry, so it is a live save
+    /// again. The filenames are synthesized from the titles, since the originals are lost.
+    pub fn rebuild_directory(&mut self) -> Result<Vec<RebuiltSave>, MCError> {
+        self.check_writable()?;
+        let rebuilt = recover::rebuild_directory(self)?;
+        self.audit.record(AuditAction::RebuildDirectory {
+            saves: rebuilt.len(),
+        });
+
+        Ok(rebuilt)
+    }
+
+    /// Regenerate the directory from the data blocks, so it matches them after low-level edits
+    /// of the blocks. See `InfoBlock::rebuild`.
+    pub fn rebuild_info(&mut self) -> Result<Vec<RebuiltSave>, MCError> {
+        self.check_writable()?;
+        let rebuilt = self.info.rebuild(&self.data)?;
+        self.audit.record(AuditAction::RebuildDirectory {
+            saves: rebuilt.len(),
+        });
+
+        Ok(rebuilt)
+    }
+
+    /// Rebuild a card from a raw image whose `InfoBlock` is destroyed, but whose data blocks
+    /// survive. Block 0 is replaced by a freshly formatted `InfoBlock`, and the directory is
+    /// rebuilt from the title frames found in the data blocks. See `rebuild_directory`.
+    pub fn rebuild(data: &[u8]) -> Result<(Self, Vec<RebuiltSave>), MCError> {
+        recover::rebuild(data)
+    }
+
+    /// Format the memory card the way the console does: the `InfoBlock` is reinitialized, so
+    /// every block is free, but the contents of the data blocks are kept. Use `wipe` to
+    /// overwrite them as well.
+    pub fn format(&mut self) -> Result<(), MCError> {
+        self.check_writable()?;
+        self.info = InfoBlock::new();
+        self.audit.record(AuditAction::Format);
+
+        Ok(())
+    }
+
+    /// Overwrite every data block with `pattern`, and reinitialize the `InfoBlock`, including
+    /// the broken frame list and the unused frames. Nothing of the old contents is left, not even
+    /// the data of deleted saves.
+    pub fn wipe(&mut self, pattern: WipePattern) -> Result<(), MCError> {
+        self.check_writable()?;
+        let mut b = [0u8; BLOCK_SIZE];
+        for d in &mut self.data {
+            pattern.fill(&mut b);
+            *d = DataBlock::from_raw(&b);
+        }
+        self.info = InfoBlock::new();
+        self.audit.record(AuditAction::Wipe(pattern));
+
+        Ok(())
+    }
+
+    /// Normalize everything on the memory card that does not hold save data, so two cards with
+    /// the same saves are byte for byte identical. The canonical form is:
+    ///
+    /// - The header and write test frames are `Header::new()`.
+    /// - Directory entries in the `Free` state are `DirectoryFrame::free()`, and their data
+    ///   blocks are zeroed. Any orphaned save data in them is lost, see `scan_orphans`.
+    /// - The padding of every other directory entry, and of every broken frame, is zeroed.
+    ///   Unused broken frame entries are `BrokenFrame::none()`.
+    /// - The unused frames are 0xFF filled.
+    /// - Every checksum in the `InfoBlock` is recomputed.
+    ///
+    /// Allocated and deleted saves, including their directory entries, are kept as they are.
+    pub fn canonicalize(&mut self) -> Result<(), MCError> {
+        self.check_writable()?;
+        self.info.header = Header::new();
+        self.info.wr_test_frame = Header::new();
+
+        for (n, d) in self.info.dir_frames.iter_mut().enumerate() {
+            if d.get_alloc_state() == BAState::Free {
+                *d = DirectoryFrame::free();
+                self.data[n] = DataBlock::zeroed();
+            } else {
+                d.edit(|d| d.pad = [0u8; 96])?;
+            }
+        }
+
+        for b in &mut self.info.broken_frames {
+            if b.broken_frame == 0xffffffff {
+                *b = BrokenFrame::none();
+            } else {
+                b.edit(|b| b.pad = [0u8; 123])?;
+            }
+        }
+
+        for f in &mut self.info.unused_frames {
+            f.data = [0xff; FRAME_SIZE];
+        }
+        self.audit.record(AuditAction::Canonicalize);
+
+        Ok(())
+    }
+
+    /// Map the byte offsets of every frame of the card image: the header, directory, broken,
+    /// unused and write test frames of block 0, and the title, icon and data frames of the other
+    /// blocks. The regions are in offset order and cover the whole image.
+    pub fn offset_map(&self) -> Vec<OffsetRegion> {
+        offsets::offset_map(self)
+    }
+
+    /// Count the used and free blocks, saves per region, broken frames and save bytes of the
+    /// memory card.
+    pub fn summary(&self) -> CardSummary {
+        summary::summarize(self)
+    }
+
+    /// Count the live saves per region and license, and find the saves whose region does not
+    /// match their product code.
+    pub fn region_stats(&self) -> RegionStats {
+        summary::region_stats(self)
+    }
+
+    /// Validate the allocation chains of the memory card. See `InfoBlock::validate_chains`.
+    pub fn validate_chains(&self) -> Vec<Finding> {
+        self.info.validate_chains()
+    }
+
+    /// Report every allocated save whose first block does not hold a title frame.
+    pub fn validate_titles(&self) -> Vec<Finding> {
+        self.info
+            .dir_frames
+            .iter()
+            .enumerate()
+            .filter(|(n, d)| {
+                d.get_alloc_state() == BAState::AllocFirst
+                    && self.data[*n].title_frame.validate().is_err()
+            })
+            .map(|(n, _)| Finding::BadTitleMagic { block: n })
+            .collect()
+    }
+
+    /// Repair the directory of the memory card: fix bad checksums, end broken allocation
+    /// chains, reconcile filesizes with the chains, and clear nonstandard allocation states, as
+    /// selected by `opts`. Returns a log of every change made. Save data is never modified.
+    pub fn repair(&mut self, opts: RepairOptions) -> Result<Vec<RepairAction>, MCError> {
+        self.check_writable()?;
+        let log = repair::repair(self, opts)?;
+        self.audit
+            .record(AuditAction::Repair { changes: log.len() });
+
+        Ok(log)
+    }
+
+    /// Compare the live saves of this card to those of `other`, such as the same card after a
+    /// play session. Saves are matched by their directory filename, and for saves on both cards
+    /// the frames that differ are listed, relative to the start of the save.
+    pub fn diff(&self, other: &MemCard) -> Result<CardDiff, MCError> {
+        diff::diff(self, other)
+    }
+
+    /// Move every block of a live save that holds a frame listed in the broken frame table to a
+    /// free block without broken frames, relinking the allocation chain. The data of a listed
+    /// frame is taken from its replacement frame in block 0, if one was written. The table
+    /// entries of the moved frames are then cleared. Returns `MCError::CardFull`, without
+    /// changing the card, if there are not enough healthy free blocks.
+    pub fn relocate_broken(&mut self) -> Result<Vec<Relocation>, MCError> {
+        self.check_writable()?;
+        let moved = relocate::relocate_broken(self)?;
+        self.audit.record(AuditAction::RelocateBroken {
+            blocks: moved.len(),
+        });
+
+        Ok(moved)
+    }
+
+    /// Run every validation: the checksums of the `InfoBlock` frames, the allocation chains
+    /// and the title frames. Open the card with `skip_checksums` to get a report for cards with
+    /// bad checksums, instead of an error.
+    pub fn validate(&self) -> ValidationReport {
+        let mut findings = self.info.validate_checksums();
+        findings.extend(self.validate_chains());
+        findings.extend(self.validate_titles());
+
+        ValidationReport { findings }
+    }
+}
+
+impl fmt::Display for MemCard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut saves: Vec<Save> = self.saves().chain(self.deleted_saves()).collect();
+        saves.sort_by_key(|s| s.index);
+
+        writeln!(
+            f,
+            "{:>4}  {:<32}  {:<10}  {:<7}  {:>6}  State",
+            "Slot", "Title", "Serial", "Region", "Blocks"
+        )?;
+        for s in &saves {
+            let title = s.title().unwrap_or_else(|_| "Unknown".to_string());
+            let region = match s.region_info() {
+                Ok(r) => format!("{:?}", r.region),
+                Err(_) => format!("{:?}", Region::UNKNOWN),
+            };
+            let state = if s.deleted { "Deleted" } else { "Used" };
+            writeln!(
+                f,
+                "{:>4}  {:<32}  {:<10}  {:<7}  {:>6}  {}",
+                s.index + 1,
+                title,
+                s.serial(),
+                region,
+                s.blocks.len(),
+                state
+            )?;
+        }
+
+        write!(f, "{} free blocks", self.summary().free_blocks)
+    }
+}
+
+impl Default for MemCard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<usize> for MemCard {
+    type Output = DataBlock;
+
+    /// Access the data block at directory index `n` (0..15).
+    fn index(&self, n: usize) -> &DataBlock {
+        &self.data[n]
+    }
+}
+
+impl IndexMut<usize> for MemCard {
+    /// Mutably access the data block at directory index `n` (0..15). Panics if the card is
+    /// read-only.
+    fn index_mut(&mut self, n: usize) -> &mut DataBlock {
+        assert!(!self.read_only, "data block {} of a read-only card", n);
+        &mut self.data[n]
+    }
+}
+
+impl<'a> IntoIterator for &'a MemCard {
+    type Item = &'a DataBlock;
+    type IntoIter = slice::Iter<'a, DataBlock>;
+
+    /// Iterate over the data blocks of the memory card.
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut MemCard {
+    type Item = &'a mut DataBlock;
+    type IntoIter = slice::IterMut<'a, DataBlock>;
+
+    /// Mutably iterate over the data blocks of the memory card. Panics if the card is read-only.
+    fn into_iter(self) -> Self::IntoIter {
+        assert!(!self.read_only, "data blocks of a read-only card");
+        self.data.iter_mut()
+    }
+}
+
+/// Implement `edit` for the frame types that end with a `checksum` field.
+macro_rules! impl_edit {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                /// Apply `f` to the frame, then recompute the checksum so the frame is never left
+                /// inconsistent.
+                pub fn edit<F: FnOnce(&mut Self)>(&mut self, f: F) -> Result<(), MCError> {
+                    f(self);
+                    self.checksum = calc_checksum(&self.to_bytes()?);
+
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_edit!(Header, DirectoryFrame, BrokenFrame);
+
+/// Calculate the `Frame` checksum.
+pub fn calc_checksum(d: &[u8]) -> u8 {
+    XorFrame.calc(d)
+}
+
+/// Calculate the `Frame` checksum and validate that it matches the expected value.
+pub fn validate_checksum(d: &[u8]) -> Result<(), MCError> {
+    XorFrame.validate(d)
+}
+
+/// Validate the checksum of every `Frame` in `d`, returning the indices of the frames whose
+/// checksum does not match.
+pub fn validate_block_checksums(d: &[u8]) -> Vec<usize> {
+    XorFrame.invalid_units(d)
+}
+
+/// Update the `Frame` checksum after making edits.
+pub fn update_checksum(d: &mut [u8]) -> Result<&[u8], MCError> {
+    XorFrame.update(d);
+    validate_checksum(d)?;
+
+    Ok(d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Generate a memory card with a few saves into a temporary file named `name`.
+    fn test_card(name: &str) -> PathBuf {
+        let card = CardGenerator::new()
+            .save(SaveSpec {
+                icon_frames: 3,
+                ..SaveSpec::new("WILD ARMS", 1)
+            })
+            .save(SaveSpec::new("FINAL FANTASY VII", 3))
+            .save(SaveSpec::new("Gran Turismo", 2))
+            .generate()
+            .unwrap();
+
+        let path = temp_path(name);
+        std::fs::write(&path, card).unwrap();
+        path
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(name)
+    }
+
+    #[test]
+    fn memcard_open() {
+        let m = MemCard::open(test_card("psxmem_open.mcr")).unwrap();
+        assert_eq!(m.data.len(), 15);
+        assert_eq!(m.data[0].icon_frames.len(), 3);
+        assert_eq!(
+            m.data[1].title_frame.decode_title().unwrap(),
+            "FINAL FANTASY VII"
+        );
+        assert!(m.validate_chains().is_empty());
+
+        /*
+        // Export images
+        for d in m.data {
+            d.export_all_images().unwrap();
+        }
+        */
+    }
+
+    #[test]
+    fn memcard_write() {
+        let m = MemCard::open(test_card("psxmem_write.mcr")).unwrap();
+
+        let w = m.find_game("WILD").unwrap();
+        assert_eq!(w.len(), 1);
+        for i in w {
+            println!("{}", i.title_frame);
+        }
+
+        let out = temp_path("psxmem_write_out.mcr");
+        m.write(&out).unwrap();
+        assert_eq!(MemCard::open(&out).unwrap(), m);
+    }
+
+    #[test]
+    fn rebuild_info() {
+        let mut m = MemCard::open(test_card("psxmem_rebuild_info.mcr")).unwrap();
+        m.delete_save(1).unwrap();
+        let dir = &mut m.info.dir_frames;
+        dir[4].edit(|d| d.filesize = 0x1234).unwrap();
+        dir[5] = DirectoryFrame::free();
+        dir[12].state = 0x51;
+
+        // Wild Arms is overwritten, and a new save is written to a free block
+        m.data[0] = DataBlock::zeroed();
+        let mut title = TitleFrame::builder().title("HOMEBREW").build().unwrap();
+        title.icon_palette[1] = 0x7fff;
+        m.data[10] = DataBlock::new(title, &[Frame::zeroed()], b"psxmem").unwrap();
+
+        let rebuilt = m.rebuild_info().unwrap();
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(
+            (rebuilt[0].index, rebuilt[0].blocks.clone()),
+            (10, vec![10])
+        );
+        assert!(m.validate().is_clean());
+
+        let live: Vec<_> = m
+            .saves()
+            .map(|s| (s.index, s.blocks.clone(), s.size()))
+            .collect();
+        assert_eq!(
+            live,
+            vec![
+                (4, vec![4, 5], 2 * BLOCK_SIZE as u32),
+                (10, vec![10], BLOCK_SIZE as u32),
+            ]
+        );
+        assert_eq!(m.get_save("BASLUS-00002SAVE02").unwrap().index, 4);
+        let deleted = m.deleted_saves();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].filename(), "BASLUS-00001SAVE01");
+        assert_eq!(deleted[0].blocks, vec![1, 2, 3]);
+        assert_eq!(m.info.dir_frames[0], DirectoryFrame::free());
+        assert_eq!(m.info.dir_frames[12], DirectoryFrame::free());
+
+        m.set_read_only(true);
+        assert!(m.rebuild_info().is_err());
+
+        // There must be a data block for every directory entry
+        let mut info = m.info.clone();
+        assert!(matches!(
+            info.rebuild(&m.data[..14]),
+            Err(MCError::InvalidValue(_))
+        ));
+        assert!(info.rebuild(&[]).is_err());
+        assert_eq!(info, m.info);
+    }
+
+    #[test]
+    fn rebuild_hiragana_title() {
+        let mut m = MemCard::open(test_card("psxmem_rebuild_hiragana.mcr")).unwrap();
+
+        // "あい" is all hiragana, which shares its lead byte with the latin letters
+        let mut title = TitleFrame::builder().build().unwrap();
+        title.title = [0u8; 64];
+        title.title[..4].copy_from_slice(&[0x82, 0xa0, 0x82, 0xa2]);
+        title.icon_palette[1] = 0x7fff;
+        m.data[10] = DataBlock::new(title, &[Frame::zeroed()], b"psxmem").unwrap();
+
+        let rebuilt = m.rebuild_directory().unwrap();
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(rebuilt[0].filename, "BIRECOV-0011SAVE");
+    }
+
+    #[test]
+    fn rebuild_nonstandard_display() {
+        let mut m = MemCard::open(test_card("psxmem_rebuild_nonstandard.mcr")).unwrap();
+
+        // A save whose display flag of 0x16 still animates 3 frames
+        let mut title = TitleFrame::builder().title("ODDICON").build().unwrap();
+        title.display = 0x16;
+        title.icon_palette[1] = 0x7fff;
+        m.data[10] = DataBlock::new(title, &[Frame::zeroed(); 3], b"psxmem").unwrap();
+
+        let orphans = m.scan_orphans();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(
+            (orphans[0].index, orphans[0].blocks.clone()),
+            (10, vec![10])
+        );
+
+        let rebuilt = m.rebuild_directory().unwrap();
+        assert_eq!(rebuilt[0].filename, "BARECOV-0011ODDICON");
+        assert_eq!(
+            m.saves()
+                .find(|s| s.index == 10)
+                .unwrap()
+                .icon_frames()
+                .len(),
+            3
+        );
+        assert_eq!(
+            m.data[10].title_frame.get_icon_display(),
+            IconDisplay::Nonstandard {
+                display: 0x16,
+                frames: 3
+            }
+        );
+        assert!(m.validate().is_clean());
+    }
+
+    #[test]
+    fn iter_blocks() {
+        let mut m = MemCard::open(test_card("psxmem_iter_blocks.mcr")).unwrap();
+        m.delete_save(4).unwrap();
+        let blocks: Vec<_> = m.iter_blocks().collect();
+        assert_eq!(blocks.len(), 15);
+
+        let ff: Vec<_> = blocks[1..4]
+            .iter()
+            .map(|b| (b.state, b.owner, b.position))
+            .collect();
+        assert_eq!(
+            ff,
+            vec![
+                (BAState::AllocFirst, Some(1), Some(0)),
+                (BAState::AllocMid, Some(1), Some(1)),
+                (BAState::AllocLast, Some(1), Some(2)),
+            ]
+        );
+        assert!(std::ptr::eq(blocks[2].block, &m.data[2]));
+        assert_eq!(m.walk_chain(1).unwrap().collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(m.walk_chain(15).is_none());
+        assert!(!blocks[1].is_deleted());
+
+        assert_eq!((blocks[5].owner, blocks[5].position), (Some(4), Some(1)));
+        assert!(blocks[4].is_deleted() && blocks[5].is_deleted());
+
+        assert_eq!(blocks[14].state, BAState::Free);
+        assert_eq!((blocks[14].owner, blocks[14].position), (None, None));
+        assert!(!blocks[14].is_deleted());
+    }
+
+    #[test]
+    fn get_save() {
+        let mut m = MemCard::open(test_card("psxmem_get_save.mcr")).unwrap();
+        assert_eq!(m.get_save("BASLUS-00001SAVE01").unwrap().index, 1);
+        assert_eq!(m.get_save("BASLUS-00002SAVE02").unwrap().index, 4);
+        assert!(m.get_save("baslus-00001save01").is_none());
+        assert!(m.get_save("BASLUS-00001SAVE0").is_none());
+        assert!(m.get_save("BASLUS-00001SAVE011").is_none());
+        assert!(m.get_save("").is_none());
+
+        m.delete_save(1).unwrap();
+        assert!(m.get_save("BASLUS-00001SAVE01").is_none());
+    }
+
+    #[test]
+    fn memcard_saves() {
+        let m = MemCard::open(test_card("psxmem_saves.mcr")).unwrap();
+
+        assert_eq!(
+            m[1].title_frame.decode_title().unwrap(),
+            "FINAL FANTASY VII"
+        );
+        assert_eq!((&m).into_iter().count(), 15);
+        assert_eq!(m[0].into_iter().count(), 63);
+
+        let saves: Vec<Save> = m.saves().collect();
+        assert_eq!(saves.len(), 3);
+        assert_eq!(saves[1].title().unwrap(), "FINAL FANTASY VII");
+        assert_eq!(saves[1].blocks, vec![1, 2, 3]);
+        assert_eq!(saves[1].size(), 3 * 0x2000);
+        assert_eq!(saves[2].region_info().unwrap().region, Region::America);
+        assert!(m.deleted_saves().is_empty());
+
+        let listing = m.to_string();
+        assert!(listing.contains("   2  FINAL FANTASY VII"));
+        assert!(listing.ends_with("9 free blocks"));
+    }
+
+    #[test]
+    fn memcard_to_bytes() {
+        let card = CardGenerator::new()
+            .save(SaveSpec::new("METAL GEAR SOLID", 1))
+            .generate()
+            .unwrap();
+
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        assert_eq!(m.to_bytes().unwrap(), card);
+
+        let (_, n) = MemCard::from_bytes((&card, 0)).unwrap();
+        assert_eq!(n, m);
+    }
+
+    #[test]
+    fn unknown_state_round_trip() {
+        let mut d = DirectoryFrame {
+            state: 0x1234,
+            filesize: 0,
+            next_block: 0xffff,
+            filename: [0u8; 21],
+            pad: [0u8; 96],
+            checksum: 0,
+        };
+        assert_eq!(d.get_alloc_state(), BAState::UNKNOWN);
+
+        d.set_alloc_state(BAState::UNKNOWN);
+        let (_, e) = DirectoryFrame::from_bytes((&d.to_bytes().unwrap(), 0)).unwrap();
+        assert_eq!(e.state, 0x1234);
+
+        d.set_alloc_state(BAState::FreeFirst);
+        assert_eq!(d.state, 0xa1);
+    }
+
+    #[test]
+    fn directory_frame_builder() {
+        let d = DirectoryFrame::builder()
+            .state(BAState::AllocFirst)
+            .filesize(0x4000)
+            .next_block(3)
+            .filename("BESLES-01234SAVE")
+            .build()
+            .unwrap();
+        assert_eq!(d.get_alloc_state(), BAState::AllocFirst);
+        assert_eq!(d.get_region_info().unwrap().region, Region::Europe);
+        assert!(validate_checksum(&d.to_bytes().unwrap()).is_ok());
+
+        assert!(DirectoryFrame::builder().next_block(15).build().is_err());
+        assert!(DirectoryFrame::builder().filesize(100).build().is_err());
+    }
+
+    #[test]
+    fn title_frame_builder() {
+        let t = TitleFrame::builder()
+            .title("Ridge Racer 4")
+            .icon_frames(2)
+            .block_num(1)
+            .build()
+            .unwrap();
+        assert_eq!(t.id, TITLE_MAGIC);
+        assert_eq!(t.get_icon_display(), IconDisplay::TwoFrames);
+        assert_eq!(t.decode_title().unwrap(), "Ridge Racer 4");
+
+        assert!(TitleFrame::builder().icon_frames(4).build().is_err());
+        assert!(TitleFrame::builder().title("Ridge-Racer").build().is_err());
+    }
+
+    #[test]
+    fn data_block_new() {
+        let title = TitleFrame::builder()
+            .title("HOMEBREW")
+            .icon_frames(2)
+            .build()
+            .unwrap();
+        let mut icon = Frame::zeroed();
+        icon.data[0] = 0x12;
+        let d = DataBlock::new(title, &[icon, Frame::zeroed()], b"psxmem").unwrap();
+        assert_eq!(d.title_frame, title);
+        assert_eq!(d.icon_frames, vec![icon, Frame::zeroed()]);
+        assert_eq!(d.data_frames.len(), FRAMES_PER_BLOCK - 3);
+        assert_eq!(&d.data_frames[0].data[..6], b"psxmem");
+        assert!(d.data_frames[0].data[6..].iter().all(|b| *b == 0));
+
+        let full = vec![0xff; BLOCK_SIZE - 3 * FRAME_SIZE];
+        assert!(DataBlock::new(title, &[icon, icon], &full).is_ok());
+        assert!(DataBlock::new(title, &[icon, icon], &[full, vec![0]].concat()).is_err());
+        assert!(DataBlock::new(title, &[icon], b"").is_err());
+        assert!(DataBlock::new(DataBlock::zeroed().title_frame, &[], b"").is_err());
+    }
+
+    #[test]
+    fn memcard_builder() {
+        let src = MemCard::open(test_card("psxmem_memcard_builder.mcr")).unwrap();
+        let wa = src.saves().next().unwrap();
+        let gt = src.saves().nth(2).unwrap();
+        let title = TitleFrame::builder()
+            .title("HOMEBREW")
+            .icon_frames(1)
+            .build()
+            .unwrap();
+        let payload = vec![0x5a; BLOCK_SIZE];
+
+        let m = MemCard::builder()
+            .mcs(&gt.to_mcs().unwrap())
+            .save("BASLUS-99999HOME", title, &[Frame::zeroed()], &payload)
+            .mcs(&wa.to_mcs().unwrap())
+            .build()
+            .unwrap();
+        assert!(m.validate().is_clean());
+        let saves: Vec<_> = m.saves().map(|s| (s.index, s.blocks.len())).collect();
+        assert_eq!(saves, vec![(0, 2), (2, 2), (4, 1)]);
+        assert_eq!(
+            m.saves().next().unwrap().to_mcs().unwrap(),
+            gt.to_mcs().unwrap()
+        );
+        let home = m.saves().nth(1).unwrap();
+        assert_eq!(home.title().unwrap(), "HOMEBREW");
+        assert_eq!(home.read_at(0, 4).unwrap(), [0x5a; 4]);
+        assert_eq!(
+            home.payload().unwrap().len(),
+            2 * BLOCK_SIZE - 2 * FRAME_SIZE
+        );
+
+        // Too many blocks, duplicate names and icons that do not match the title frame
+        let big = vec![0u8; 15 * BLOCK_SIZE];
+        assert!(matches!(
+            MemCard::builder()
+                .mcs(&wa.to_mcs().unwrap())
+                .save(
+                    "BASLUS-99999HOME",
+                    title,
+                    &[Frame::zeroed()],
+                    &big[..BLOCK_SIZE * 14]
+                )
+                .build(),
+            Err(MCError::CardFull)
+        ));
+        assert!(MemCard::builder()
+            .mcs(&wa.to_mcs().unwrap())
+            .mcs(&wa.to_mcs().unwrap())
+            .build()
+            .is_err());
+        assert!(MemCard::builder()
+            .save("BASLUS-99999HOME", title, &[], &payload)
+            .build()
+            .is_err());
+        assert!(MemCard::builder()
+            .mcs_file(temp_path("psxmem_memcard_builder_missing.mcs"))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn state_strings() {
+        for raw in [0x51, 0x52, 0x53, 0xa0, 0xa1, 0xa2, 0xa3] {
+            let state = BAState::from(raw);
+            assert_eq!(state.to_raw(), Some(raw));
+            assert_eq!(state.to_string().parse::<BAState>().unwrap(), state);
+            assert_eq!(format!("{:#x}", raw).parse::<BAState>().unwrap(), state);
+        }
+        assert_eq!(BAState::AllocFirst.to_string(), "first block");
+        assert_eq!(BAState::FreeMid.to_string(), "deleted middle block");
+        assert_eq!("alloc-last".parse::<BAState>().unwrap(), BAState::AllocLast);
+        assert_eq!("FreeFirst".parse::<BAState>().unwrap(), BAState::FreeFirst);
+        assert_eq!(BAState::from(0x1234).to_raw(), None);
+        assert!("0x1234".parse::<BAState>().is_err());
+        assert!("taken".parse::<BAState>().is_err());
+
+        for raw in [0x11, 0x12, 0x13] {
+            let display = IconDisplay::from(raw);
+            assert_eq!(display.to_raw(), raw);
+            assert_eq!(display.to_string().parse::<IconDisplay>().unwrap(), display);
+        }
+        assert_eq!(IconDisplay::TwoFrames.to_string(), "2 frames");
+        assert_eq!(IconDisplay::ThreeFrames.frames(), 3);
+        assert_eq!("1".parse::<IconDisplay>().unwrap(), IconDisplay::OneFrame);
+        assert_eq!(
+            "0x13".parse::<IconDisplay>().unwrap(),
+            IconDisplay::ThreeFrames
+        );
+        assert!("0x114".parse::<IconDisplay>().is_err());
+        assert!("4".parse::<IconDisplay>().is_err());
+    }
+
+    #[test]
+    fn blank_memcard() {
+        let m = MemCard::new();
+        let b = m.to_bytes().unwrap();
+        assert_eq!(b.len(), 0x20000);
+        assert_eq!(MemCard::from_reader(&b[..]).unwrap(), m);
+        assert_eq!(m.saves().count(), 0);
+
+        let info = m.info.to_string();
+        assert!(info.contains("Header: MC"));
+        assert!(info.contains("Directory: 0 used, 15 free"));
+        assert!(info.ends_with("Broken frames: none"));
+    }
+
+    #[test]
+    fn edit_keeps_checksum() {
+        let mut d = DirectoryFrame::free();
+        d.edit(|d| {
+            d.set_alloc_state(BAState::AllocFirst);
+            d.filesize = 0x2000;
+        })
+        .unwrap();
+        assert_eq!(d.filesize, 0x2000);
+        assert!(validate_checksum(&d.to_bytes().unwrap()).is_ok());
+
+        let mut b = BrokenFrame::none();
+        b.edit(|b| b.broken_frame = 100).unwrap();
+        assert!(validate_checksum(&b.to_bytes().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn compact_debug() {
+        let d = format!("{:?}", DirectoryFrame::free());
+        assert!(d.contains("state: Free (0xa0)"));
+        assert!(d.contains("pad: [0x00; 96]"));
+        assert!(format!("{:?}", Block::zeroed()).len() < 64);
+    }
+
+    #[test]
+    fn memcard_modify() {
+        let out = temp_path("psxmem_modify.mcr");
+
+        let mut a = MemCard::open(test_card("psxmem_modify_in.mcr")).unwrap();
+        a.info.header.pad[0] = 0x11;
+        a.write(&out).unwrap();
+
+        let mut b = MemCard::open(&out).unwrap();
+        b.info.dir_frames[0].filesize = 4000000;
+        b.write(&out).unwrap();
+
+        let mut c = MemCard::open(&out).unwrap();
+        c.info.broken_frames[0].broken_frame = 12345;
+        c.write(&out).unwrap();
+
+        let d = MemCard::open(&out).unwrap();
+        assert_eq!(d.info.header.pad[0], 0x11);
+        assert_eq!(d.info.dir_frames[0].filesize, 4000000);
+        assert_eq!(d.info.broken_frames[0].broken_frame, 12345);
+    }
+
+    #[test]
+    fn generated_defects() {
+        let card = CardGenerator::new()
+            .save(SaveSpec::new("CRASH", 2))
+            .save(SaveSpec::new("SPYRO", 1))
+            .defect(Defect::ChainCycle { save: 0 })
+            .defect(Defect::BrokenChain { save: 1 })
+            .generate()
+            .unwrap();
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        assert_eq!(
+            m.validate_chains(),
+            vec![
+                Finding::ChainCycle { start: 0, block: 0 },
+                Finding::ChainOutOfRange {
+                    start: 2,
+                    block: 2,
+                    next: 0xff
+                },
+            ]
+        );
+
+        let card = CardGenerator::new()
+            .defect(Defect::BadChecksum { block: 0, frame: 3 })
+            .generate()
+            .unwrap();
+        assert!(matches!(
+            MemCard::from_reader(&card[..]),
+            Err(MCError::BadChecksum)
+        ));
+    }
+
+    #[test]
+    fn generated_defects_out_of_range() {
+        let generator = CardGenerator::new().save(SaveSpec::new("CRASH", 2));
+        for defect in [
+            Defect::BrokenChain { save: 1 },
+            Defect::ChainCycle { save: 5 },
+            Defect::BadChecksum {
+                block: BLOCKS_PER_CARD,
+                frame: 0,
+            },
+            Defect::BadChecksum {
+                block: 1,
+                frame: FRAMES_PER_BLOCK,
+            },
+        ] {
+            assert!(
+                matches!(
+                    generator.clone().defect(defect).generate(),
+                    Err(MCError::InvalidValue(_))
+                ),
+                "{:?}",
+                defect
+            );
+        }
+
+        let last = Defect::BadChecksum {
+            block: BLOCKS_PER_CARD - 1,
+            frame: FRAMES_PER_BLOCK - 1,
+        };
+        assert!(generator.defect(last).generate().is_ok());
+    }
+
+    #[test]
+    fn raw_block_access() {
+        let mut m = MemCard::open(test_card("psxmem_raw_block.mcr")).unwrap();
+
+        let mut b = m.block(0).unwrap();
+        assert_eq!(b.data[..2], HEADER_MAGIC);
+        b.data[FRAME_SIZE + 4] = 0x55;
+        m.set_block(0, b).unwrap();
+        assert_eq!(m.info.dir_frames[0].filesize & 0xff, 0x55);
+        validate_checksum(&m.block(0).unwrap().data[FRAME_SIZE..FRAME_SIZE * 2]).unwrap();
+
+        let mut b = m.block(2).unwrap();
+        b.data[BLOCK_SIZE - 1] = 0xaa;
+        m.set_block(2, b).unwrap();
+        assert_eq!(
+            m.data[1].data_frames.last().unwrap().data[FRAME_SIZE - 1],
+            0xaa
+        );
+
+        assert!(m.block(16).is_err());
+        assert!(m.set_block(0, Block::zeroed()).is_err());
+    }
+
+    #[test]
+    fn try_from_slice() {
+        let image = std::fs::read(test_card("psxmem_try_from_slice.mcr")).unwrap();
+        let b = Block::try_from_slice(&image[BLOCK_SIZE..2 * BLOCK_SIZE]).unwrap();
+        assert_eq!(
+            b,
+            MemCard::from_reader(&image[..]).unwrap().block(1).unwrap()
+        );
+        assert!(Block::try_from_slice(&image[..BLOCK_SIZE - 1]).is_err());
+        assert!(Block::try_from_slice(&image[..BLOCK_SIZE + 1]).is_err());
+
+        let f = Frame::try_from_slice(&image[FRAME_SIZE..2 * FRAME_SIZE]).unwrap();
+        assert_eq!(
+            DirectoryFrame::from_raw(&f.data).get_filename(),
+            "BASLUS-00000SAVE00"
+        );
+        assert!(Frame::try_from_slice(&[]).is_err());
+        assert!(Frame::try_from_slice(&image[..FRAME_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn frame_and_block_bytes() {
+        fn total<B: AsRef<[u8]>>(b: B) -> usize {
+            b.as_ref().len()
+        }
+
+        let m = MemCard::open(test_card("psxmem_frame_and_block_bytes.mcr")).unwrap();
+        let b = m.block(0).unwrap();
+        let f = m.frame(0, 1).unwrap();
+        assert_eq!(total(&b), BLOCK_SIZE);
+        assert_eq!(total(f), FRAME_SIZE);
+        assert_eq!(b[..2], HEADER_MAGIC);
+        assert_eq!(&b[FRAME_SIZE..2 * FRAME_SIZE], &*f);
+        assert!(f.starts_with(&[0x51]));
+
+        let mut out = Vec::new();
+        out.write_all(&f).unwrap();
+        assert_eq!(out, f.data);
+        validate_checksum(&f).unwrap();
+    }
+
+    #[test]
+    fn frame_access() {
+        let mut m = MemCard::open(test_card("psxmem_frame_access.mcr")).unwrap();
+
+        let title = m.frame(1, 0).unwrap();
+        assert_eq!(title.data[..2], TITLE_MAGIC);
+
+        let mut f = m.frame(0, 2).unwrap();
+        f.data[4] = 0x77;
+        m.set_frame(0, 2, f).unwrap();
+        assert_eq!(m.info.dir_frames[1].filesize & 0xff, 0x77);
+
+        assert!(m.frame(0, 64).is_err());
+        assert!(m.set_frame(16, 0, f).is_err());
+    }
+
+    #[test]
+    fn save_metadata() {
+        let m = MemCard::open(test_card("psxmem_save_metadata.mcr")).unwrap();
+        let meta = m.saves().nth(1).unwrap().metadata().unwrap();
+        assert_eq!(
+            meta,
+            SaveMetadata {
+                title: "FINAL FANTASY VII".to_string(),
+                serial: "SLUS-00001".to_string(),
+                region: Region::America,
+                license: License::Licensed,
+                name: "SAVE01".to_string(),
+                size: (BLOCK_SIZE * 3) as u32,
+                blocks: 3,
+                icon_frames: 1,
+                state: BAState::AllocFirst,
+            }
+        );
+    }
+
+    #[test]
+    fn card_geometry() {
+        let g = CardGeometry::default();
+        assert_eq!(g.size(), 0x20000);
+        assert_eq!(g.data_blocks(), 15);
+        assert_eq!(g.offset(1, 2), Some(BLOCK_SIZE + 2 * FRAME_SIZE));
+        assert_eq!(g.locate(BLOCK_SIZE + 2 * FRAME_SIZE + 5), Some((1, 2)));
+        assert_eq!(g.offset(0, FRAMES_PER_BLOCK), None);
+        assert_eq!(g.locate(g.size()), None);
+    }
+
+    #[test]
+    fn multi_page_image() {
+        let a = CardGenerator::new()
+            .save(SaveSpec::new("PAGE ONE", 1))
+            .generate()
+            .unwrap();
+        let b = CardGenerator::new()
+            .save(SaveSpec::new("PAGE TWO", 2))
+            .generate()
+            .unwrap();
+        let image = [a, b].concat();
+
+        let pages = MemCard::from_image(&image, OpenOptions::default()).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages[1].saves().next().unwrap().title().unwrap(),
+            "PAGE TWO"
+        );
+
+        let mut out = Vec::<u8>::new();
+        MemCard::write_image(&pages, &mut out).unwrap();
+        assert_eq!(out, image);
+
+        assert_eq!(CardGeometry::from_size(image.len()).unwrap().pages(), 2);
+        assert!(MemCard::from_image(&image[1..], OpenOptions::default()).is_err());
+    }
+
+    #[test]
+    fn unused_and_wr_test_frames() {
+        let mut m = MemCard::open(test_card("psxmem_unused_frames.mcr")).unwrap();
+        assert_eq!(m.info.unused_frames().len(), 27);
+        assert_eq!(m.info.wr_test_frame().id, HEADER_MAGIC);
+
+        let mut f = Frame::zeroed();
+        f.data[0] = 0x42;
+        m.info.set_unused_frame(3, f).unwrap();
+        assert!(m.info.set_unused_frame(27, f).is_err());
+        assert!(m.info.set_wr_test_frame(Header::default()).is_ok());
+
+        let mut h = Header::new();
+        h.id = *b"XX";
+        assert!(m.info.set_wr_test_frame(h).is_err());
+
+        let out = temp_path("psxmem_unused_frames_out.mcr");
+        m.write(&out).unwrap();
+        let m = MemCard::open(&out).unwrap();
+        assert_eq!(m.info.unused_frames()[3].data[0], 0x42);
+    }
+
+    #[test]
+    fn save_handles() {
+        let card = Arc::new(MemCard::open(test_card("psxmem_save_handles.mcr")).unwrap());
+        let handles = card.save_handles();
+        assert_eq!(handles.len(), 3);
+        assert!(handles.iter().all(|h| Arc::ptr_eq(h.card(), &card)));
+        assert_eq!(handles[1].save().title().unwrap(), "FINAL FANTASY VII");
+
+        let mut h = handles[2].clone();
+        h.block_mut(1).unwrap().data_frames[0].data[0] = 0x99;
+        assert!(!Arc::ptr_eq(h.card(), &card));
+        assert_eq!(h.save().data[1].data_frames[0].data[0], 0x99);
+        assert_ne!(card.data[5].data_frames[0].data[0], 0x99);
+        assert!(h.block_mut(2).is_none());
+    }
+
+    #[test]
+    fn card_summary() {
+        let mut m = MemCard::open(test_card("psxmem_card_summary.mcr")).unwrap();
+        m.info.broken_frames[0].broken_frame = 0x40;
+
+        let s = m.summary();
+        assert_eq!(s.used_blocks, 6);
+        assert_eq!(s.free_blocks, 9);
+        assert_eq!(s.saves, 3);
+        assert_eq!(s.regions[1], (Region::America, 3));
+        assert_eq!(s.broken_frames, 1);
+        assert_eq!(s.payload_bytes, (BLOCK_SIZE * 6) as u64);
+    }
+
+    #[test]
+    fn region_stats() {
+        let image = CardGenerator::new()
+            .save(SaveSpec::new("ONE", 1))
+            .save(SaveSpec {
+                filename: Some("BESCES-00001ONE".to_string()),
+                ..SaveSpec::new("TWO", 1)
+            })
+            .save(SaveSpec {
+                filename: Some("BISLPS-00002TWO".to_string()),
+                ..SaveSpec::new("THREE", 1)
+            })
+            .save(SaveSpec {
+                filename: Some("BESLUS-00003MISFLAG".to_string()),
+                ..SaveSpec::new("FOUR", 1)
+            })
+            .save(SaveSpec {
+                filename: Some("XXHOMEBREW".to_string()),
+                ..SaveSpec::new("FIVE", 1)
+            })
+            .generate()
+            .unwrap();
+        let m = MemCard::from_reader(&image[..]).unwrap();
+
+        let s = m.region_stats();
+        assert_eq!(s.counts.len(), 12);
+        assert_eq!(s.count(Region::America, License::Licensed), 1);
+        assert_eq!(s.count(Region::Europe, License::Sony), 1);
+        assert_eq!(s.count(Region::Europe, License::Licensed), 1);
+        assert_eq!(s.count(Region::Japan, License::Licensed), 1);
+        assert_eq!(s.count(Region::UNKNOWN, License::UNKNOWN), 1);
+        assert_eq!(s.count(Region::Japan, License::Sony), 0);
+        assert_eq!(s.region(Region::Europe), 2);
+        assert_eq!(s.license(License::Licensed), 3);
+        assert_eq!(s.mismatched, [3]);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let mut a = MemCard::open(test_card("psxmem_canonicalize.mcr")).unwrap();
+        let mut b = a.clone();
+        b.info.header.pad[3] = 0x12;
+        b.info.dir_frames[0].pad[0] = 0x34;
+        b.info.dir_frames[10].filename[0] = b'X';
+        b.data[10].data_frames[0].data[0] = 0x56;
+        b.info.set_unused_frame(0, Frame::zeroed()).unwrap();
+        assert_ne!(a, b);
+
+        a.canonicalize().unwrap();
+        b.canonicalize().unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.saves().count(), 3);
+
+        let mut out = Vec::<u8>::new();
+        a.write_to(&mut out).unwrap();
+        assert_eq!(MemCard::from_reader(&out[..]).unwrap(), a);
+    }
+
+    #[test]
+    fn title_magic() {
+        let mut m = MemCard::open(test_card("psxmem_title_magic.mcr")).unwrap();
+        assert!(m.validate_titles().is_empty());
+
+        m.data[1].title_frame.id = [0u8; 2];
+        assert_eq!(
+            m.validate_titles(),
+            vec![Finding::BadTitleMagic { block: 1 }]
+        );
+        assert!(matches!(
+            m.saves().nth(1).unwrap().title(),
+            Err(MCError::NotASave)
+        ));
+        assert!(m.find_game("fantasy").unwrap().is_empty());
+    }
+
+    #[test]
+    fn memcard_ref() {
+        let path = test_card("psxmem_memcard_ref.mcr");
+        let raw = std::fs::read(&path).unwrap();
+        let owned = MemCard::open(&path).unwrap();
+
+        let view = MemCardRef::new(&raw).unwrap();
+        for (d, o) in view.dir_entries().zip(&owned.info.dir_frames) {
+            assert_eq!(d.alloc_state(), o.get_alloc_state());
+            assert_eq!(d.filesize(), o.filesize);
+            assert_eq!(d.next_block(), o.next_block);
+        }
+        assert_eq!(view.dir_entry(1).unwrap().filename(), b"BASLUS-00001SAVE01");
+        assert_eq!(view.title(1).unwrap(), "FINAL FANTASY VII");
+        assert!(matches!(view.title(2), Err(MCError::NotASave)));
+        assert_eq!(view.to_memcard().unwrap(), owned);
+
+        assert!(MemCardRef::new(&raw[1..]).is_err());
+    }
+
+    #[test]
+    fn lazy_memcard() {
+        let path = test_card("psxmem_lazy.mcr");
+        let owned = MemCard::open(&path).unwrap();
+
+        let lazy = LazyMemCard::open(&path).unwrap();
+        assert_eq!(lazy.info, owned.info);
+        assert!(!lazy.is_parsed(1));
+        assert_eq!(lazy.data_block(1).unwrap(), &owned.data[1]);
+        assert!(lazy.is_parsed(1));
+        assert!(!lazy.is_parsed(2));
+        assert!(lazy.data_block(15).is_err());
+
+        assert_eq!(lazy.into_memcard().unwrap(), owned);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_card() {
+        let path = test_card("psxmem_mapped.mcr");
+        let mapped = MappedCard::open(&path).unwrap();
+        assert_eq!(mapped.geometry(), CardGeometry::STANDARD);
+
+        let view = mapped.view().unwrap();
+        assert_eq!(view.title(0).unwrap(), "WILD ARMS");
+        assert!(mapped.page(1).is_err());
+        assert_eq!(view.to_memcard().unwrap(), MemCard::open(&path).unwrap());
+    }
+
+    #[test]
+    fn card_stream() {
+        let raw = std::fs::read(test_card("psxmem_card_stream.mcr")).unwrap();
+        let owned = MemCard::from_reader(&raw[..]).unwrap();
+
+        // Only the first block has arrived
+        let mut s = CardStream::new(&raw[..BLOCK_SIZE]);
+        assert_eq!(s.info().unwrap(), &owned.info);
+        assert!(s.next().unwrap().is_err());
+        assert!(s.next().is_none());
+
+        let mut s = CardStream::new(&raw[..]);
+        assert_eq!(s.next().unwrap().unwrap(), owned.data[0]);
+        assert_eq!(s.count(), 14);
+
+        let s = CardStream::new(&raw[..]);
+        assert_eq!(s.into_memcard().unwrap(), owned);
+    }
+
+    #[test]
+    fn backup_on_write() {
+        let dir = std::env::temp_dir().join("psxmem_backup_on_write");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("card.mcr");
+        std::fs::write(dir.join("card.mcr.notes.bak"), b"not a backup").unwrap();
+        let opts = WriteOptions {
+            backups: 2,
+            ..Default::default()
+        };
+
+        // Nothing to back up yet, then one backup per write, keeping the newest two
+        let mut m = MemCard::open(test_card("psxmem_backup_on_write.mcr")).unwrap();
+        let mut images = Vec::new();
+        for n in 0..4 {
+            m.write_with(&path, opts).unwrap();
+            images.push(std::fs::read(&path).unwrap());
+            if n < 3 {
+                let index = m.saves().next().unwrap().index;
+                m.delete_save(index).unwrap();
+            }
+        }
+
+        let mut backups: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .filter(|n| n != "card.mcr" && n != "card.mcr.notes.bak")
+            .collect();
+        backups.sort();
+        assert_eq!(backups.len(), 2, "{:?}", backups);
+        assert!(backups
+            .iter()
+            .all(|b| b.starts_with("card.mcr.") && b.ends_with(".bak")));
+        assert_eq!(std::fs::read(dir.join(&backups[0])).unwrap(), images[1]);
+        assert_eq!(std::fs::read(dir.join(&backups[1])).unwrap(), images[2]);
+        assert!(dir.join("card.mcr.notes.bak").exists());
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = std::env::temp_dir().join("psxmem_atomic_write");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("card.mcr");
+        std::fs::write(&path, b"an older, longer file that is replaced").unwrap();
+
+        let mut m = MemCard::open(test_card("psxmem_atomic_write.mcr")).unwrap();
+        m.write(&path).unwrap();
+        m.delete_save(0).unwrap();
+        m.write_with(
+            &path,
+            WriteOptions {
+                sync: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let mut expected = Vec::new();
+        m.write_to(&mut expected).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+        // No temporary files are left behind
+        let names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["card.mcr"]);
+
+        // A failed write leaves the card as it was
+        assert!(m.write(dir.join("missing/card.mcr")).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{symlink, PermissionsExt};
+
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+            let link = dir.join("link.mcr");
+            symlink(&path, &link).unwrap();
+            m.write(&link).unwrap();
+            assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn write_into_buffer() {
+        let m = MemCard::open(test_card("psxmem_write_into.mcr")).unwrap();
+
+        let mut buf = vec![0xaa];
+        m.write_into(&mut buf).unwrap();
+        m.write_into(&mut buf).unwrap();
+        assert_eq!(buf.len(), 1 + 2 * CardGeometry::STANDARD.size());
+
+        let mut out = Vec::<u8>::new();
+        m.write_to(&mut out).unwrap();
+        assert_eq!(buf[1..1 + out.len()], out[..]);
+        assert_eq!(buf[1 + out.len()..], out[..]);
+    }
+
+    #[test]
+    fn frame_load() {
+        let raw = std::fs::read(test_card("psxmem_frame_load.mcr")).unwrap();
+
+        let frames = Frame::load(&raw[..BLOCK_SIZE], 16).unwrap();
+        assert_eq!(frames.len(), 16);
+        assert_eq!(frames[1].data[..], raw[FRAME_SIZE..FRAME_SIZE * 2]);
+        assert!(Frame::load(&raw[..FRAME_SIZE], 2).is_err());
+
+        assert!(Frame::load_unchecked(&raw, 0).unwrap().is_empty());
+        assert_eq!(Frame::load_unchecked(&raw, 64).unwrap().len(), 64);
+    }
+
+    #[test]
+    fn checksums() {
+        let raw = std::fs::read(test_card("psxmem_checksums.mcr")).unwrap();
+        for f in raw[..BLOCK_SIZE].chunks_exact(FRAME_SIZE) {
+            let c = f[..FRAME_SIZE - 1].iter().fold(0, |c, b| c ^ b);
+            assert_eq!(calc_checksum(f), c);
+        }
+        assert_eq!(calc_checksum(&[0x12, 0x34, 0x56]), 0x12 ^ 0x34 ^ 0x56);
+
+        let mut info = raw[..BLOCK_SIZE].to_vec();
+        assert!(validate_block_checksums(&info).is_empty());
+        info[FRAME_SIZE * 3] ^= 1;
+        assert_eq!(validate_block_checksums(&info), vec![3]);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn watched_card() {
+        let path = test_card("psxmem_watched_card.mcr");
+        let mut w = WatchedCard::open(&path).unwrap();
+        assert_eq!(w.saves().count(), 3);
+        assert!(w.poll().unwrap().is_empty());
+
+        let mut m = MemCard::open(&path).unwrap();
+        m.delete_save(1).unwrap();
+        m.data[0].icon_frames[0].data[0] ^= 0xff;
+        m.write(&path).unwrap();
+
+        let mut events = w.wait(std::time::Duration::from_secs(5)).unwrap();
+        events.sort_by_key(|e| format!("{:?}", e));
+        assert_eq!(events, vec![CardEvent::Changed(0), CardEvent::Removed(1)]);
+        assert_eq!(w.saves().count(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_io() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let path = test_card("psxmem_async_io.mcr");
+            let mut m = MemCard::open_async(&path).await.unwrap();
+            assert_eq!(m, MemCard::open(&path).unwrap());
+
+            m.delete_save(0).unwrap();
+            let out = temp_path("psxmem_async_io_out.mcr");
+            m.write_async(&out).await.unwrap();
+            assert_eq!(MemCard::open(&out).unwrap(), m);
+
+            // The previous file is backed up
+            let before = std::fs::read(&out).unwrap();
+            let opts = WriteOptions {
+                backups: 1,
+                ..Default::default()
+            };
+            MemCard::new().write_with_async(&out, opts).await.unwrap();
+            let backups: Vec<_> = std::fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .map(|e| e.unwrap().path())
+                .filter(|p| crate::backup::is_backup(&out, p.file_name().unwrap()))
+                .collect();
+            assert_eq!(backups.len(), 1);
+            assert_eq!(std::fs::read(&backups[0]).unwrap(), before);
+            std::fs::remove_file(&backups[0]).unwrap();
+
+            let e = MemCard::open_async(temp_path("psxmem_async_missing.mcr"))
+                .await
+                .unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::Io);
+        });
+    }
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn remote_cards() {
+        use std::collections::HashMap;
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+        use std::sync::Mutex;
+
+        // A minimal FTP server holding one card
+        let files = Arc::new(Mutex::new(HashMap::<String, Vec<u8>>::new()));
+        let card = std::fs::read(test_card("psxmem_remote_cards.mcr")).unwrap();
+        files
+            .lock()
+            .unwrap()
+            .insert("/MemoryCards/SLUS-00001/SLUS-00001-1.mcd".to_string(), card);
+
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = files.clone();
+        std::thread::spawn(move || {
+            let (stream, _) = server.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut ctrl = BufReader::new(stream.try_clone().unwrap());
+            let mut out = stream;
+            let mut data = None;
+            writeln!(out, "220 ready\r").unwrap();
+            loop {
+                let mut line = String::new();
+                if ctrl.read_line(&mut line).unwrap() == 0 {
+                    return;
+                }
+                let (cmd, arg) = line
+                    .trim_end()
+                    .split_once(' ')
+                    .unwrap_or((line.trim_end(), ""));
+                match cmd {
+                    "USER" => writeln!(out, "331 password\r").unwrap(),
+                    "PASS" | "TYPE" => writeln!(out, "230 ok\r").unwrap(),
+                    "PASV" => {
+                        let l = TcpListener::bind("127.0.0.1:0").unwrap();
+                        let p = l.local_addr().unwrap().port();
+                        writeln!(out, "227 Passive (10,0,0,1,{},{})\r", p >> 8, p & 0xff).unwrap();
+                        data = Some(l);
+                    }
+                    "RETR" if !shared.lock().unwrap().contains_key(arg) => {
+                        writeln!(out, "550 not found\r").unwrap()
+                    }
+                    "NLST" | "RETR" | "STOR" => {
+                        let (mut d, _) = data.take().unwrap().accept().unwrap();
+                        writeln!(out, "150 opening\r").unwrap();
+                        let mut files = shared.lock().unwrap();
+                        match cmd {
+                            "NLST" => {
+                                let mut names: Vec<String> = files
+                                    .keys()
+                                    .filter_map(|k| k.strip_prefix(&format!("{}/", arg)))
+                                    .map(|k| k.split('/').next().unwrap().to_string())
+                                    .collect();
+                                names.sort();
+                                names.dedup();
+                                d.write_all(names.join("\r\n").as_bytes()).unwrap();
+                            }
+                            "RETR" => d.write_all(&files[arg]).unwrap(),
+                            _ => {
+                                let mut v = Vec::new();
+                                d.read_to_end(&mut v).unwrap();
+                                files.insert(arg.to_string(), v);
+                            }
+                        }
+                        drop(d);
+                        writeln!(out, "226 done\r").unwrap();
+                    }
+                    "QUIT" => {
+                        writeln!(out, "221 bye\r").unwrap();
+                        return;
+                    }
+                    _ => writeln!(out, "502 unknown\r").unwrap(),
+                }
+            }
+        });
+
+        let mut r = RemoteCards::connect(addr, "user", "secret").unwrap();
+        let cards = r.list().unwrap();
+        assert_eq!(cards, vec!["/MemoryCards/SLUS-00001/SLUS-00001-1.mcd"]);
+
+        let mut m = r.download(&cards[0]).unwrap();
+        assert_eq!(m.saves().count(), 3);
+        m.delete_save(0).unwrap();
+        r.upload("/MemoryCards/SLUS-00001/SLUS-00001-2.mcd", &m)
+            .unwrap();
+        assert!(r.download("/MemoryCards/missing.mcd").is_err());
+        r.quit().unwrap();
+
+        let uploaded = files.lock().unwrap()["/MemoryCards/SLUS-00001/SLUS-00001-2.mcd"].clone();
+        assert_eq!(MemCard::from_reader(&uploaded[..]).unwrap(), m);
+    }
+
+    #[test]
+    fn locator_scan_dir() {
+        let dir = temp_path("psxmem_locator");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Beetle PSX HW")).unwrap();
+
+        let card = std::fs::read(test_card("psxmem_locator.mcr")).unwrap();
+        std::fs::write(dir.join("epsxe000.mcr"), &card).unwrap();
+        std::fs::write(dir.join("Beetle PSX HW/Game.SRM"), &card).unwrap();
+        std::fs::write(dir.join("PS1CARD.VM1"), &card).unwrap();
+        std::fs::write(dir.join("notes.txt"), &card).unwrap();
+        std::fs::write(dir.join("short.mcd"), &card[..100]).unwrap();
+
+        assert_eq!(
+            locator::scan_dir(&dir, 1),
+            vec![
+                dir.join("Beetle PSX HW/Game.SRM"),
+                dir.join("PS1CARD.VM1"),
+                dir.join("epsxe000.mcr")
+            ]
+        );
+        assert_eq!(
+            locator::scan_dir(&dir, 0),
+            vec![dir.join("PS1CARD.VM1"), dir.join("epsxe000.mcr")]
+        );
+
+        for e in locator::Emulator::ALL {
+            assert!(!e.card_dirs().is_empty(), "{}", e);
+        }
+    }
+
+    #[test]
+    fn patch_save() {
+        let mut m = MemCard::open(test_card("psxmem_patch_save.mcr")).unwrap();
+        let save = m.saves().nth(1).unwrap();
+        let (before, icons) = (save.payload().unwrap(), save.icon_frames().len());
+        let len = before.len();
+
+        // A patch across the boundary of the first and second block of the save
+        let offset = BLOCK_SIZE - (1 + icons) * FRAME_SIZE - 2;
+        m.patch_save(1, offset, b"psxmem").unwrap();
+        let save = m.saves().nth(1).unwrap();
+        assert_eq!(save.read_at(offset, 6).unwrap(), b"psxmem");
+        assert_eq!(save.read_at(0, 4).unwrap(), before[..4]);
+        let mut b = [0u8; BLOCK_SIZE];
+        m.data[2].write_raw(&mut b).unwrap();
+        assert_eq!(b[..4], b"xmem"[..]);
+
+        assert!(m.patch_save(1, len - 1, b"ab").is_err());
+        assert!(m.patch_save(1, usize::MAX, b"ab").is_err());
+        assert!(m.saves().nth(1).unwrap().read_at(len, 1).is_err());
+        assert!(m.patch_save(2, 0, b"ab").is_err());
+        m.patch_save(1, len - 2, b"ab").unwrap();
+        assert_eq!(
+            m.saves().nth(1).unwrap().read_at(len - 2, 2).unwrap(),
+            b"ab"
+        );
+
+        let mut h = SaveHandle::new(Arc::new(m), 4).unwrap();
+        h.patch(0, &[0x42]).unwrap();
+        assert_eq!(h.save().read_at(0, 1).unwrap(), [0x42]);
+    }
+
+    #[test]
+    fn grep_saves() {
+        let mut m = MemCard::open(test_card("psxmem_grep_saves.mcr")).unwrap();
+        m.patch_save(1, 100, &[0x12, 0x34, 0x56]).unwrap();
+        m.patch_save(4, 8000, &[0x12, 0x3f, 0x56, 0x12, 0x34])
+            .unwrap();
+
+        let exact: BytePattern = "12 34 56".parse().unwrap();
+        assert_eq!(exact, BytePattern::new(&[0x12, 0x34, 0x56]));
+        assert_eq!(m.saves().nth(1).unwrap().find(&exact).unwrap(), vec![100]);
+
+        let masked: BytePattern = "12 3? 56".parse().unwrap();
+        assert_eq!(masked.to_string(), "12 3? 56");
+        assert_eq!(
+            masked,
+            BytePattern::with_mask(&[0x12, 0x30, 0x56], &[0xff, 0xf0, 0xff]).unwrap()
+        );
+        let found = m.grep(&masked).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                SearchMatch {
+                    index: 1,
+                    filename: "BASLUS-00001SAVE01".to_string(),
+                    offset: 100,
+                },
+                SearchMatch {
+                    index: 4,
+                    filename: "BASLUS-00002SAVE02".to_string(),
+                    offset: 8000,
+                },
+            ]
+        );
+        assert_eq!(found[1].to_string(), " 5 BASLUS-00002SAVE02 0x1f40");
+
+        let any: BytePattern = "12 ??".parse().unwrap();
+        assert!(any.matches(&[0x12, 0x00]));
+        assert!(m
+            .saves()
+            .nth(2)
+            .unwrap()
+            .find(&any)
+            .unwrap()
+            .contains(&8003));
+        assert!("12 3".parse::<BytePattern>().is_err());
+        assert!("zz".parse::<BytePattern>().is_err());
+        assert!(BytePattern::with_mask(&[1], &[]).is_err());
+        assert!(BytePattern::new(&[]).find_in(&[1, 2]).is_empty());
+    }
+
+    #[test]
+    fn card_slots() {
+        let dir = temp_path("psxmem_card_slots");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy(test_card("psxmem_card_slots.mcr"), dir.join("epsxe000.mcr")).unwrap();
+
+        // The missing second card is created on the first write
+        let mut slots = CardSlots::epsxe(&dir).unwrap();
+        assert_eq!(slots.path(1), dir.join("epsxe001.mcr"));
+        assert_eq!(slots.changed(), vec![1]);
+        assert_eq!(slots[1].saves().count(), 0);
+
+        assert_eq!(slots.copy_save(0, 1).unwrap(), 0);
+        assert_eq!(slots.move_save(0, 4).unwrap(), 3);
+        assert!(slots.copy_save(0, 4).is_err());
+        assert_eq!(slots.changed(), vec![0, 1]);
+        assert_eq!(slots.write(WriteOptions::default()).unwrap(), vec![0, 1]);
+        assert!(slots.changed().is_empty());
+        assert!(slots.write(WriteOptions::default()).unwrap().is_empty());
+
+        let slots = CardSlots::epsxe(&dir).unwrap();
+        assert!(slots.changed().is_empty());
+        assert_eq!(slots[0].saves().count(), 2);
+        assert_eq!(slots[1].saves().count(), 2);
+
+        let opts = OpenOptions {
+            read_only: true,
+            ..Default::default()
+        };
+        let mut slots =
+            CardSlots::open_with(dir.join("epsxe000.mcr"), dir.join("epsxe001.mcr"), opts).unwrap();
+        assert!(matches!(slots.move_save(1, 0), Err(MCError::ReadOnly)));
+        assert_eq!(slots[1].saves().count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_cursor() {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        let mut m = MemCard::open(test_card("psxmem_save_cursor.mcr")).unwrap();
+        let mut c = m.save_cursor(1).unwrap();
+        assert_eq!(c.len(), (3 * BLOCK_SIZE - 2 * FRAME_SIZE) as u64);
+
+        c.seek(SeekFrom::Start(BLOCK_SIZE as u64)).unwrap();
+        c.write_all(b"psxmem").unwrap();
+        c.seek(SeekFrom::Current(-6)).unwrap();
+        let mut buf = [0u8; 6];
+        c.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"psxmem");
+
+        c.seek(SeekFrom::End(-2)).unwrap();
+        assert!(c.write_all(b"abc").is_err());
+        assert!(c.seek(SeekFrom::Current(-(c.len() as i64) - 1)).is_err());
+
+        // Positions far past the end read and write nothing
+        assert_eq!(c.seek(SeekFrom::Start(u64::MAX)).unwrap(), u64::MAX);
+        assert_eq!(c.read(&mut buf).unwrap(), 0);
+        assert_eq!(c.write(b"abc").unwrap(), 0);
+        assert!(c.seek(SeekFrom::Current(1)).is_err());
+        assert!(c.seek(SeekFrom::End(i64::MAX)).is_ok());
+        assert_eq!(c.read(&mut buf).unwrap(), 0);
+        c.flush().unwrap();
+        drop(c);
+
+        // The payload starts after the title frame and one icon frame of the first block
+        let ofs = BLOCK_SIZE + 2 * FRAME_SIZE;
+        let raw = m.block(3).unwrap();
+        assert_eq!(&raw.data[ofs - BLOCK_SIZE..][..6], b"psxmem");
+        assert!(m.info.validate_checksums().is_empty());
+        assert_eq!(
+            m.saves().find(|s| s.index == 1).unwrap().title().unwrap(),
+            "FINAL FANTASY VII"
+        );
+
+        assert!(m.save_cursor(5).is_err());
+    }
+
+    #[test]
+    fn export_csv() {
+        let mut m = MemCard::open(test_card("psxmem_export_csv.mcr")).unwrap();
+        m.delete_save(4).unwrap();
+
+        let mut out = Vec::<u8>::new();
+        m.export_csv(&mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], "slot,serial,title,region,blocks,size,state");
+        assert_eq!(
+            rows[2],
+            "2,SLUS-00001,FINAL FANTASY VII,America,3,24576,AllocFirst"
+        );
+        assert_eq!(
+            rows[3],
+            "5,SLUS-00002,Gran Turismo,America,2,16384,FreeFirst"
+        );
+    }
+
+    #[test]
+    fn export_markdown() {
+        let card = CardGenerator::new()
+            .save(SaveSpec::new("WILD ARMS", 1))
+            .save(SaveSpec {
+                filename: Some("BESLES-00002SAVE".into()),
+                ..SaveSpec::new("GRAN TURISMO 2", 2)
+            })
+            .generate()
+            .unwrap();
+        let m = MemCard::from_reader(&card[..]).unwrap();
+
+        let mut out = Vec::<u8>::new();
+        m.export_markdown(&mut out, None).unwrap();
+        let md = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = md.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], "| Slot | Title | Serial | Region | Blocks |");
+        assert_eq!(rows[3], "| 2 | GRAN TURISMO 2 | SLES-00002 | Europe | 2 |");
+
+        let dir = temp_path("psxmem_export_markdown");
+        let _ = std::fs::create_dir(&dir);
+        m.write_thumbnails(&dir).unwrap();
+        let mut out = Vec::<u8>::new();
+        m.export_markdown(&mut out, Some("icons/")).unwrap();
+        let md = String::from_utf8(out).unwrap();
+        let row = md.lines().nth(2).unwrap();
+        let name = m.saves().next().unwrap().filename();
+        assert!(row.contains(&format!("](icons/{}.png) |", name)));
+        assert!(dir.join(format!("{}.png", name)).is_file());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn icon_source() {
+        let m = MemCard::open(test_card("psxmem_icon_source.mcr")).unwrap();
+        let d = &m[0];
+
+        let mut out = Vec::<u8>::new();
+        d.write_icon_source(SourceLang::C, "wild-arms", &mut out)
+            .unwrap();
+        let c = String::from_utf8(out).unwrap();
+        assert!(c.contains("#define WILD_ARMS_ICON_FRAMES 3\n"));
+        assert!(c.contains("static const unsigned short wild_arms_clut[16] = {\n"));
+        assert!(c.contains(&format!("    {:#06x}, ", d.title_frame.icon_palette[0])));
+        assert!(c.contains("static const unsigned char wild_arms_icon[3][128] = {\n"));
+        assert!(c.trim_end().ends_with("#endif"));
+
+        let mut out = Vec::<u8>::new();
+        d.write_icon_source(SourceLang::Rust, "1st", &mut out)
+            .unwrap();
+        let rs = String::from_utf8(out).unwrap();
+        assert!(rs.contains("pub const X1ST_CLUT: [u16; 16] = [\n"));
+        assert!(rs.contains("pub const X1ST_ICON: [[u8; 128]; 3] = [\n"));
+        let first = d.icon_frames[0].data[..16]
+            .iter()
+            .map(|b| format!("{:#04x}", b))
+            .collect::<Vec<_>>()
+            .join(", ");
+        assert!(rs.contains(&format!("    [\n        {},\n", first)));
+
+        let card = CardGenerator::new()
+            .save(SaveSpec {
+                icon_frames: 0,
+                ..SaveSpec::new("NO ICON", 1)
+            })
+            .generate()
+            .unwrap();
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        assert!(m[0]
+            .write_icon_source(SourceLang::C, "none", &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn icon_raw() {
+        let mut m = MemCard::open(test_card("psxmem_icon_raw.mcr")).unwrap();
+        let (pixels, clut) = (m[0].icon_pixels(), m[0].icon_clut());
+        assert_eq!(pixels.len(), 3 * FRAME_SIZE);
+        assert_eq!(
+            u16::from_le_bytes([clut[2], clut[3]]),
+            m[0].title_frame.icon_palette[1]
+        );
+
+        // Round trip another save's icon onto the first save, losslessly
+        let png = m[1].icon_png(0).unwrap();
+        let other = m[1].icon_pixels();
+        let mut swapped = other[..FRAME_SIZE].to_vec();
+        swapped.extend_from_slice(&pixels[FRAME_SIZE..]);
+        m.set_icon_raw(0, &swapped, &m[1].icon_clut()).unwrap();
+        assert_eq!(m[0].icon_png(0).unwrap(), png);
+        assert_eq!(m[0].icon_clut(), m[1].icon_clut());
+        assert_eq!(m[0].icon_pixels()[FRAME_SIZE..], pixels[FRAME_SIZE..]);
+
+        let mut b = [0u8; BLOCK_SIZE];
+        m.data[0].write_raw(&mut b).unwrap();
+        assert_eq!(DataBlock::from_raw(&b).icon_pixels(), swapped);
+
+        assert!(m.set_icon_raw(0, &swapped[..FRAME_SIZE], &clut).is_err());
+        assert!(m.set_icon_raw(0, &swapped, &clut[..16]).is_err());
+        assert!(m.set_icon_raw(2, &swapped, &clut).is_err());
+        m.set_read_only(true);
+        assert!(m.set_icon_raw(0, &swapped, &clut).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn validation_report_json() {
+        let card = CardGenerator::new()
+            .save(SaveSpec::new("WILD ARMS", 1))
+            .defect(Defect::BadChecksum { block: 0, frame: 3 })
+            .generate()
+            .unwrap();
+        let opts = OpenOptions {
+            skip_checksums: true,
+            ..Default::default()
+        };
+        let mut m = MemCard::from_reader_with(&card[..], opts).unwrap();
+        m.info.dir_frames[0].next_block = 20;
+
+        let report = m.validate();
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"findings":[{"kind":"bad_checksum","block":0,"frame":1},"#,
+                r#"{"kind":"bad_checksum","block":0,"frame":3},"#,
+                r#"{"kind":"chain_out_of_range","start":0,"block":0,"next":20}]}"#
+            )
+        );
+        assert_eq!(
+            serde_json::from_str::<ValidationReport>(&json).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn game_save_codec() {
+        // A codec with a 32-bit counter at offset 4, and a byte sum of the counter at offset 0
+        struct Counter;
+
+        impl GameSaveCodec for Counter {
+            fn name(&self) -> &str {
+                "counter"
+            }
+
+            fn matches(&self, serial: &str) -> bool {
+                serial == "SLUS-00001"
+            }
+
+            fn decode(&self, payload: &[u8]) -> Result<SaveFields, MCError> {
+                let v = u32::from_le_bytes(payload[4..8].try_into().unwrap());
+                Ok(SaveFields::from([(
+                    "count".to_string(),
+                    FieldValue::Int(v as i64),
+                )]))
+            }
+
+            fn encode(&self, fields: &SaveFields, payload: &mut [u8]) -> Result<(), MCError> {
+                match fields.get("count") {
+                    Some(FieldValue::Int(v)) => {
+                        payload[4..8].copy_from_slice(&(*v as u32).to_le_bytes())
+                    }
+                    _ => return Err(MCError::InvalidValue("count".to_string())),
+                }
+                Ok(())
+            }
+
+            fn fix_checksums(&self, payload: &mut [u8]) -> Result<(), MCError> {
+                payload[0] = payload[4..8].iter().fold(0u8, |a, b| a.wrapping_add(*b));
+                Ok(())
+            }
+        }
+
+        let mut registry = CodecRegistry::new();
+        registry.register(Counter);
+        assert_eq!(registry.find("SLUS-00001").unwrap().name(), "counter");
+        assert!(registry.find("SLUS-00000").is_none());
+
+        let mut m = MemCard::open(test_card("psxmem_game_save_codec.mcr")).unwrap();
+        registry
+            .edit(&mut m, 1, |f| {
+                f.insert("count".to_string(), FieldValue::Int(0x01020304));
+            })
+            .unwrap();
+        assert!(registry.edit(&mut m, 0, |_| ()).is_err());
+
+        let save = m.saves().find(|s| s.index == 1).unwrap();
+        let fields = registry.decode(&save).unwrap().unwrap();
+        assert_eq!(fields["count"], FieldValue::Int(0x01020304));
+        assert_eq!(save.payload().unwrap()[0], 10);
+        assert_eq!(registry.decode(&m.saves().next().unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    fn frame_checksum_trait() {
+        // A 16-bit big endian sum over 32 byte units, stored in the first two bytes
+        struct Sum16;
+
+        impl FrameChecksum for Sum16 {
+            type Value = u16;
+
+            fn unit_size(&self) -> usize {
+                32
+            }
+
+            fn calc(&self, d: &[u8]) -> u16 {
+                d[2..32].iter().fold(0, |s, b| s.wrapping_add(*b as u16))
+            }
+
+            fn stored(&self, d: &[u8]) -> u16 {
+                u16::from_be_bytes([d[0], d[1]])
+            }
+
+            fn store(&self, d: &mut [u8], v: u16) {
+                d[..2].copy_from_slice(&v.to_be_bytes());
+            }
+        }
+
+        let mut d = [0xffu8; 96];
+        assert_eq!(Sum16.invalid_units(&d), vec![0, 1, 2]);
+        Sum16.update(&mut d[32..64]);
+        assert_eq!(Sum16.stored(&d[32..]), 30 * 0xff);
+        assert_eq!(Sum16.invalid_units(&d), vec![0, 2]);
+        assert!(matches!(Sum16.validate(&d), Err(MCError::BadChecksum)));
+
+        let mut f = [7u8; FRAME_SIZE * 2];
+        f[0] = 1;
+        f[FRAME_SIZE] = 1;
+        XorFrame.update(&mut f[FRAME_SIZE..]);
+        assert_eq!(XorFrame.invalid_units(&f), vec![0]);
+        assert_eq!(
+            XorFrame.calc(&f[..FRAME_SIZE]),
+            calc_checksum(&f[..FRAME_SIZE])
+        );
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn digests() {
+        let m = MemCard::open(test_card("psxmem_digests.mcr")).unwrap();
+        // The same as sha256sum of the image of a freshly formatted card
+        let empty = MemCard::new().digest().unwrap();
+        let hex: String = empty.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "2a359869fa6e5a74f9355fbc0039c21b90fd94455c10c838bd975893ee9ae2d5"
+        );
+        assert_ne!(m.digest().unwrap(), empty);
+
+        // The same save has the same digest wherever it is stored
+        let wa = m.saves().next().unwrap();
+        let mut other = MemCard::new();
+        other
+            .import_mcs(&m.saves().nth(2).unwrap().to_mcs().unwrap())
+            .unwrap();
+        let index = other.import_mcs(&wa.to_mcs().unwrap()).unwrap();
+        assert_ne!(index, wa.index);
+        let moved = other.saves().find(|s| s.index == index).unwrap();
+        assert_eq!(moved.digest().unwrap(), wa.digest().unwrap());
+        assert_ne!(
+            m.saves().nth(1).unwrap().digest().unwrap(),
+            wa.digest().unwrap()
+        );
+    }
+
+    #[cfg(feature = "sidecar")]
+    #[test]
+    fn sidecar() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let path = temp_path("psxmem_sidecar.mcr");
+        let _ = std::fs::remove_file(Sidecar::path(&path));
+        let mut m = MemCard::open(test_card("psxmem_sidecar_src.mcr")).unwrap();
+        let wa = "BASLUS-00000SAVE00";
+        let ff = "BASLUS-00001SAVE01";
+
+        let mut s = m.update_sidecar(&path).unwrap();
+        assert_eq!(s.saves.len(), 3);
+        assert!(s.get(wa).unwrap().created > 0);
+        s.set_notes(wa, "Before the final boss").unwrap();
+        assert!(s.set_notes("BASLUS-99999", "").is_err());
+
+        // Only the save that changed is modified
+        let t = UNIX_EPOCH + Duration::from_secs(2_000_000_000);
+        let mut b = [0u8; BLOCK_SIZE];
+        m.data[0].write_raw(&mut b).unwrap();
+        b[0x1000] ^= 1;
+        m.data[0] = DataBlock::from_raw(&b);
+        m.delete_save(4).unwrap();
+        s.update_at(&m, t).unwrap();
+        let notes = s.get(wa).unwrap();
+        assert_eq!(notes.modified, 2_000_000_000);
+        assert!(notes.created < notes.modified);
+        assert_eq!(notes.notes, "Before the final boss");
+        assert!(s.get(ff).unwrap().modified < 2_000_000_000);
+        assert_eq!(s.saves.len(), 2);
+
+        s.store(&path).unwrap();
+        assert_eq!(Sidecar::load(&path).unwrap(), s);
+        let _ = std::fs::remove_file(Sidecar::path(&path));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn save_schema() {
+        let schema = SaveSchema::from_toml(
+            r#"
+            name = "test"
+            serials = ["SLUS-00001"]
+
+            [[fields]]
+            name = "gil"
+            offset = 0x10
+            type = "u32"
+
+            [[fields]]
+            name = "level"
+            offset = 0x14
+            type = "i16"
+            endian = "big"
+
+            [[fields]]
+            name = "hero"
+            offset = 0x20
+            type = "text"
+            len = 8
+            "#,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(SaveSchema::from_json(&json).unwrap(), schema);
+        assert!(SaveSchema::from_json(
+            r#"{"name": "x", "serials": [], "fields": [{"name": "a", "offset": 0, "type": "text"}]}"#
+        )
+        .is_err());
+
+        let mut m = MemCard::open(test_card("psxmem_save_schema.mcr")).unwrap();
+        m.set_save_field(1, &schema, "gil", &FieldValue::Int(123456))
+            .unwrap();
+        m.set_save_field(1, &schema, "level", &FieldValue::Int(-2))
+            .unwrap();
+        m.set_save_field(1, &schema, "hero", &FieldValue::Text("Cloud".into()))
+            .unwrap();
+        assert!(m
+            .set_save_field(1, &schema, "level", &FieldValue::Int(40000))
+            .is_err());
+        assert!(m
+            .set_save_field(1, &schema, "hero", &FieldValue::Int(1))
+            .is_err());
+        assert!(m
+            .set_save_field(1, &schema, "mp", &FieldValue::Int(1))
+            .is_err());
+
+        let save = m.saves().nth(1).unwrap();
+        assert_eq!(save.read_at(0x14, 2).unwrap(), [0xff, 0xfe]);
+        assert_eq!(
+            save.get_field(&schema, "level").unwrap(),
+            FieldValue::Int(-2)
+        );
+        assert_eq!(
+            save.get_field(&schema, "hero").unwrap(),
+            FieldValue::Text("Cloud".into())
+        );
+
+        let mut codecs = CodecRegistry::new();
+        codecs.register(schema);
+        let fields = codecs.decode(&save).unwrap().unwrap();
+        assert_eq!(fields["gil"], FieldValue::Int(123456));
+        assert!(codecs.decode(&m.saves().next().unwrap()).unwrap().is_none());
+    }
+
+    #[cfg(feature = "dexdrive")]
+    #[test]
+    fn dexdrive_transfers() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::sync::Mutex;
+
+        fn read<const N: usize>(s: &mut TcpStream) -> [u8; N] {
+            let mut b = [0u8; N];
+            s.read_exact(&mut b).unwrap();
+            b
+        }
+
+        // A DexDrive holding one card, which corrupts the first frame it sends
+        let image = std::fs::read(test_card("psxmem_dexdrive_transfers.mcr")).unwrap();
+        let stored = Arc::new(Mutex::new(image));
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = stored.clone();
+        std::thread::spawn(move || {
+            let (mut s, _) = server.accept().unwrap();
+            s.set_nodelay(true).unwrap();
+            let mut corrupt = true;
+            loop {
+                let mut cmd = [0u8; 4];
+                if s.read_exact(&mut cmd).is_err() {
+                    return;
+                }
+                assert_eq!(&cmd[..3], b"IAI");
+                match cmd[3] {
+                    0x00 => {
+                        read::<17>(&mut s);
+                        s.write_all(b"IAI\x40\x00PSX\x41").unwrap();
+                    }
+                    0x07 => {
+                        read::<1>(&mut s);
+                    }
+                    0x27 => (),
+                    0x02 => {
+                        let [msb, lsb] = read::<2>(&mut s);
+                        let n = u16::from_be_bytes([msb, lsb]) as usize;
+                        let mut frame = shared.lock().unwrap()[n * 128..][..128].to_vec();
+                        let xor = frame.iter().fold(msb ^ lsb, |c, b| c ^ b);
+                        if std::mem::take(&mut corrupt) {
+                            frame[5] ^= 0x10;
+                        }
+                        s.write_all(b"IAI\x41").unwrap();
+                        s.write_all(&frame).unwrap();
+                        s.write_all(&[xor]).unwrap();
+                    }
+                    0x04 => {
+                        let [msb, lsb, rlsb, rmsb] = read::<4>(&mut s);
+                        assert_eq!((rmsb, rlsb), (msb.reverse_bits(), lsb.reverse_bits()));
+                        let frame = read::<128>(&mut s);
+                        let [xor] = read::<1>(&mut s);
+                        let init = msb ^ lsb ^ rmsb ^ rlsb;
+                        assert_eq!(frame.iter().fold(init, |c, b| c ^ b), xor);
+                        let n = u16::from_be_bytes([msb, lsb]) as usize;
+                        shared.lock().unwrap()[n * 128..][..128].copy_from_slice(&frame);
+                        s.write_all(b"IAI\x28").unwrap();
+                    }
+                    c => panic!("unexpected command {:#04x}", c),
+                }
+            }
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut dex = DexDrive::new(stream).unwrap();
+        assert_eq!(dex.firmware(), 0x41);
+
+        let mut card = dex.read_card().unwrap();
+        assert_eq!(card.saves().count(), 3);
+
+        card.delete_save(0).unwrap();
+        dex.write_card(&card).unwrap();
+        let mut expected = Vec::new();
+        card.write_to(&mut expected).unwrap();
+        assert_eq!(*stored.lock().unwrap(), expected);
+    }
+
+    #[cfg(feature = "unirom")]
+    #[test]
+    fn unirom_transfers() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::sync::Mutex;
+
+        fn sum(d: &[u8]) -> u32 {
+            d.iter().fold(0, |s, b| s.wrapping_add(*b as u32))
+        }
+
+        fn read4(s: &mut TcpStream) -> [u8; 4] {
+            let mut b = [0u8; 4];
+            s.read_exact(&mut b).unwrap();
+            b
+        }
+
+        // A console running Unirom with one card, which corrupts the first chunk it sends and
+        // rejects the first chunk it receives, to exercise the V2 retries
+        let image = std::fs::read(test_card("psxmem_unirom_transfers.mcr")).unwrap();
+        let stored = Arc::new(Mutex::new(image.clone()));
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = stored.clone();
+        let console = std::thread::spawn(move || {
+            let (mut s, _) = server.accept().unwrap();
+            s.set_nodelay(true).unwrap();
+            let (mut corrupt, mut reject) = (true, true);
+            loop {
+                let mut cmd = [0u8; 4];
+                if s.read_exact(&mut cmd).is_err() {
+                    return;
+                }
+                s.write_all(b"debug text OKV2").unwrap();
+                assert_eq!(&read4(&mut s), b"UPV2");
+                s.write_all(b"OKAY").unwrap();
+
+                match &cmd {
+                    b"MCDN" => {
+                        assert_eq!(u32::from_le_bytes(read4(&mut s)), 1);
+                        s.write_all(b"MCRD").unwrap();
+                        s.write_all(&0x8010_0000u32.to_le_bytes()).unwrap();
+                        s.write_all(&(image.len() as u32).to_le_bytes()).unwrap();
+                    }
+                    b"DUMP" => {
+                        assert_eq!(u32::from_le_bytes(read4(&mut s)), 0x8010_0000);
+                        let size = u32::from_le_bytes(read4(&mut s)) as usize;
+                        let data = shared.lock().unwrap()[..size].to_vec();
+                        for chunk in data.chunks(2048) {
+                            loop {
+                                let mut sent = chunk.to_vec();
+                                if std::mem::take(&mut corrupt) {
+                                    sent[0] ^= 0xff;
+                                }
+                                s.write_all(&sent).unwrap();
+                                assert_eq!(&read4(&mut s), b"CHEK");
+                                s.write_all(&sum(chunk).to_le_bytes()).unwrap();
+                                if &read4(&mut s) == b"MORE" {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    b"MCUP" => {
+                        assert_eq!(u32::from_le_bytes(read4(&mut s)), 0);
+                        let size = u32::from_le_bytes(read4(&mut s)) as usize;
+                        let total = u32::from_le_bytes(read4(&mut s));
+                        let mut data = vec![0u8; size];
+                        for chunk in data.chunks_mut(2048) {
+                            loop {
+                                s.read_exact(chunk).unwrap();
+                                s.write_all(b"CHEK").unwrap();
+                                let ok = u32::from_le_bytes(read4(&mut s)) == sum(chunk);
+                                match ok && !std::mem::take(&mut reject) {
+                                    true => {
+                                        s.write_all(b"MORE").unwrap();
+                                        break;
+                                    }
+                                    false => s.write_all(b"ERR!").unwrap(),
+                                }
+                            }
+                        }
+                        assert_eq!(sum(&data), total);
+                        *shared.lock().unwrap() = data;
+                    }
+                    _ => panic!("unexpected command {:?}", cmd),
+                }
+            }
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut u = Unirom::new(stream);
+
+        let mut card = u.read_card(1).unwrap();
+        assert_eq!(card.saves().count(), 3);
+
+        card.delete_save(0).unwrap();
+        u.write_card(0, &card).unwrap();
+
+        // The console stores the upload after acknowledging the last chunk
+        drop(u);
+        console.join().unwrap();
+        let mut expected = Vec::new();
+        card.write_to(&mut expected).unwrap();
+        assert_eq!(*stored.lock().unwrap(), expected);
+    }
+
+    #[cfg(feature = "unirom")]
+    #[test]
+    fn unirom_rejects_bad_size() {
+        use std::io::{Cursor, Read, Write};
+
+        // A console that acknowledges every command, then reports a 4 GiB card
+        struct Console {
+            input: Cursor<Vec<u8>>,
+            output: Vec<u8>,
+        }
+
+        impl Read for Console {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.input.read(buf)
+            }
+        }
+
+        impl Write for Console {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.output.write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut input = b"OKAYMCRD".to_vec();
+        input.extend_from_slice(&0x8010_0000u32.to_le_bytes());
+        input.extend_from_slice(&u32::MAX.to_le_bytes());
+        let mut u = Unirom::new(Console {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        });
+
+        assert!(matches!(u.read_raw(0), Err(MCError::InvalidValue(_))));
+        let sent = u.into_inner().output;
+        assert!(!sent.windows(4).any(|w| w == b"DUMP"));
+    }
+
+    #[cfg(feature = "ps3mca")]
+    #[test]
+    fn ps3_adaptor_commands() {
+        use crate::ps3mca::{parse_read, parse_write, read_command, write_command};
+
+        // The adaptor passes the card bus bytes through to the card
+        fn exchange(sio: &mut SioCard, cmd: &[u8]) -> Vec<u8> {
+            assert_eq!(cmd[..2], [0xaa, 0x42]);
+            let len = u16::from_le_bytes([cmd[2], cmd[3]]) as usize;
+            assert_eq!(cmd.len(), 4 + len);
+
+            let mut resp = vec![0x55, 0x5a, cmd[2], cmd[3]];
+            sio.select();
+            resp.extend(cmd[4..].iter().map(|b| sio.transfer(*b).data));
+            sio.deselect();
+            resp
+        }
+
+        let m = MemCard::open(test_card("psxmem_ps3_adaptor.mcr")).unwrap();
+        let mut sio = SioCard::new(&m).unwrap();
+
+        let frame = parse_read(&exchange(&mut sio, &read_command(1)), 1).unwrap();
+        assert_eq!(frame[..], sio.image()[FRAME_SIZE..2 * FRAME_SIZE]);
+
+        let frame = [0x5au8; FRAME_SIZE];
+        parse_write(&exchange(&mut sio, &write_command(200, &frame))).unwrap();
+        let read = parse_read(&exchange(&mut sio, &read_command(200)), 200).unwrap();
+        assert_eq!(read, frame);
+
+        // Frames past the end of the card
+        let resp = exchange(&mut sio, &read_command(1024));
+        assert!(matches!(
+            parse_read(&resp, 1024),
+            Err(MCError::InvalidValue(_))
+        ));
+        let resp = exchange(&mut sio, &write_command(1024, &frame));
+        assert!(parse_write(&resp).is_err());
+        assert!(parse_read(&resp[..10], 1).is_err());
+    }
+
+    #[test]
+    fn sio_card() {
+        let m = MemCard::open(test_card("psxmem_sio_card.mcr")).unwrap();
+        let mut sio = SioCard::new(&m).unwrap();
+
+        // Exchange a whole command, checking that only the last byte is not acknowledged
+        fn command(sio: &mut SioCard, bytes: &[u8]) -> Vec<u8> {
+            sio.select();
+            let replies: Vec<SioReply> = bytes.iter().map(|b| sio.transfer(*b)).collect();
+            sio.deselect();
+            let last = replies.len() - 1;
+            assert!(replies[..last].iter().all(|r| r.ack));
+            assert!(!replies[last].ack);
+            replies.iter().map(|r| r.data).collect()
+        }
+
+        // Another device on the bus
+        sio.select();
+        assert!(!sio.transfer(0x01).ack);
+
+        let id = command(&mut sio, &[0x81, b'S', 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            id[1..],
+            [0x08, 0x5a, 0x5d, 0x5c, 0x5d, 0x04, 0x00, 0x00, 0x80]
+        );
+
+        // Read the directory frame of the first save
+        let mut read = vec![0x81, b'R', 0, 0, 0x00, 0x01];
+        read.resize(6 + 4 + FRAME_SIZE + 2, 0);
+        let r = command(&mut sio, &read);
+        assert_eq!(r[2..10], [0x5a, 0x5d, 0x00, 0x00, 0x5c, 0x5d, 0x00, 0x01]);
+        assert_eq!(
+            r[10..10 + FRAME_SIZE],
+            sio.image()[FRAME_SIZE..2 * FRAME_SIZE]
+        );
+        let chk = r[10..10 + FRAME_SIZE].iter().fold(0x01, |c, b| c ^ b);
+        assert_eq!(r[10 + FRAME_SIZE..], [chk, b'G']);
+
+        // A sector past the end of the card ends the command early
+        let r = command(&mut sio, &[0x81, b'R', 0, 0, 0x04, 0x00, 0, 0, 0, 0]);
+        assert_eq!(r[6..], [0x5c, 0x5d, 0xff, 0xff]);
+
+        // Write the first frame of the second save block, first with a bad checksum
+        let frame = [0x33u8; FRAME_SIZE];
+        let sector = (2 * FRAMES_PER_BLOCK) as u16;
+        let [msb, lsb] = sector.to_be_bytes();
+        let chk = frame.iter().fold(msb ^ lsb, |c, b| c ^ b);
+        let mut write = vec![0x81, b'W', 0, 0, msb, lsb];
+        write.extend_from_slice(&frame);
+        write.extend_from_slice(&[chk ^ 1, 0, 0, 0]);
+        let r = command(&mut sio, &write);
+        assert_eq!(r[r.len() - 3..], [0x5c, 0x5d, b'N']);
+        assert!(!sio.is_dirty());
+
+        write[6 + FRAME_SIZE] = chk;
+        let r = command(&mut sio, &write);
+        assert_eq!(r[1], 0x08 | 0x04);
+        assert_eq!(r[5..7], [msb, lsb]);
+        assert_eq!(r[r.len() - 3..], [0x5c, 0x5d, b'G']);
+        assert!(sio.is_dirty());
+
+        // The flag is cleared by a good write
+        let id = command(&mut sio, &[0x81, b'S', 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(id[1], 0x00);
+
+        let mut out = Vec::new();
+        sio.to_card().unwrap().write_to(&mut out).unwrap();
+        assert_eq!(out[sector as usize * FRAME_SIZE..][..FRAME_SIZE], frame);
+    }
+
+    #[test]
+    fn sync_merge() {
+        use crate::sync::{merge, merge_files, SyncAction, SyncSide};
+        use std::io::Write;
+        use std::time::{Duration, SystemTime};
+
+        fn mcs(card: &MemCard, index: usize) -> Vec<u8> {
+            let save = card.saves().find(|s| s.index == index).unwrap();
+            save.to_mcs().unwrap()
+        }
+
+        let local = MemCard::open(test_card("psxmem_sync_merge.mcr")).unwrap();
+        let names: Vec<String> = local.saves().map(|s| s.filename()).collect();
+
+        // The remote copy lost the first save, and played the third one further. Moving the
+        // second save keeps it the same, even though its links change.
+        let mut remote = local.clone();
+        remote.delete_save(0).unwrap();
+        let ff7 = mcs(&remote, 1);
+        remote.delete_save(1).unwrap();
+        remote.import_mcs(&ff7).unwrap();
+        let mut c = remote.save_cursor(4).unwrap();
+        c.write_all(b"more progress").unwrap();
+        drop(c);
+
+        let t = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let (merged, report) = merge(&local, t, &remote, t + Duration::from_secs(60)).unwrap();
+        let actions: Vec<(&str, SyncAction)> = report
+            .entries
+            .iter()
+            .map(|e| (e.filename.as_str(), e.action))
+            .collect();
+        let mut expected = vec![
+            (names[0].as_str(), SyncAction::Only(SyncSide::Local)),
+            (names[1].as_str(), SyncAction::Unchanged),
+            (names[2].as_str(), SyncAction::Newer(SyncSide::Remote)),
+        ];
+        expected.sort_by_key(|e| e.0);
+        assert_eq!(actions, expected);
+        assert_eq!(report.conflicts().count(), 1);
+        assert_eq!(merged.saves().count(), 3);
+        let gt = merged.saves().find(|s| s.filename() == names[2]).unwrap();
+        assert!(gt.payload().unwrap().starts_with(b"more progress"));
+
+        // With the local card newer, its copy wins
+        let (merged, report) = merge(&local, t, &remote, t).unwrap();
+        assert_eq!(report.entries[2].action, SyncAction::Newer(SyncSide::Local));
+        let gt = merged.saves().find(|s| s.filename() == names[2]).unwrap();
+        assert!(!gt.payload().unwrap().starts_with(b"more progress"));
+
+        // Saves that do not fit are dropped
+        let mut full = local.clone();
+        while full.import_mcs(&mcs(&local, 0)).is_ok() {}
+        let mut other = local.clone();
+        let mut renamed = mcs(&local, 1);
+        renamed[21] = b'9';
+        update_checksum(&mut renamed[..FRAME_SIZE]).unwrap();
+        other.import_mcs(&renamed).unwrap();
+        let (_, report) = merge(&full, t + Duration::from_secs(1), &other, t).unwrap();
+        assert!(!report.is_clean());
+        assert!(report
+            .entries
+            .iter()
+            .any(|e| e.action == SyncAction::Dropped(SyncSide::Remote)));
+
+        // Files are compared by modification time
+        let dir = std::env::temp_dir();
+        let (lp, rp) = (
+            dir.join("psxmem_sync_local.mcr"),
+            dir.join("psxmem_sync_remote.mcr"),
+        );
+        local.write(&lp).unwrap();
+        remote.write(&rp).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&lp)
+            .unwrap()
+            .set_modified(t)
+            .unwrap();
+        let (_, report) = merge_files(&lp, &rp).unwrap();
+        assert_eq!(
+            report.entries[2].action,
+            SyncAction::Newer(SyncSide::Remote)
+        );
+    }
+
+    #[test]
+    fn offset_map() {
+        let m = MemCard::open(test_card("psxmem_offset_map.mcr")).unwrap();
+        let map = m.offset_map();
+
+        let mut end = 0;
+        for r in &map {
+            assert_eq!(r.range.start, end);
+            end = r.range.end;
+        }
+        assert_eq!(end, CardGeometry::STANDARD.size());
+
+        let at = |offset: usize| map.iter().find(|r| r.range.contains(&offset)).unwrap();
+        assert_eq!(at(0).kind, RegionKind::Header);
+        assert_eq!(at(0x80).to_string(), "0x00080-0x000ff directory frame 0");
+        assert_eq!(at(16 * FRAME_SIZE).kind, RegionKind::Broken(0));
+        assert_eq!(at(BLOCK_SIZE - 1).kind, RegionKind::WriteTest);
+
+        // The first save has three icon frames, the second save spans three blocks
+        assert_eq!(at(BLOCK_SIZE).kind, RegionKind::Title { block: 1 });
+        assert_eq!(
+            at(BLOCK_SIZE + 3 * FRAME_SIZE).kind,
+            RegionKind::Icon { block: 1, frame: 2 }
+        );
+        assert_eq!(
+            at(BLOCK_SIZE + 4 * FRAME_SIZE).kind,
+            RegionKind::Data { block: 1, frame: 4 }
+        );
+        assert_eq!(
+            at(2 * BLOCK_SIZE + FRAME_SIZE).kind,
+            RegionKind::Icon { block: 2, frame: 0 }
+        );
+        assert_eq!(
+            at(3 * BLOCK_SIZE).kind,
+            RegionKind::Data { block: 3, frame: 0 }
+        );
+        assert_eq!(
+            at(3 * BLOCK_SIZE).to_string(),
+            "0x06000-0x0607f block 3 data frame 0"
+        );
+    }
+
+    #[test]
+    fn repair_card() {
+        let opts = OpenOptions {
+            skip_checksums: true,
+            ..Default::default()
+        };
+        let image = CardGenerator::new()
+            .save(SaveSpec::new("ONE", 1))
+            .save(SaveSpec::new("TWO", 3))
+            .save(SaveSpec::new("THREE", 2))
+            .defect(Defect::BrokenChain { save: 0 })
+            .defect(Defect::ChainCycle { save: 1 })
+            .defect(Defect::BadChecksum {
+                block: 0,
+                frame: 40,
+            })
+            .generate()
+            .unwrap();
+        let mut m = MemCard::from_reader_with(&image[..], opts).unwrap();
+        m.info.dir_frames[4].filesize = 3 * BLOCK_SIZE as u32;
+        m.info.dir_frames[9].state = 0x77;
+        m.info.dir_frames[11].state = BAState::AllocMid as u32;
+        m.info.dir_frames[12].checksum ^= 0xff;
+        assert!(!m.validate().is_clean());
+
+        // Nothing to do without any repairs selected
+        let none = RepairOptions {
+            fix_checksums: false,
+            terminate_chains: false,
+            fix_filesizes: false,
+            clear_invalid_states: false,
+        };
+        assert_eq!(m.clone().repair(none).unwrap(), []);
+
+        let log = m.repair(RepairOptions::default()).unwrap();
+        assert_eq!(
+            log,
+            [
+                RepairAction::ClearedState {
+                    block: 9,
+                    state: 0x77
+                },
+                RepairAction::TerminatedChain {
+                    start: 0,
+                    block: 0,
+                    next: 0x00ff
+                },
+                RepairAction::TerminatedChain {
+                    start: 1,
+                    block: 3,
+                    next: 1
+                },
+                RepairAction::FreedOrphan { block: 11 },
+                RepairAction::FixedFilesize {
+                    block: 4,
+                    old: 3 * BLOCK_SIZE as u32,
+                    new: 2 * BLOCK_SIZE as u32
+                },
+                RepairAction::FixedChecksum { frame: 13 },
+                RepairAction::FixedChecksum { frame: 40 },
+            ]
+        );
+        assert_eq!(
+            log[1].to_string(),
+            "Ended the chain of block 0 at block 0, which linked to 0xff"
+        );
+        assert!(m.validate().is_clean(), "{:?}", m.validate());
+        assert_eq!(m.saves().count(), 3);
+        assert_eq!(m.repair(RepairOptions::default()).unwrap(), []);
+    }
+
+    #[test]
+    fn relocate_broken() {
+        let image = CardGenerator::new()
+            .save(SaveSpec::new("ONE", 1))
+            .save(SaveSpec::new("TWO", 3))
+            .save(SaveSpec::new("THREE", 2))
+            .generate()
+            .unwrap();
+        let mut m = MemCard::from_reader(&image[..]).unwrap();
+        let before: Vec<Vec<u8>> = m.saves().map(|s| s.to_mcs().unwrap()).collect();
+
+        // Frame 10 of the first block of ONE, with a replacement frame, frame 5 of the middle
+        // block of TWO, and a frame of a free block
+        let mut replacement = Frame::zeroed();
+        replacement.data[0] = 0x42;
+        m.info.broken_frames[0]
+            .edit(|b| b.broken_frame = 64 + 10)
+            .unwrap();
+        m.info.set_unused_frame(0, replacement).unwrap();
+        m.info.broken_frames[1]
+            .edit(|b| b.broken_frame = 3 * 64 + 5)
+            .unwrap();
+        m.info.broken_frames[2]
+            .edit(|b| b.broken_frame = 7 * 64)
+            .unwrap();
+
+        let mut full = m.clone();
+        for n in 6..15 {
+            full.info.dir_frames[n]
+                .edit(|d| d.set_alloc_state(BAState::AllocMid))
+                .unwrap();
+        }
+        assert!(matches!(full.relocate_broken(), Err(MCError::CardFull)));
+
+        let moved = m.relocate_broken().unwrap();
+        assert_eq!(
+            moved,
+            [Relocation { from: 0, to: 7 }, Relocation { from: 2, to: 8 }]
+        );
+        assert_eq!(moved[0].to_string(), "Moved block 0 to block 7");
+        assert!(m.validate().is_clean(), "{:?}", m.validate());
+        assert_eq!(
+            m.saves().map(|s| s.blocks).collect::<Vec<_>>(),
+            [vec![1, 8, 3], vec![4, 5], vec![7]]
+        );
+
+        // The broken frame of ONE was taken from its replacement, the other saves are unchanged
+        let after: Vec<Vec<u8>> = m.saves().map(|s| s.to_mcs().unwrap()).collect();
+        assert_eq!(after[..2], before[1..]);
+        let one = &m.data[7];
+        assert_eq!(
+            one.data_frames[10 - 1 - one.icon_frames.len()].data[0],
+            0x42
+        );
+
+        // Only the entry of the free block is left
+        assert_eq!(m.summary().broken_frames, 1);
+        assert_eq!(m.relocate_broken().unwrap(), []);
+    }
+
+    #[test]
+    fn wipe_card() {
+        let mut m = MemCard::open(test_card("psxmem_wipe_card.mcr")).unwrap();
+        m.delete_save(0).unwrap();
+        m.info.broken_frames[0]
+            .edit(|b| b.broken_frame = 0x40)
+            .unwrap();
+
+        let mut formatted = m.clone();
+        formatted.format().unwrap();
+        assert_eq!(formatted.saves().count(), 0);
+        assert_eq!(formatted.data, m.data);
+        assert_eq!(formatted.info, InfoBlock::new());
+
+        let raw = |m: &MemCard| {
+            let mut out = Vec::<u8>::new();
+            m.write_to(&mut out).unwrap();
+            out
+        };
+        for (pattern, fill) in [(WipePattern::Zeros, 0x00), (WipePattern::Ones, 0xff)] {
+            let mut w = m.clone();
+            w.wipe(pattern).unwrap();
+            assert!(raw(&w)[BLOCK_SIZE..].iter().all(|b| *b == fill));
+            assert_eq!(w.info, InfoBlock::new());
+            assert_eq!(w.summary().broken_frames, 0);
+            assert!(w.deleted_saves().is_empty());
+        }
+
+        let mut a = m.clone();
+        let mut b = m.clone();
+        a.wipe(WipePattern::Random).unwrap();
+        b.wipe(WipePattern::Random).unwrap();
+        assert_ne!(raw(&a)[BLOCK_SIZE..], raw(&b)[BLOCK_SIZE..]);
+        assert_eq!(a.saves().count(), 0);
+        assert!(MemCard::from_reader(&raw(&a)[..]).is_ok());
+    }
+
+    #[test]
+    fn diff_cards() {
+        let old = MemCard::open(test_card("psxmem_diff_cards.mcr")).unwrap();
+        assert!(old.diff(&old).unwrap().is_empty());
+
+        // Change two frames of FINAL FANTASY VII, move and grow Gran Turismo, drop WILD ARMS and
+        // add a new save
+        let mut new = old.clone();
+        new.data[1].data_frames[0].data[0] ^= 0xff;
+        new.data[3].data_frames[10].data[1] ^= 0xff;
+        let gt = old
+            .saves()
+            .find(|s| s.index == 4)
+            .unwrap()
+            .to_mcs()
+            .unwrap();
+        new.delete_save(0).unwrap();
+        new.delete_save(4).unwrap();
+        let mut grown = gt.clone();
+        grown[4..8].copy_from_slice(&(3 * BLOCK_SIZE as u32).to_le_bytes());
+        update_checksum(&mut grown[..FRAME_SIZE]).unwrap();
+        grown.resize(FRAME_SIZE + 3 * BLOCK_SIZE, 0x11);
+        new.import_mcs(&grown).unwrap();
+        let extra = CardGenerator::new()
+            .save(SaveSpec {
+                filename: Some("BESLES-00000EXTRA".to_string()),
+                ..SaveSpec::new("EXTRA", 1)
+            })
+            .generate()
+            .unwrap();
+        let extra = MemCard::from_reader(&extra[..]).unwrap();
+        new.import_mcs(&extra.saves().next().unwrap().to_mcs().unwrap())
+            .unwrap();
+
+        let names: Vec<String> = old.saves().map(|s| s.filename()).collect();
+        let d = old.diff(&new).unwrap();
+        let icons = old.data[1].icon_frames.len();
+        assert_eq!(
+            d.saves,
+            [
+                SaveDiff {
+                    filename: names[0].clone(),
+                    change: SaveChange::Removed,
+                },
+                SaveDiff {
+                    filename: names[1].clone(),
+                    change: SaveChange::Modified {
+                        frames: vec![
+                            ChangedFrame {
+                                block: 0,
+                                frame: 1 + icons,
+                            },
+                            ChangedFrame {
+                                block: 2,
+                                frame: 1 + new.data[3].icon_frames.len() + 10,
+                            },
+                        ],
+                    },
+                },
+                SaveDiff {
+                    filename: names[2].clone(),
+                    change: SaveChange::Modified {
+                        frames: (0..64)
+                            .map(|frame| ChangedFrame { block: 2, frame })
+                            .collect(),
+                    },
+                },
+                SaveDiff {
+                    filename: "BESLES-00000EXTRA".to_string(),
+                    change: SaveChange::Added,
+                },
+            ]
+        );
+        assert!(d
+            .to_string()
+            .starts_with(&format!("- {}\n~ {} (2 frames", names[0], names[1])));
+    }
+
+    #[test]
+    fn read_only_card() {
+        let path = test_card("psxmem_read_only_card.mcr");
+        let opts = OpenOptions {
+            read_only: true,
+            ..Default::default()
+        };
+        let mut m = MemCard::open_with(&path, opts).unwrap();
+        assert!(m.is_read_only());
+        assert!(!MemCard::open(&path).unwrap().is_read_only());
+        assert!(LazyMemCard::open_with(&path, opts)
+            .unwrap()
+            .into_memcard()
+            .unwrap()
+            .is_read_only());
+
+        let mcs = m.saves().next().unwrap().to_mcs().unwrap();
+        let original = m.clone();
+        let read_only = |r: Result<(), MCError>| matches!(r, Err(MCError::ReadOnly));
+        assert!(read_only(m.write(&path)));
+        assert!(read_only(m.delete_save(0)));
+        assert!(read_only(m.import_mcs(&mcs).map(|_| ())));
+        assert!(read_only(m.set_block(
+            1,
+            Block {
+                data: [0u8; BLOCK_SIZE]
+            }
+        )));
+        assert!(read_only(m.save_cursor(0).map(|_| ())));
+        assert!(read_only(m.canonicalize()));
+        assert!(read_only(m.repair(RepairOptions::default()).map(|_| ())));
+        assert!(read_only(m.relocate_broken().map(|_| ())));
+        assert!(read_only(m.rebuild_directory().map(|_| ())));
+        assert!(read_only(m.format()));
+        assert!(read_only(m.wipe(WipePattern::Zeros)));
+        assert_eq!(m, original);
+        assert_eq!(MCError::ReadOnly.kind(), ErrorKind::ReadOnly);
+
+        // Tracked cards keep the flag, and are not written either
+        let mut t = TrackedCard::from_memcard(m.clone()).unwrap();
+        t.data_mut(0).unwrap().data_frames[0].data[0] ^= 0xff;
+        assert!(read_only(t.write(&path)));
+        let mut image = std::io::Cursor::new(std::fs::read(&path).unwrap());
+        assert!(read_only(t.write_changes(&mut image).map(|_| ())));
+        assert!(read_only(t.update(&path).map(|_| ())));
+        assert_eq!(image.into_inner(), std::fs::read(&path).unwrap());
+
+        // Blocks can not be borrowed mutably through indexing or iteration
+        let panics = |f: &dyn Fn(&mut MemCard)| {
+            let mut m = m.clone();
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut m))).is_err()
+        };
+        assert!(panics(&|m| m[0].data_frames[0].data[0] = 0));
+        assert!(panics(&|m| {
+            for b in m {
+                b.data_frames[0].data[0] = 0;
+            }
+        }));
+
+        // Reading and exporting still work
+        assert_eq!(m[0], original.data[0]);
+        assert_eq!((&m).into_iter().count(), 15);
+        let mut out = Vec::new();
+        m.write_to(&mut out).unwrap();
+        assert_eq!(out, std::fs::read(&path).unwrap());
+
+        m.set_read_only(false);
+        m.delete_save(0).unwrap();
+        m.write(&path).unwrap();
+    }
+
+    #[test]
+    fn audit_log() {
+        let path = test_card("psxmem_audit_log.mcr");
+        let log = temp_path("psxmem_audit_log.mcr.log");
+        let _ = std::fs::remove_file(&log);
+        let original = MemCard::open(&path).unwrap();
+        let mut m = original.clone();
+        assert!(m.audit_log().is_empty());
+
+        let mcs = m.saves().nth(1).unwrap().to_mcs().unwrap();
+        m.delete_save(0).unwrap();
+        assert!(m.delete_save(0).is_err());
+        m.rename_save(4, "BASLUS-99999RENAMED").unwrap();
+        assert!(m.rename_save(4, "BASLUS-99999TOOLONGNAME").is_err());
+        m.write_audit_log(&path).unwrap();
+        let index = m.import_mcs(&mcs).unwrap();
+        m.patch_save(index, 16, b"psxmem").unwrap();
+        m.write_audit_log(&path).unwrap();
+
+        let actions: Vec<AuditAction> = m.audit_log().iter().map(|e| e.action.clone()).collect();
+        assert_eq!(
+            actions,
+            [
+                AuditAction::DeleteSave {
+                    index: 0,
+                    filename: "BASLUS-00000SAVE00".to_string()
+                },
+                AuditAction::RenameSave {
+                    index: 4,
+                    old: "BASLUS-00002SAVE02".to_string(),
+                    new: "BASLUS-99999RENAMED".to_string()
+                },
+                AuditAction::ImportSave {
+                    index,
+                    filename: "BASLUS-00001SAVE01".to_string()
+                },
+                AuditAction::PatchSave {
+                    index,
+                    filename: "BASLUS-00001SAVE01".to_string(),
+                    offset: 16,
+                    len: 6
+                },
+            ]
+        );
+        assert_eq!(
+            m.saves().find(|s| s.index == 4).unwrap().filename(),
+            "BASLUS-99999RENAMED"
+        );
+
+        // Each entry is appended to the log file once
+        let text = std::fs::read_to_string(&log).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].find('T'), Some("2024-01-31".len()));
+        assert_eq!(lines[0].find('Z'), Some("2024-01-31T23:59:59.123".len()));
+        assert!(lines[0].ends_with("Z Deleted BASLUS-00000SAVE00 from block 0"));
+        assert!(
+            lines[1].ends_with("Z Renamed BASLUS-00002SAVE02 in block 4 to BASLUS-99999RENAMED")
+        );
+        assert!(lines[3].ends_with(&format!(
+            "Z Patched 6 bytes at offset 16 of BASLUS-00001SAVE01 in block {}",
+            index
+        )));
+
+        // The log does not take part in comparisons
+        let mut n = original.clone();
+        n.format().unwrap();
+        let mut o = original.clone();
+        o.info = InfoBlock::new();
+        assert_eq!(n, o);
+        assert_ne!(n.audit_log(), o.audit_log());
+    }
+
+    #[test]
+    fn rebuild_card() {
+        let mut image = CardGenerator::new()
+            .save(SaveSpec::new("ONE", 1))
+            .save(SaveSpec::new("Two 2", 3))
+            .save(SaveSpec::new("THREE", 2))
+            .generate()
+            .unwrap();
+        image[..BLOCK_SIZE].fill(0);
+        assert!(MemCard::from_reader(&image[..]).is_err());
+
+        let (m, rebuilt) = MemCard::rebuild(&image).unwrap();
+        assert_eq!(
+            rebuilt
+                .iter()
+                .map(|r| (r.index, r.blocks.clone(), r.filename.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (0, vec![0], "BARECOV-0001ONE"),
+                (1, vec![1, 2, 3], "BARECOV-0002TWO2"),
+                (4, vec![4, 5], "BARECOV-0005THREE"),
+            ]
+        );
+        assert!(m.validate().is_clean(), "{:?}", m.validate());
+        assert_eq!(
+            m.saves().map(|s| s.size()).collect::<Vec<_>>(),
+            [
+                BLOCK_SIZE as u32,
+                3 * BLOCK_SIZE as u32,
+                2 * BLOCK_SIZE as u32
+            ]
+        );
+
+        // The rebuilt card can be written and opened again
+        let mut out = Vec::<u8>::new();
+        m.write_to(&mut out).unwrap();
+        assert_eq!(MemCard::from_reader(&out[..]).unwrap().saves().count(), 3);
+
+        // Nothing is left to rebuild
+        let mut m = m;
+        assert_eq!(m.rebuild_directory().unwrap(), []);
+        assert!(MemCard::rebuild(&image[1..]).is_err());
+    }
+
+    #[test]
+    fn tracked_card() {
+        let mut t = TrackedCard::open(test_card("psxmem_tracked.mcr")).unwrap();
+        assert!(t.dirty_blocks().is_empty());
+        assert_eq!(t.saves().count(), 3);
+
+        t.data_mut(1).unwrap().data_frames[0].data[0] = 0x42;
+        t.info_mut().dir_frames[1].filesize = 0x1234;
+        assert_eq!(t.dirty_blocks(), vec![0, 2]);
+        assert!(t.data_mut(15).is_none());
+
+        let mut expected = Vec::<u8>::new();
+        t.write_to(&mut expected).unwrap();
+        assert!(t.dirty_blocks().is_empty());
+
+        // Writing to a file backs up the previous one, like MemCard::write_with
+        let path = temp_path("psxmem_tracked_out.mcr");
+        t.write(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+        t.data_mut(1).unwrap().data_frames[0].data[0] = 0x43;
+        let opts = WriteOptions {
+            sync: true,
+            backups: 1,
+        };
+        t.write_with(&path, opts).unwrap();
+        let backups: Vec<_> = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| crate::backup::is_backup(&path, p.file_name().unwrap()))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read(&backups[0]).unwrap(), expected);
+        std::fs::remove_file(&backups[0]).unwrap();
+        let mut expected = Vec::<u8>::new();
+        t.write_to(&mut expected).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+        let mut out = Vec::<u8>::new();
+        t.into_inner().write_to(&mut out).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn raw_matches_deku() {
+        let raw = std::fs::read(test_card("psxmem_raw_matches_deku.mcr")).unwrap();
+        let (_, deku) = MemCard::from_bytes((&raw, 0)).unwrap();
+        let fast = MemCard::from_reader(&raw[..]).unwrap();
+        assert_eq!(fast, deku);
+
+        let mut out = Vec::<u8>::new();
+        fast.write_into(&mut out).unwrap();
+        assert_eq!(out, deku.to_bytes().unwrap());
+
+        // Arbitrary bytes in every field
+        let mut b = [0u8; BLOCK_SIZE];
+        for (n, v) in b.iter_mut().enumerate() {
+            *v = (n * 7 + n / 128) as u8;
+        }
+        let (_, info) = InfoBlock::from_bytes((&b, 0)).unwrap();
+        assert_eq!(InfoBlock::from_raw(&b), info);
+        let (_, data) = DataBlock::from_bytes((&b, 0)).unwrap();
+        assert_eq!(DataBlock::from_raw(&b), data);
+
+        let mut out = [0u8; BLOCK_SIZE];
+        info.write_raw(&mut out);
+        assert_eq!(out, b);
+        data.write_raw(&mut out).unwrap();
+        assert_eq!(out, b);
+
+        let mut broken = data.clone();
+        broken.data_frames.pop();
+        assert!(broken.write_raw(&mut out).is_err());
+    }
+
+    #[test]
+    fn cached_title() {
+        let mut m = MemCard::open(test_card("psxmem_cached_title.mcr")).unwrap();
+        let s = m.saves().next().unwrap();
+        assert_eq!(s.title().unwrap(), "WILD ARMS");
+        assert_eq!(s.title().unwrap(), "WILD ARMS");
+        let s = s.clone();
+        assert_eq!(s.title().unwrap(), "WILD ARMS");
+
+        // The cache does not take part in comparisons, and outlives the views
+        let (a, b) = (m.saves().next().unwrap(), m.saves().next().unwrap());
+        assert_eq!(a.title().unwrap(), "WILD ARMS");
+        assert_eq!(a, b);
+        assert!(m.titles.titles.lock().unwrap()[0].is_some());
+        fn sync<T: Sync>(_: &T) {}
+        sync(&a);
+
+        // Changed titles are decoded again
+        let mut title = TitleFrame::builder().title("RENAMED").build().unwrap();
+        title.icon_palette = m.data[0].title_frame.icon_palette;
+        m.data[0].title_frame.title = title.title;
+        assert_eq!(m.saves().next().unwrap().title().unwrap(), "RENAMED");
+        assert_eq!(m.find_game("renamed").unwrap().len(), 1);
+
+        m.data[0].title_frame.id = [0u8; 2];
+        assert!(m.saves().next().unwrap().title().is_err());
+    }
+
+    #[test]
+    fn partial_writes() {
+        let path = test_card("psxmem_partial_writes.mcr");
+        let mut t = TrackedCard::open(&path).unwrap();
+        t.data_mut(4).unwrap().data_frames[2].data[9] = 0x5a;
+
+        // Writing to a blank image only fills in the dirty block
+        let mut blank = std::io::Cursor::new(vec![0u8; BLOCK_SIZE * BLOCKS_PER_CARD]);
+        assert_eq!(t.clone().write_changes(&mut blank).unwrap(), vec![5]);
+        let blank = blank.into_inner();
+        assert!(blank[..BLOCK_SIZE * 5].iter().all(|b| *b == 0));
+        assert!(blank[BLOCK_SIZE * 6..].iter().all(|b| *b == 0));
+
+        assert_eq!(t.update(&path).unwrap(), vec![5]);
+        assert!(t.update(&path).unwrap().is_empty());
+        let m = MemCard::open(&path).unwrap();
+        assert_eq!(m.data[4].data_frames[2].data[9], 0x5a);
+        assert_eq!(&m, &*t);
+    }
+
+    #[test]
+    fn skip_checksums() {
+        let card = CardGenerator::new()
+            .defect(Defect::BadChecksum { block: 0, frame: 3 })
+            .generate()
+            .unwrap();
+        assert!(MemCard::from_reader(&card[..]).is_err());
+
+        let opts = OpenOptions {
+            skip_checksums: true,
+            ..Default::default()
+        };
+        let m = MemCard::from_reader_with(&card[..], opts).unwrap();
+        assert_eq!(
+            m.info.validate_checksums(),
+            vec![Finding::BadChecksum { block: 0, frame: 3 }]
+        );
+
+        let mut card = card;
+        card[0] = b'X';
+        assert!(matches!(
+            MemCard::from_reader_with(&card[..], opts),
+            Err(MCError::NotAMemoryCard)
+        ));
+    }
+
+    #[test]
+    fn unused_frame_checksums() {
+        let card = CardGenerator::new()
+            .defect(Defect::BadChecksum {
+                block: 0,
+                frame: 40,
+            })
+            .generate()
+            .unwrap();
+
+        // Garbage in the unused frames is common on real cards, so it is only reported
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        assert_eq!(
+            m.info.validate_unused_frames(),
+            vec![Finding::BadChecksum {
+                block: 0,
+                frame: 40
+            }]
+        );
+
+        let opts = OpenOptions {
+            strict_unused_frames: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            MemCard::from_reader_with(&card[..], opts),
+            Err(MCError::BadChecksum)
+        ));
+    }
+
+    #[test]
+    fn not_a_memory_card() {
+        // A file of the right size whose checksums do not match either
+        let data: Vec<u8> = (0..BLOCK_SIZE * BLOCKS_PER_CARD).map(|n| n as u8).collect();
+        assert!(matches!(
+            MemCard::from_reader(&data[..]),
+            Err(MCError::NotAMemoryCard)
+        ));
+        assert!(matches!(
+            MemCard::from_reader(&vec![0u8; BLOCK_SIZE * BLOCKS_PER_CARD][..]),
+            Err(MCError::NotAMemoryCard)
+        ));
+    }
+
+    #[test]
+    fn mcs_export_import() {
+        let mut m = MemCard::open(test_card("psxmem_mcs.mcr")).unwrap();
+        let mcs = m.saves().nth(1).unwrap().to_mcs().unwrap();
+        assert_eq!(mcs.len(), FRAME_SIZE + BLOCK_SIZE * 3);
+
+        m.delete_save(1).unwrap();
+        assert_eq!(m.saves().count(), 2);
+        assert_eq!(m.deleted_saves()[0].title().unwrap(), "FINAL FANTASY VII");
+        assert!(m.delete_save(2).is_err());
+
+        // Never used blocks are filled first
+        let n = m.import_mcs(&mcs).unwrap();
+        assert_eq!(n, 6);
+        let s = m.saves().find(|s| s.index == n).unwrap();
+        assert_eq!(s.blocks, vec![6, 7, 8]);
+        assert_eq!(s.title().unwrap(), "FINAL FANTASY VII");
+        assert_eq!(s.serial(), "SLUS-00001");
+        assert_eq!(s.to_mcs().unwrap(), mcs);
+        assert!(m.validate_chains().is_empty());
+
+        // Then the blocks of deleted saves
+        assert_eq!(m.import_mcs(&mcs).unwrap(), 9);
+        assert_eq!(m.import_mcs(&mcs).unwrap(), 12);
+        assert_eq!(m.import_mcs(&mcs).unwrap(), 1);
+        assert!(m.deleted_saves().is_empty());
+        assert!(matches!(m.import_mcs(&mcs), Err(MCError::CardFull)));
+        assert!(m.import_mcs(&mcs[1..]).is_err());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn ffi_round_trip() {
+        use crate::ffi::*;
+        use std::ffi::CStr;
+
+        let raw = std::fs::read(test_card("psxmem_ffi_round_trip.mcr")).unwrap();
+        unsafe {
+            let card = psxmem_open_bytes(raw.as_ptr(), raw.len());
+            assert!(!card.is_null());
+            assert_eq!(psxmem_save_count(card), 3);
+
+            let mut info = std::mem::zeroed::<PsxSaveInfo>();
+            assert_eq!(psxmem_save_info(card, 1, &mut info), PsxStatus::Ok);
+            assert_eq!(info.slot, 2);
+            assert_eq!(info.blocks, 3);
+            assert_eq!(
+                CStr::from_ptr(info.serial.as_ptr()).to_str(),
+                Ok("SLUS-00001")
+            );
+
+            let mut mcs = std::ptr::null_mut();
+            let mut len = 0;
+            assert_eq!(
+                psxmem_export_save(card, 2, &mut mcs, &mut len),
+                PsxStatus::Ok
+            );
+            assert_eq!(len, FRAME_SIZE + 3 * BLOCK_SIZE);
+
+            let mut slot = 0;
+            assert_eq!(psxmem_import_save(card, mcs, len, &mut slot), PsxStatus::Ok);
+            assert_eq!(slot, 7);
+            assert_eq!(psxmem_save_count(card), 4);
+            psxmem_bytes_free(mcs, len);
+
+            assert_eq!(
+                psxmem_export_save(card, 9, &mut mcs, &mut len),
+                PsxStatus::NotFound
+            );
+            assert!(!psxmem_last_error().is_null());
+
+            psxmem_free(card);
+        }
+    }
+
+    #[test]
+    fn save_without_icon() {
+        let card = CardGenerator::new()
+            .save(SaveSpec {
+                icon_frames: 0,
+                ..SaveSpec::new("HOMEBREW", 1)
+            })
+            .generate()
+            .unwrap();
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        let d = &m.data[0];
+        assert_eq!(d.title_frame.display, 0x00);
+        assert_eq!(d.title_frame.get_icon_display(), IconDisplay::NoIcon);
+        assert!(d.icon_frames.is_empty());
+        assert_eq!(d.data_frames.len(), FRAMES_PER_BLOCK - 1);
+        assert_eq!(
+            d.data_frames[0].data[..],
+            card[BLOCK_SIZE + FRAME_SIZE..][..FRAME_SIZE]
+        );
+        assert_eq!(
+            DataBlock::load_data_block(&Block::from_bytes((&card[BLOCK_SIZE..], 0)).unwrap().1)
+                .unwrap(),
+            *d
+        );
+
+        let save = m.saves().next().unwrap();
+        assert_eq!(save.title().unwrap(), "HOMEBREW");
+        assert_eq!(save.payload().unwrap().len(), BLOCK_SIZE - FRAME_SIZE);
+
+        // Exports get a placeholder in place of the missing icon
+        let rgba = d.icon_rgba(0).unwrap();
+        assert_eq!(rgba.len(), 16 * 16 * 4);
+        assert_eq!(rgba[..4], [0x80, 0x80, 0x80, 0xff]);
+        assert!(d.icon_rgba(1).is_none());
+        assert_eq!(&d.icon_png(0).unwrap()[..8], b"\x89PNG\r\n\x1a\n");
+        let mut gif = Vec::<u8>::new();
+        d.write_icon_gif(&mut gif).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+
+        let mut out = Vec::<u8>::new();
+        m.write_to(&mut out).unwrap();
+        assert_eq!(out, card);
+    }
+
+    #[test]
+    fn nonstandard_icon_display() {
+        let quirk = |display| IconDisplay::Nonstandard {
+            display,
+            frames: IconDisplay::from(display).frames(),
+        };
+        assert_eq!(IconDisplay::from(0x14), quirk(0x14));
+        assert_eq!(IconDisplay::from(0x14).frames(), 3);
+        assert_eq!(IconDisplay::from(0x17).frames(), 3);
+        assert_eq!(IconDisplay::from(0x92).frames(), 2);
+        assert_eq!(IconDisplay::from(0x20).frames(), 0);
+        assert!(IconDisplay::from(0x92).is_nonstandard());
+        assert!(!IconDisplay::TwoFrames.is_nonstandard());
+        assert_eq!(IconDisplay::from(0x92).to_raw(), 0x92);
+        assert_eq!(
+            IconDisplay::from(0x15).to_string(),
+            "3 frames (nonstandard 0x15)"
+        );
+        assert_eq!("0x15".parse::<IconDisplay>().unwrap(), quirk(0x15));
+
+        // The block is split after the guessed number of icon frames, and written back as is
+        let card = CardGenerator::new()
+            .save(SaveSpec {
+                icon_frames: 3,
+                ..SaveSpec::new("QUIRKY", 1)
+            })
+            .generate()
+            .unwrap();
+        let mut raw = card[BLOCK_SIZE..2 * BLOCK_SIZE].to_vec();
+        raw[2] = 0x16;
+        let b = raw.first_chunk::<BLOCK_SIZE>().unwrap();
+        let d = DataBlock::from_raw(b);
+        assert_eq!(d.title_frame.get_icon_display(), quirk(0x16));
+        assert_eq!(d.icon_frames.len(), 3);
+        assert_eq!(d.data_frames.len(), FRAMES_PER_BLOCK - 4);
+        assert_eq!(DataBlock::from_bytes((&raw, 0)).unwrap().1, d);
+        let mut out = [0u8; BLOCK_SIZE];
+        d.write_raw(&mut out).unwrap();
+        assert_eq!(out[..], raw[..]);
+    }
+
+    #[test]
+    fn pocketstation_save() {
+        let mut card = CardGenerator::new()
+            .save(SaveSpec::new("POCKET GAME", 2))
+            .generate()
+            .unwrap();
+        let header = BLOCK_SIZE + 0x50;
+        card[header..header + 7].copy_from_slice(b"\x02\x00MCX1\x01");
+
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        let d = &m.data[0];
+        assert_eq!(
+            d.title_frame.pocketstation(),
+            Some(PocketStationInfo {
+                mono_frames: 2,
+                snapshot: true,
+                artifacts: 1,
+            })
+        );
+        assert_eq!(d.icon_frames.len(), 1);
+        assert_eq!(d.pocket_frames.len(), 2);
+        assert_eq!(d.data_frames.len(), FRAMES_PER_BLOCK - 4);
+        assert_eq!(
+            d.pocket_frames[0].data[..],
+            card[BLOCK_SIZE + 2 * FRAME_SIZE..][..FRAME_SIZE]
+        );
+        assert_eq!(
+            d.data_frames[0].data[..],
+            card[BLOCK_SIZE + 4 * FRAME_SIZE..][..FRAME_SIZE]
+        );
+        assert_eq!(d.into_iter().count(), FRAMES_PER_BLOCK - 1);
+        assert_eq!(
+            DataBlock::from_bytes((&card[BLOCK_SIZE..], 0)).unwrap().1,
+            *d
+        );
+        assert!(m.data[1].pocket_frames.is_empty());
+        assert_eq!(m.data[1].title_frame.pocketstation(), None);
+
+        let map = m.offset_map();
+        assert_eq!(
+            map[(BLOCK_SIZE + 3 * FRAME_SIZE) / FRAME_SIZE].kind,
+            RegionKind::PocketIcon { block: 1, frame: 1 }
+        );
+
+        let mut out = Vec::<u8>::new();
+        m.write_to(&mut out).unwrap();
+        assert_eq!(out, card);
+
+        // Frames past the first block stay in the data frames of the next ones
+        card[header..header + 2].copy_from_slice(&300u16.to_le_bytes());
+        let m = MemCard::from_reader(&card[..]).unwrap();
+        assert_eq!(m.data[0].pocket_frames.len(), FRAMES_PER_BLOCK - 2);
+        assert!(m.data[0].data_frames.is_empty());
+    }
+
+    #[test]
+    fn icon_images_in_memory() {
+        let m = MemCard::open(test_card("psxmem_icon_images.mcr")).unwrap();
+
+        let png = m.data[0].icon_png(0).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(m.data[0].icon_png(3).is_err());
+
+        let mut gif = Vec::<u8>::new();
+        m.data[0].write_icon_gif(&mut gif).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+    }
+
+    #[test]
+    fn error_kind() {
+        let card = CardGenerator::new()
+            .defect(Defect::BadChecksum { block: 0, frame: 3 })
+            .generate()
+            .unwrap();
+        let path = temp_path("psxmem_error_kind.mcr");
+        std::fs::write(&path, card).unwrap();
+
+        let e = MemCard::open(&path).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Corrupt);
+        assert!(e.to_string().contains("psxmem_error_kind.mcr"));
+
+        let e = MemCard::open(temp_path("psxmem_missing.mcr")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Io);
+
+        let zeros = vec![0u8; BLOCK_SIZE * BLOCKS_PER_CARD];
+        let e = MemCard::from_reader(&zeros[..]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Unsupported);
+
+        // Lookups of saves that do not exist
+        let mut m = MemCard::open(test_card("psxmem_error_kind_lookups.mcr")).unwrap();
+        let not_found = |r: Result<(), MCError>| r.unwrap_err().kind() == ErrorKind::NotFound;
+        assert!(not_found(m.delete_save(2)));
+        assert!(not_found(m.rename_save(2, "BASLUS-99999RENAMED")));
+        assert!(not_found(m.save_cursor(2).map(|_| ())));
+        assert!(not_found(m.patch_save(2, 0, b"ab")));
+        assert!(not_found(m.set_icon_raw(2, &[], &[])));
+
+        let len = m.saves().nth(1).unwrap().payload().unwrap().len();
+        let e = m.patch_save(1, len, b"ab").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+        let mcs = m.saves().nth(1).unwrap().to_mcs().unwrap();
+        let full = CardGenerator::new()
+            .save(SaveSpec::new("FULL", 15))
+            .generate()
+            .unwrap();
+        let mut full = MemCard::from_reader(&full[..]).unwrap();
+        assert_eq!(full.import_mcs(&mcs).unwrap_err().kind(), ErrorKind::Full);
+
+        m.set_read_only(true);
+        assert_eq!(m.delete_save(0).unwrap_err().kind(), ErrorKind::ReadOnly);
     }
 }
@@ -0,0 +1,188 @@
+//! Finding the memory card files of installed emulators in their default locations.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::CardGeometry;
+
+/// The extensions of card image files, compared case-insensitively. ".VM1" is the raw image the
+/// PS3 keeps PS1 cards in.
+const CARD_EXTENSIONS: [&str; 6] = ["mcd", "mcr", "mc", "srm", "bin", "vm1"];
+
+/// Emulator
+///
+/// An emulator whose default memory card locations are known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Emulator {
+    EPsxe,
+    DuckStation,
+    PcsxRedux,
+    Mednafen,
+    RetroArch,
+}
+
+/// CardLocation
+///
+/// A memory card file found by `find_cards`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CardLocation {
+    /// The emulator the file belongs to.
+    pub emulator: Emulator,
+
+    /// The path of the card image.
+    pub path: PathBuf,
+}
+
+impl Emulator {
+    /// All emulators with known locations.
+    pub const ALL: [Emulator; 5] = [
+        Emulator::EPsxe,
+        Emulator::DuckStation,
+        Emulator::PcsxRedux,
+        Emulator::Mednafen,
+        Emulator::RetroArch,
+    ];
+
+    /// The directories the emulator keeps its memory cards in by default on this platform,
+    /// whether or not they exist. Environment variables that the emulators honor, such as
+    /// `XDG_DATA_HOME` or `MEDNAFEN_HOME`, are taken into account.
+    pub fn card_dirs(&self) -> Vec<PathBuf> {
+        let home = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(PathBuf::from);
+        let var = |name: &str| env::var_os(name).map(PathBuf::from);
+        let in_home = |p: &str| home.as_ref().map(|h| h.join(p));
+
+        let dirs = if cfg!(windows) {
+            let appdata = var("APPDATA");
+            let local = var("LOCALAPPDATA");
+            match self {
+                Emulator::EPsxe => vec![Some(PathBuf::from(r"C:\ePSXe\memcards"))],
+                Emulator::DuckStation => vec![
+                    local.map(|d| d.join(r"DuckStation\memcards")),
+                    in_home(r"Documents\DuckStation\memcards"),
+                ],
+                Emulator::PcsxRedux => vec![appdata.map(|d| d.join("pcsx-redux"))],
+                Emulator::Mednafen => vec![var("MEDNAFEN_HOME").map(|d| d.join("sav"))],
+                Emulator::RetroArch => vec![
+                    appdata.map(|d| d.join(r"RetroArch\saves")),
+                    Some(PathBuf::from(r"C:\RetroArch-Win64\saves")),
+                ],
+            }
+        } else if cfg!(target_os = "macos") {
+            let support = in_home("Library/Application Support");
+            match self {
+                Emulator::EPsxe => vec![in_home(".epsxe/memcards")],
+                Emulator::DuckStation => vec![support.map(|d| d.join("DuckStation/memcards"))],
+                Emulator::PcsxRedux => vec![support.map(|d| d.join("pcsx-redux"))],
+                Emulator::Mednafen => vec![mednafen_home(&home).map(|d| d.join("sav"))],
+                Emulator::RetroArch => vec![support.map(|d| d.join("RetroArch/saves"))],
+            }
+        } else {
+            let data = var("XDG_DATA_HOME").or_else(|| in_home(".local/share"));
+            let config = var("XDG_CONFIG_HOME").or_else(|| in_home(".config"));
+            match self {
+                Emulator::EPsxe => vec![in_home(".epsxe/memcards")],
+                Emulator::DuckStation => vec![
+                    data.map(|d| d.join("duckstation/memcards")),
+                    in_home(".var/app/org.duckstation.DuckStation/data/duckstation/memcards"),
+                ],
+                Emulator::PcsxRedux => vec![config.as_ref().map(|d| d.join("pcsx-redux"))],
+                Emulator::Mednafen => vec![mednafen_home(&home).map(|d| d.join("sav"))],
+                Emulator::RetroArch => vec![
+                    config.map(|d| d.join("retroarch/saves")),
+                    in_home(".var/app/org.libretro.RetroArch/config/retroarch/saves"),
+                ],
+            }
+        };
+
+        dirs.into_iter().flatten().collect()
+    }
+
+    /// Find the memory card files in the default directories of the emulator.
+    pub fn find_cards(&self) -> Vec<PathBuf> {
+        let mut cards: Vec<PathBuf> = self
+            .card_dirs()
+            .iter()
+            .flat_map(|d| scan_dir(d, 1))
+            .collect();
+        cards.dedup();
+        cards
+    }
+}
+
+impl fmt::Display for Emulator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Emulator::EPsxe => "ePSXe",
+            Emulator::DuckStation => "DuckStation",
+            Emulator::PcsxRedux => "PCSX-Redux",
+            Emulator::Mednafen => "Mednafen",
+            Emulator::RetroArch => "RetroArch",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Find the memory card files of all known emulators in their default directories.
+pub fn find_cards() -> Vec<CardLocation> {
+    Emulator::ALL
+        .iter()
+        .flat_map(|e| {
+            e.find_cards()
+                .into_iter()
+                .map(|path| CardLocation { emulator: *e, path })
+        })
+        .collect()
+}
+
+/// Find the memory card files in `dir`, descending up to `depth` levels of subdirectories, such
+/// as the per-core save directories of RetroArch. Files are recognized by their extension and
+/// by having the size of a card image. The paths are returned sorted.
+pub fn scan_dir<P: AsRef<Path>>(dir: P, depth: usize) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return out;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+
+        if meta.is_dir() {
+            if depth > 0 {
+                out.extend(scan_dir(&path, depth - 1));
+            }
+        } else if is_card_file(&path, meta.len()) {
+            out.push(path);
+        }
+    }
+    out.sort();
+
+    out
+}
+
+fn is_card_file(path: &Path, len: u64) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match ext {
+        Some(e) if CARD_EXTENSIONS.contains(&e.as_str()) => {
+            CardGeometry::from_size(len as usize).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Mednafen keeps its files in `MEDNAFEN_HOME` if set, and `~/.mednafen` otherwise.
+fn mednafen_home(home: &Option<PathBuf>) -> Option<PathBuf> {
+    env::var_os("MEDNAFEN_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".mednafen")))
+}
@@ -0,0 +1,93 @@
+//! Exporting the save list of a memory card as a Markdown table, for documenting collections in
+//! wikis and READMEs.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{MCError, MemCard, Save};
+
+/// Write a table with one row per live save on `card` to `out`, in directory order. With
+/// `icons`, every row links to the thumbnail `icons/<filename>.png`.
+pub(crate) fn export<W: Write>(
+    card: &MemCard,
+    out: &mut W,
+    icons: Option<&str>,
+) -> Result<(), MCError> {
+    match icons {
+        Some(_) => {
+            writeln!(out, "| Slot | Icon | Title | Serial | Region | Blocks |")?;
+            writeln!(out, "| ---: | :---: | --- | --- | --- | ---: |")?;
+        }
+        None => {
+            writeln!(out, "| Slot | Title | Serial | Region | Blocks |")?;
+            writeln!(out, "| ---: | --- | --- | --- | ---: |")?;
+        }
+    }
+
+    for s in card.saves() {
+        let mut cells = vec![(s.index + 1).to_string()];
+        if let Some(dir) = icons {
+            cells.push(format!(
+                "![{}]({}/{})",
+                escape(&s.title().unwrap_or_default()),
+                dir.trim_end_matches('/'),
+                thumbnail_name(&s)
+            ));
+        }
+        cells.extend(row(&s));
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+
+    Ok(())
+}
+
+/// Write the first icon frame of every live save on `card` to `dir`, as the thumbnails linked
+/// to by `export`.
+pub(crate) fn write_thumbnails(card: &MemCard, dir: &Path) -> Result<(), MCError> {
+    for s in card.saves() {
+        let path = dir.join(thumbnail_name(&s));
+        let write = || -> Result<(), MCError> {
+            let mut out = BufWriter::new(File::create(&path)?);
+            s.data[0].write_icon_png(0, &mut out)?;
+            out.flush()?;
+            Ok(())
+        };
+        write().map_err(|e| e.context(format!("Unable to write {}", path.display())))?;
+    }
+
+    Ok(())
+}
+
+/// The file name of the thumbnail of `save`. The directory filename is used, since unlike the
+/// title it is ASCII and unique on the card.
+fn thumbnail_name(save: &Save) -> String {
+    format!("{}.png", save.filename())
+}
+
+fn row(s: &Save) -> [String; 4] {
+    let region = s
+        .region_info()
+        .map(|r| format!("{:?}", r.region))
+        .unwrap_or_default();
+
+    [
+        escape(&s.title().unwrap_or_default()),
+        escape(&s.serial()),
+        region,
+        s.blocks.len().to_string(),
+    ]
+}
+
+/// Escape the characters that would end a table cell or start inline markup.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
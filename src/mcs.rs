@@ -0,0 +1,90 @@
+//! Single save files in the `.mcs` format: the directory frame of the save followed by its
+//! data blocks.
+
+use crate::{
+    BAState, DataBlock, DirectoryFrame, MCError, MemCard, Save, BLOCK_SIZE, CHAIN_END, FRAME_SIZE,
+};
+
+/// Export `save` as a `.mcs` file. The directory frame is written as a single block save, with
+/// the `AllocFirst` state and no next block.
+pub(crate) fn export(save: &Save) -> Result<Vec<u8>, MCError> {
+    let mut dir = *save.dir_frame;
+    dir.edit(|d| {
+        d.set_alloc_state(BAState::AllocFirst);
+        d.next_block = CHAIN_END;
+    })?;
+
+    let mut out = Vec::<u8>::with_capacity(FRAME_SIZE + BLOCK_SIZE * save.data.len());
+    out.extend_from_slice(&dir.to_raw());
+    for d in &save.data {
+        let mut b = [0u8; BLOCK_SIZE];
+        d.write_raw(&mut b)?;
+        out.extend_from_slice(&b);
+    }
+
+    Ok(out)
+}
+
+/// Import a `.mcs` file into the free blocks of `card`. Blocks that have never been used are
+/// filled before the blocks of deleted saves. Returns the directory index of the first block.
+pub(crate) fn import(card: &mut MemCard, mcs: &[u8]) -> Result<usize, MCError> {
+    let (head, data) = mcs
+        .split_first_chunk::<FRAME_SIZE>()
+        .ok_or_else(|| MCError::InvalidValue("save file is too short".to_string()))?;
+    let (blocks, rest) = data.as_chunks::<BLOCK_SIZE>();
+    if blocks.is_empty() || !rest.is_empty() {
+        return Err(MCError::InvalidValue(format!(
+            "save file of {} bytes is not a directory frame followed by whole blocks",
+            mcs.len()
+        )));
+    }
+
+    let dir = DirectoryFrame::from_raw(head);
+    let mut free: Vec<usize> = card
+        .info
+        .dir_frames
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| {
+            matches!(
+                d.get_alloc_state(),
+                BAState::Free | BAState::FreeFirst | BAState::FreeMid | BAState::FreeLast
+            )
+        })
+        .map(|(n, _)| n)
+        .collect();
+    free.sort_by_key(|n| {
+        (
+            card.info.dir_frames[*n].get_alloc_state() != BAState::Free,
+            *n,
+        )
+    });
+    if free.len() < blocks.len() {
+        return Err(MCError::CardFull);
+    }
+    free.truncate(blocks.len());
+    free.sort_unstable();
+
+    for (i, (n, b)) in free.iter().zip(blocks).enumerate() {
+        let state = match i {
+            0 => BAState::AllocFirst,
+            _ if i == blocks.len() - 1 => BAState::AllocLast,
+            _ => BAState::AllocMid,
+        };
+        let next = free.get(i + 1).map_or(CHAIN_END, |n| *n as u16);
+
+        let mut d = DirectoryFrame::free();
+        if i == 0 {
+            d.filesize = (blocks.len() * BLOCK_SIZE) as u32;
+            d.filename = dir.filename;
+        }
+        d.edit(|d| {
+            d.set_alloc_state(state);
+            d.next_block = next;
+        })?;
+        card.info.dir_frames[*n] = d;
+        card.data[*n] = DataBlock::from_raw(b);
+    }
+
+    Ok(free[0])
+}
@@ -0,0 +1,69 @@
+//! Memory-mapped access to card images on disk.
+
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{CardGeometry, MCError, MemCardRef};
+
+/// MappedCard
+///
+/// A card image file that is memory-mapped instead of read into memory. Combined with
+/// `MemCardRef`, only the pages of the file that are actually looked at are loaded, which keeps
+/// indexing large collections of images cheap.
+///
+/// The file must not be modified by another process while it is mapped.
+#[derive(Debug)]
+pub struct MappedCard {
+    map: Mmap,
+    geometry: CardGeometry,
+}
+
+impl MappedCard {
+    /// Map the card image at `filename`. Returns an error if the file size is not a valid card
+    /// image size, see `CardGeometry::from_size`.
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        let path = filename.as_ref();
+        let file = File::open(path)?;
+
+        // Safety: the mapping is read-only, and it is documented that the file must not be
+        // modified while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let geometry = CardGeometry::from_size(map.len()).ok_or_else(|| {
+            MCError::InvalidValue(format!(
+                "{} is not a card image, it is {} bytes",
+                path.display(),
+                map.len()
+            ))
+        })?;
+
+        Ok(MappedCard { map, geometry })
+    }
+
+    /// The geometry of the mapped image.
+    pub fn geometry(&self) -> CardGeometry {
+        self.geometry
+    }
+
+    /// A view of page `n` of the image. Standard images only have page 0.
+    pub fn page(&self, n: usize) -> Result<MemCardRef<'_>, MCError> {
+        if n >= self.geometry.pages() {
+            return Err(MCError::InvalidValue(format!("page {} is out of range", n)));
+        }
+
+        let size = CardGeometry::STANDARD.size();
+        MemCardRef::new(&self.map[n * size..(n + 1) * size])
+    }
+
+    /// A view of the first page of the image.
+    pub fn view(&self) -> Result<MemCardRef<'_>, MCError> {
+        self.page(0)
+    }
+
+    /// The raw bytes of the whole image.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+}
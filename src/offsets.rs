@@ -0,0 +1,121 @@
+//! A map of the byte offsets of every structure in a card image, for overlaying the structure of
+//! a raw dump in hex editors and forensic tools.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{MemCard, BLOCK_SIZE, FRAME_SIZE, TITLE_MAGIC};
+
+/// RegionKind
+///
+/// The structure stored in a region of a card image. Blocks are numbered as on the card, from 1
+/// to 15, since block 0 holds the directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    /// The header frame, starting with "MC".
+    Header,
+
+    /// A directory frame, by directory index.
+    Directory(usize),
+
+    /// An entry of the broken frame list.
+    Broken(usize),
+
+    /// One of the unused frames between the broken frame list and the write test frame.
+    Unused(usize),
+
+    /// The write test frame at the end of block 0.
+    WriteTest,
+
+    /// The title frame of the save that starts at `block`.
+    Title { block: usize },
+
+    /// An icon frame of the save that starts at `block`.
+    Icon { block: usize, frame: usize },
+
+    /// A PocketStation monochrome icon frame of the save that starts at `block`.
+    PocketIcon { block: usize, frame: usize },
+
+    /// A data frame of `block`, numbered from the start of the block.
+    Data { block: usize, frame: usize },
+}
+
+/// OffsetRegion
+///
+/// A labeled range of bytes in a card image, as returned by `MemCard::offset_map`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetRegion {
+    /// The byte offsets of the region.
+    pub range: Range<usize>,
+
+    /// The structure stored in the region.
+    pub kind: RegionKind,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionKind::Header => write!(f, "header"),
+            RegionKind::Directory(n) => write!(f, "directory frame {}", n),
+            RegionKind::Broken(n) => write!(f, "broken frame {}", n),
+            RegionKind::Unused(n) => write!(f, "unused frame {}", n),
+            RegionKind::WriteTest => write!(f, "write test frame"),
+            RegionKind::Title { block } => write!(f, "block {} title frame", block),
+            RegionKind::Icon { block, frame } => write!(f, "block {} icon frame {}", block, frame),
+            RegionKind::PocketIcon { block, frame } => {
+                write!(f, "block {} PocketStation icon frame {}", block, frame)
+            }
+            RegionKind::Data { block, frame } => write!(f, "block {} data frame {}", block, frame),
+        }
+    }
+}
+
+impl fmt::Display for OffsetRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#07x}-{:#07x} {}",
+            self.range.start,
+            self.range.end - 1,
+            self.kind
+        )
+    }
+}
+
+/// Map every frame of `card`. The regions are in offset order and cover the whole image.
+pub(crate) fn offset_map(card: &MemCard) -> Vec<OffsetRegion> {
+    let info = &card.info;
+    let mut kinds = vec![RegionKind::Header];
+    kinds.extend((0..info.dir_frames.len()).map(RegionKind::Directory));
+    kinds.extend((0..info.broken_frames.len()).map(RegionKind::Broken));
+    kinds.extend((0..info.unused_frames().len()).map(RegionKind::Unused));
+    kinds.push(RegionKind::WriteTest);
+
+    for (n, d) in card.data.iter().enumerate() {
+        let block = n + 1;
+        // Only blocks that start a save hold a title and icons, the rest is all data
+        let (icons, pocket) = match d.title_frame.id == TITLE_MAGIC {
+            true => {
+                kinds.push(RegionKind::Title { block });
+                (d.icon_frames.len(), d.pocket_frames.len())
+            }
+            false => (0, 0),
+        };
+        kinds.extend((0..icons).map(|frame| RegionKind::Icon { block, frame }));
+        kinds.extend((0..pocket).map(|frame| RegionKind::PocketIcon { block, frame }));
+
+        let data_start = kinds.len() - block * BLOCK_SIZE / FRAME_SIZE;
+        kinds.extend(
+            (data_start..BLOCK_SIZE / FRAME_SIZE).map(|frame| RegionKind::Data { block, frame }),
+        );
+    }
+
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(n, kind)| OffsetRegion {
+            range: n * FRAME_SIZE..(n + 1) * FRAME_SIZE,
+            kind,
+        })
+        .collect()
+}
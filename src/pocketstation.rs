@@ -0,0 +1,48 @@
+//! PocketStation saves, which follow the standard icon frames with monochrome animation frames
+//! for the PocketStation screen and the PocketStation executable.
+
+use crate::{TitleFrame, FRAMES_PER_BLOCK, TITLE_MAGIC};
+
+/// PocketStationInfo
+///
+/// The PocketStation header, stored in the reserved bytes 0x50 to 0x56 of the title frame of a
+/// PocketStation save.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PocketStationInfo {
+    /// The number of 32x32 monochrome icon frames that follow the standard icon frames.
+    pub mono_frames: u16,
+
+    /// Whether the identifier is "MCX1", which marks a save with a snapshot, rather than "MCX0".
+    pub snapshot: bool,
+
+    /// The number of entries in the artifact list of the executable.
+    pub artifacts: u8,
+}
+
+/// The offset of the PocketStation header within `TitleFrame::reserved`.
+const HEADER: usize = 0x50 - 0x44;
+
+impl TitleFrame {
+    /// Parse the PocketStation header, or `None` if this is not the title frame of a
+    /// PocketStation save.
+    pub fn pocketstation(&self) -> Option<PocketStationInfo> {
+        let h = &self.reserved[HEADER..];
+        if self.id != TITLE_MAGIC || !matches!(&h[2..6], b"MCX0" | b"MCX1") {
+            return None;
+        }
+
+        Some(PocketStationInfo {
+            mono_frames: u16::from_le_bytes([h[0], h[1]]),
+            snapshot: h[5] == b'1',
+            artifacts: h[6],
+        })
+    }
+
+    /// The number of monochrome icon frames in the first block of the save. Frames that do not
+    /// fit in the first block are left in the data frames of the next ones.
+    pub fn pocketstation_frame_count(&self) -> usize {
+        let room = FRAMES_PER_BLOCK - 1 - self.icon_frame_count();
+        self.pocketstation()
+            .map_or(0, |p| usize::from(p.mono_frames).min(room))
+    }
+}
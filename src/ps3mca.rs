@@ -0,0 +1,205 @@
+//! Reading and writing physical memory cards with the official PS3 Memory Card Adaptor
+//! (CECHZM1) over USB.
+
+use std::time::Duration;
+
+use rusb::{DeviceHandle, GlobalContext};
+
+use crate::{CardGeometry, MCError, MemCard, OpenOptions, FRAME_SIZE, SIO_ADDRESS};
+
+/// The USB vendor and product IDs of the adaptor.
+pub const VENDOR_ID: u16 = 0x054c;
+pub const PRODUCT_ID: u16 = 0x02ea;
+
+const ENDPOINT_OUT: u8 = 0x02;
+const ENDPOINT_IN: u8 = 0x81;
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The headers of commands to and responses from the adaptor.
+const COMMAND: u8 = 0xaa;
+const RESPONSE: u8 = 0x55;
+
+/// Commands of the adaptor itself, and the one passing bytes through to the card.
+const CARD_TYPE: u8 = 0x40;
+const PASS_THROUGH: u8 = 0x42;
+
+/// The reply to `CARD_TYPE` for a PS1 card.
+const PS1_CARD: u8 = 0x01;
+
+/// The length of the memory card read and write commands, in bytes.
+const READ_LEN: usize = 10 + FRAME_SIZE + 2;
+const WRITE_LEN: usize = 6 + FRAME_SIZE + 4;
+
+/// Ps3Adaptor
+///
+/// A connection to a PS3 Memory Card Adaptor with a PS1 card inserted. Cards are read with
+/// `read_card` and written with `write_card`, one frame at a time, by passing the commands of
+/// the card bus protocol through the adaptor.
+#[derive(Debug)]
+pub struct Ps3Adaptor {
+    handle: DeviceHandle<GlobalContext>,
+}
+
+impl Ps3Adaptor {
+    /// Open the first adaptor that is plugged in. Returns an error if there is none, or if no
+    /// PS1 card is inserted.
+    pub fn open() -> Result<Self, MCError> {
+        let handle = rusb::open_device_with_vid_pid(VENDOR_ID, PRODUCT_ID)
+            .ok_or_else(|| usb_error("no PS3 Memory Card Adaptor found"))?;
+        handle
+            .claim_interface(0)
+            .map_err(|e| usb_error(&e.to_string()))?;
+
+        let mut adaptor = Ps3Adaptor { handle };
+        if adaptor.exchange(&[COMMAND, CARD_TYPE])?.get(1) != Some(&PS1_CARD) {
+            return Err(usb_error("no PS1 card in the adaptor"));
+        }
+
+        Ok(adaptor)
+    }
+
+    /// Read the frame `n` of the card.
+    pub fn read_frame(&mut self, n: u16) -> Result<[u8; FRAME_SIZE], MCError> {
+        let resp = self.exchange(&read_command(n))?;
+        parse_read(&resp, n)
+    }
+
+    /// Write `frame` to the frame `n` of the card.
+    pub fn write_frame(&mut self, n: u16, frame: &[u8; FRAME_SIZE]) -> Result<(), MCError> {
+        let resp = self.exchange(&write_command(n, frame))?;
+        parse_write(&resp)
+    }
+
+    /// Read the raw image of the card.
+    pub fn read_raw(&mut self) -> Result<Vec<u8>, MCError> {
+        let frames = CardGeometry::STANDARD.size() / FRAME_SIZE;
+        let mut data = Vec::with_capacity(CardGeometry::STANDARD.size());
+        for n in 0..frames {
+            data.extend_from_slice(&self.read_frame(n as u16)?);
+        }
+
+        Ok(data)
+    }
+
+    /// Read the card.
+    pub fn read_card(&mut self) -> Result<MemCard, MCError> {
+        self.read_card_with(OpenOptions::default())
+    }
+
+    /// Read the card, parsing it with the provided `OpenOptions`.
+    pub fn read_card_with(&mut self, opts: OpenOptions) -> Result<MemCard, MCError> {
+        MemCard::from_reader_with(self.read_raw()?.as_slice(), opts)
+    }
+
+    /// Write the raw image `data` to the card.
+    pub fn write_raw(&mut self, data: &[u8]) -> Result<(), MCError> {
+        if data.len() != CardGeometry::STANDARD.size() {
+            return Err(MCError::InvalidValue(format!(
+                "{} bytes is not a card image size",
+                data.len()
+            )));
+        }
+
+        for (n, frame) in data.as_chunks::<FRAME_SIZE>().0.iter().enumerate() {
+            self.write_frame(n as u16, frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write `card` to the card.
+    pub fn write_card(&mut self, card: &MemCard) -> Result<(), MCError> {
+        let mut data = Vec::with_capacity(CardGeometry::STANDARD.size());
+        card.write_to(&mut data)?;
+
+        self.write_raw(&data)
+    }
+
+    /// Send a command and receive the response.
+    fn exchange(&mut self, cmd: &[u8]) -> Result<Vec<u8>, MCError> {
+        self.handle
+            .write_bulk(ENDPOINT_OUT, cmd, TIMEOUT)
+            .map_err(|e| usb_error(&e.to_string()))?;
+
+        let mut buf = vec![0u8; 4 + READ_LEN];
+        let n = self
+            .handle
+            .read_bulk(ENDPOINT_IN, &mut buf, TIMEOUT)
+            .map_err(|e| usb_error(&e.to_string()))?;
+        buf.truncate(n);
+
+        Ok(buf)
+    }
+}
+
+/// Wrap the card bus bytes `bus` in a pass through command.
+fn pass_through(bus: &[u8]) -> Vec<u8> {
+    let mut cmd = vec![COMMAND, PASS_THROUGH];
+    cmd.extend_from_slice(&(bus.len() as u16).to_le_bytes());
+    cmd.extend_from_slice(bus);
+    cmd
+}
+
+/// Unwrap the card bus bytes of the response to a pass through command.
+fn bus_reply(resp: &[u8], len: usize) -> Result<&[u8], MCError> {
+    match resp {
+        [RESPONSE, 0x5a, lo, hi, bus @ ..]
+            if u16::from_le_bytes([*lo, *hi]) as usize == len && bus.len() >= len =>
+        {
+            Ok(&bus[..len])
+        }
+        _ => Err(usb_error("malformed response from the adaptor")),
+    }
+}
+
+/// The command that reads the frame `n`.
+pub(crate) fn read_command(n: u16) -> Vec<u8> {
+    let mut bus = vec![0u8; READ_LEN];
+    bus[..2].copy_from_slice(&[SIO_ADDRESS, b'R']);
+    bus[4..6].copy_from_slice(&n.to_be_bytes());
+    pass_through(&bus)
+}
+
+/// Parse the response to `read_command(n)`.
+pub(crate) fn parse_read(resp: &[u8], n: u16) -> Result<[u8; FRAME_SIZE], MCError> {
+    let bus = bus_reply(resp, READ_LEN)?;
+    if bus[8..10] != n.to_be_bytes() {
+        return Err(MCError::InvalidValue(format!(
+            "frame {} is not on the card",
+            n
+        )));
+    }
+
+    let mut frame = [0u8; FRAME_SIZE];
+    frame.copy_from_slice(&bus[10..10 + FRAME_SIZE]);
+    let [msb, lsb] = n.to_be_bytes();
+    let chk = frame.iter().fold(msb ^ lsb, |c, b| c ^ b);
+    match bus[10 + FRAME_SIZE..] {
+        [c, b'G'] if c == chk => Ok(frame),
+        _ => Err(MCError::BadChecksum),
+    }
+}
+
+/// The command that writes `frame` to the frame `n`.
+pub(crate) fn write_command(n: u16, frame: &[u8; FRAME_SIZE]) -> Vec<u8> {
+    let [msb, lsb] = n.to_be_bytes();
+    let mut bus = Vec::with_capacity(WRITE_LEN);
+    bus.extend_from_slice(&[SIO_ADDRESS, b'W', 0, 0, msb, lsb]);
+    bus.extend_from_slice(frame);
+    bus.push(frame.iter().fold(msb ^ lsb, |c, b| c ^ b));
+    bus.extend_from_slice(&[0, 0, 0]);
+    pass_through(&bus)
+}
+
+/// Parse the response to `write_command`.
+pub(crate) fn parse_write(resp: &[u8]) -> Result<(), MCError> {
+    match bus_reply(resp, WRITE_LEN)?[WRITE_LEN - 1] {
+        b'G' => Ok(()),
+        b'N' => Err(MCError::BadChecksum),
+        s => Err(usb_error(&format!("write failed with status {:#04x}", s))),
+    }
+}
+
+fn usb_error(msg: &str) -> MCError {
+    std::io::Error::other(format!("PS3 Memory Card Adaptor: {}", msg)).into()
+}
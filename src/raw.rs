@@ -0,0 +1,196 @@
+//! Hand-written parsers and serializers for the frame and block types.
+//!
+//! These produce exactly the same results as the derived `DekuRead`/`DekuWrite` impls, but work
+//! on fixed-size arrays without deku's per-field overhead. `MemCard::from_reader` and
+//! `MemCard::write_into` use them for bulk loads and exports.
+
+use crate::{
+    BrokenFrame, DataBlock, DirectoryFrame, Frame, Header, InfoBlock, MCError, TitleFrame,
+    BLOCK_SIZE, FRAMES_PER_BLOCK, FRAME_SIZE,
+};
+
+/// Copy a slice into an array of the same length.
+fn arr<const N: usize>(s: &[u8]) -> [u8; N] {
+    s.try_into().expect("slice length matches the array")
+}
+
+/// Split a block into its frames.
+fn frames(b: &[u8; BLOCK_SIZE]) -> &[[u8; FRAME_SIZE]] {
+    b.as_chunks::<FRAME_SIZE>().0
+}
+
+impl Header {
+    /// Parse a `Header` from a raw frame.
+    pub fn from_raw(f: &[u8; FRAME_SIZE]) -> Self {
+        Header {
+            id: arr(&f[..2]),
+            pad: arr(&f[2..127]),
+            checksum: f[127],
+        }
+    }
+
+    /// Serialize the `Header` into a raw frame.
+    pub fn to_raw(&self) -> [u8; FRAME_SIZE] {
+        let mut f = [0u8; FRAME_SIZE];
+        f[..2].copy_from_slice(&self.id);
+        f[2..127].copy_from_slice(&self.pad);
+        f[127] = self.checksum;
+        f
+    }
+}
+
+impl DirectoryFrame {
+    /// Parse a `DirectoryFrame` from a raw frame.
+    pub fn from_raw(f: &[u8; FRAME_SIZE]) -> Self {
+        DirectoryFrame {
+            state: u32::from_le_bytes(arr(&f[..4])),
+            filesize: u32::from_le_bytes(arr(&f[4..8])),
+            next_block: u16::from_le_bytes(arr(&f[8..10])),
+            filename: arr(&f[10..31]),
+            pad: arr(&f[31..127]),
+            checksum: f[127],
+        }
+    }
+
+    /// Serialize the `DirectoryFrame` into a raw frame.
+    pub fn to_raw(&self) -> [u8; FRAME_SIZE] {
+        let mut f = [0u8; FRAME_SIZE];
+        f[..4].copy_from_slice(&self.state.to_le_bytes());
+        f[4..8].copy_from_slice(&self.filesize.to_le_bytes());
+        f[8..10].copy_from_slice(&self.next_block.to_le_bytes());
+        f[10..31].copy_from_slice(&self.filename);
+        f[31..127].copy_from_slice(&self.pad);
+        f[127] = self.checksum;
+        f
+    }
+}
+
+impl BrokenFrame {
+    /// Parse a `BrokenFrame` from a raw frame.
+    pub fn from_raw(f: &[u8; FRAME_SIZE]) -> Self {
+        BrokenFrame {
+            broken_frame: u32::from_le_bytes(arr(&f[..4])),
+            pad: arr(&f[4..127]),
+            checksum: f[127],
+        }
+    }
+
+    /// Serialize the `BrokenFrame` into a raw frame.
+    pub fn to_raw(&self) -> [u8; FRAME_SIZE] {
+        let mut f = [0u8; FRAME_SIZE];
+        f[..4].copy_from_slice(&self.broken_frame.to_le_bytes());
+        f[4..127].copy_from_slice(&self.pad);
+        f[127] = self.checksum;
+        f
+    }
+}
+
+impl TitleFrame {
+    /// Parse a `TitleFrame` from a raw frame.
+    pub fn from_raw(f: &[u8; FRAME_SIZE]) -> Self {
+        let mut icon_palette = [0u16; 16];
+        for (c, b) in icon_palette.iter_mut().zip(f[96..].as_chunks::<2>().0) {
+            *c = u16::from_le_bytes(*b);
+        }
+
+        TitleFrame {
+            id: arr(&f[..2]),
+            display: f[2],
+            block_num: f[3],
+            title: arr(&f[4..68]),
+            reserved: arr(&f[68..96]),
+            icon_palette,
+        }
+    }
+
+    /// Serialize the `TitleFrame` into a raw frame.
+    pub fn to_raw(&self) -> [u8; FRAME_SIZE] {
+        let mut f = [0u8; FRAME_SIZE];
+        f[..2].copy_from_slice(&self.id);
+        f[2] = self.display;
+        f[3] = self.block_num;
+        f[4..68].copy_from_slice(&self.title);
+        f[68..96].copy_from_slice(&self.reserved);
+        for (b, c) in f[96..].chunks_exact_mut(2).zip(self.icon_palette) {
+            b.copy_from_slice(&c.to_le_bytes());
+        }
+        f
+    }
+}
+
+impl InfoBlock {
+    /// Parse an `InfoBlock` from a raw block, without validating it.
+    pub fn from_raw(b: &[u8; BLOCK_SIZE]) -> Self {
+        let f = frames(b);
+
+        InfoBlock {
+            header: Header::from_raw(&f[0]),
+            dir_frames: f[1..16].iter().map(DirectoryFrame::from_raw).collect(),
+            broken_frames: f[16..36].iter().map(BrokenFrame::from_raw).collect(),
+            unused_frames: f[36..63].iter().map(|f| Frame { data: *f }).collect(),
+            wr_test_frame: Header::from_raw(&f[63]),
+        }
+    }
+
+    /// Serialize the `InfoBlock` into a raw block, as is. Unlike `write`, the checksums are not
+    /// updated.
+    pub fn write_raw(&self, b: &mut [u8; BLOCK_SIZE]) {
+        let raw = [self.header.to_raw()]
+            .into_iter()
+            .chain(self.dir_frames.iter().map(DirectoryFrame::to_raw))
+            .chain(self.broken_frames.iter().map(BrokenFrame::to_raw))
+            .chain(self.unused_frames.iter().map(|f| f.data))
+            .chain([self.wr_test_frame.to_raw()]);
+
+        for (dst, src) in b.chunks_exact_mut(FRAME_SIZE).zip(raw) {
+            dst.copy_from_slice(&src);
+        }
+    }
+}
+
+impl DataBlock {
+    /// Parse a `DataBlock` from a raw block.
+    pub fn from_raw(b: &[u8; BLOCK_SIZE]) -> Self {
+        let f = frames(b);
+        let title_frame = TitleFrame::from_raw(&f[0]);
+        let icons = 1 + title_frame.icon_frame_count();
+        let pocket = icons + title_frame.pocketstation_frame_count();
+
+        DataBlock {
+            title_frame,
+            icon_frames: f[1..icons].iter().map(|f| Frame { data: *f }).collect(),
+            pocket_frames: f[icons..pocket]
+                .iter()
+                .map(|f| Frame { data: *f })
+                .collect(),
+            data_frames: f[pocket..FRAMES_PER_BLOCK]
+                .iter()
+                .map(|f| Frame { data: *f })
+                .collect(),
+        }
+    }
+
+    /// Serialize the `DataBlock` into a raw block. Returns `MCError::InvalidValue` if the icon
+    /// and data frames do not add up to a whole block.
+    pub fn write_raw(&self, b: &mut [u8; BLOCK_SIZE]) -> Result<(), MCError> {
+        let count = 1 + self.icon_frames.len() + self.pocket_frames.len() + self.data_frames.len();
+        if count != FRAMES_PER_BLOCK {
+            return Err(MCError::InvalidValue(format!(
+                "data block has {} frames instead of {}",
+                count, FRAMES_PER_BLOCK
+            )));
+        }
+
+        let raw = [self.title_frame.to_raw()]
+            .into_iter()
+            .chain(self.icon_frames.iter().map(|f| f.data))
+            .chain(self.pocket_frames.iter().map(|f| f.data))
+            .chain(self.data_frames.iter().map(|f| f.data));
+
+        for (dst, src) in b.chunks_exact_mut(FRAME_SIZE).zip(raw) {
+            dst.copy_from_slice(&src);
+        }
+
+        Ok(())
+    }
+}
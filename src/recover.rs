@@ -0,0 +1,316 @@
+//! Recovery of saves whose directory entries have been lost.
+
+use crate::validate::walk_chain;
+use crate::{
+    BAState, DataBlock, DirectoryFrame, IconDisplay, InfoBlock, MCError, MemCard, TitleFrame,
+    BLOCKS_PER_CARD, BLOCK_SIZE, CHAIN_END, TITLE_MAGIC,
+};
+
+/// OrphanSave
+///
+/// A save that was found by scanning the unallocated blocks of a memory card for title frames.
+/// The directory entry for it is gone, so the blocks that belong to it are a best guess: the
+/// block holding the title frame, followed by any contiguous unallocated blocks that are not blank
+/// and do not start another save.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrphanSave<'a> {
+    /// The directory index of the block that holds the title frame.
+    pub index: usize,
+
+    /// The directory indices of the blocks that are assumed to belong to the save.
+    pub blocks: Vec<usize>,
+
+    /// The data blocks that are assumed to belong to the save, in order.
+    pub data: Vec<&'a DataBlock>,
+}
+
+impl<'a> OrphanSave<'a> {
+    /// The title frame that was found at the start of the save.
+    pub fn title_frame(&self) -> &'a TitleFrame {
+        &self.data[0].title_frame
+    }
+}
+
+/// RebuiltSave
+///
+/// A save that was given a new directory entry by `MemCard::rebuild_directory`. The filename is
+/// synthesized, since the original one was stored in the lost directory entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RebuiltSave {
+    /// The directory index of the first block of the save.
+    pub index: usize,
+
+    /// The directory indices of the blocks that were allocated to the save, in order.
+    pub blocks: Vec<usize>,
+
+    /// The filename written to the directory entry, such as "BARECOV-0001FINALFAN".
+    pub filename: String,
+}
+
+/// Check that a title frame looks like the start of a real save rather than leftover data: the
+/// magic must match, and the palette of an icon can not be entirely blank. Any icon display flag
+/// is accepted, since a few games use nonstandard ones, see `IconDisplay`.
+pub(crate) fn plausible_title_frame(t: &TitleFrame) -> bool {
+    if t.id != TITLE_MAGIC {
+        return false;
+    }
+
+    if IconDisplay::from(t.display).frames() == 0 {
+        return true;
+    }
+
+    let blank =
+        t.icon_palette.iter().all(|c| *c == 0) || t.icon_palette.iter().all(|c| *c == 0xffff);
+    !blank
+}
+
+/// Check if a block holds nothing but 0x00 or 0xFF fill.
+fn is_blank(d: &DataBlock) -> bool {
+    let mut buf = Vec::<u8>::new();
+    if d.write(&mut buf).is_err() {
+        return true;
+    }
+
+    buf.iter().all(|b| *b == 0) || buf.iter().all(|b| *b == 0xff)
+}
+
+/// Scan every block that is not part of a live save, or an intact deleted save, for a plausible
+/// title frame.
+pub(crate) fn scan_orphans(card: &MemCard) -> Vec<OrphanSave<'_>> {
+    let dir = &card.info.dir_frames;
+
+    // Blocks that are accounted for by the directory
+    let mut claimed = vec![false; dir.len()];
+    for (n, d) in dir.iter().enumerate() {
+        match d.get_alloc_state() {
+            BAState::AllocFirst | BAState::AllocMid | BAState::AllocLast => claimed[n] = true,
+            _ => (),
+        }
+    }
+    for save in card.deleted_saves() {
+        for b in save.blocks {
+            claimed[b] = true;
+        }
+    }
+
+    orphans(&card.data, &claimed)
+}
+
+/// Find a plausible title frame in every block of `data` that is not `claimed`, and assume the
+/// contiguous unclaimed blocks after it that are not blank and do not start another save belong
+/// to it as well.
+fn orphans<'a>(data: &'a [DataBlock], claimed: &[bool]) -> Vec<OrphanSave<'a>> {
+    let mut found = Vec::<OrphanSave>::new();
+    let mut n = 0;
+    while n < data.len() {
+        if claimed[n] || !plausible_title_frame(&data[n].title_frame) {
+            n += 1;
+            continue;
+        }
+
+        let mut blocks = vec![n];
+        let mut next = n + 1;
+        while next < data.len()
+            && !claimed[next]
+            && data[next].title_frame.id != TITLE_MAGIC
+            && !is_blank(&data[next])
+        {
+            blocks.push(next);
+            next += 1;
+        }
+
+        let found_data = blocks.iter().map(|b| &data[*b]).collect();
+        found.push(OrphanSave {
+            index: n,
+            blocks,
+            data: found_data,
+        });
+        n = next;
+    }
+
+    found
+}
+
+/// Allocate a directory entry for every save found by `scan_orphans`, chaining its blocks in
+/// order.
+pub(crate) fn rebuild_directory(card: &mut MemCard) -> Result<Vec<RebuiltSave>, MCError> {
+    let found: Vec<(usize, Vec<usize>, String)> = scan_orphans(card)
+        .into_iter()
+        .map(|o| (o.index, o.blocks.clone(), synth_filename(&o)))
+        .collect();
+
+    let mut rebuilt = Vec::with_capacity(found.len());
+    for (index, blocks, filename) in found {
+        for (n, b) in blocks.iter().enumerate() {
+            let state = match n {
+                0 => BAState::AllocFirst,
+                _ if n == blocks.len() - 1 => BAState::AllocLast,
+                _ => BAState::AllocMid,
+            };
+            let filesize = match n {
+                0 => (blocks.len() * BLOCK_SIZE) as u32,
+                _ => 0,
+            };
+            let next = blocks.get(n + 1).map_or(CHAIN_END, |b| *b as u16);
+
+            card.info.dir_frames[*b] = DirectoryFrame::builder()
+                .state(state)
+                .filesize(filesize)
+                .next_block(next)
+                .filename(if n == 0 { &filename } else { "" })
+                .build()?;
+        }
+
+        rebuilt.push(RebuiltSave {
+            index,
+            blocks,
+            filename,
+        });
+    }
+
+    Ok(rebuilt)
+}
+
+/// Rebuild a card from the raw image `data`, whose `InfoBlock` is ignored and replaced by a
+/// freshly formatted one.
+pub(crate) fn rebuild(data: &[u8]) -> Result<(MemCard, Vec<RebuiltSave>), MCError> {
+    if data.len() != BLOCK_SIZE * BLOCKS_PER_CARD {
+        return Err(MCError::InvalidValue(format!(
+            "{} bytes is not a card image size",
+            data.len()
+        )));
+    }
+
+    let mut blocks = Vec::<DataBlock>::with_capacity(BLOCKS_PER_CARD - 1);
+    for (n, b) in data[BLOCK_SIZE..].chunks_exact(BLOCK_SIZE).enumerate() {
+        blocks.push(DataBlock::parse_block(n + 1, b)?);
+    }
+
+    let mut card = MemCard::from_parts(InfoBlock::new(), blocks);
+    let rebuilt = rebuild_directory(&mut card)?;
+
+    Ok((card, rebuilt))
+}
+
+impl InfoBlock {
+    /// Regenerate every directory entry from the data blocks `data`, so the directory matches
+    /// them after low-level edits. Saves listed in the directory whose first block still holds a
+    /// title frame keep their filename and the blocks of their chain, up to a block that is
+    /// claimed by another save or starts one itself. Live saves are kept before deleted ones.
+    /// Title frames in the remaining blocks become new saves as in
+    /// `MemCard::rebuild_directory`, and every other block is freed. The states, links,
+    /// filesizes and checksums of all entries are rewritten. Returns the new saves, or
+    /// `MCError::InvalidValue` if there is not one data block for every directory entry.
+    pub fn rebuild(&mut self, data: &[DataBlock]) -> Result<Vec<RebuiltSave>, MCError> {
+        if data.len() != self.dir_frames.len() {
+            return Err(MCError::InvalidValue(format!(
+                "{} data blocks for {} directory entries",
+                data.len(),
+                self.dir_frames.len()
+            )));
+        }
+
+        let old = &self.dir_frames;
+        let mut claimed = vec![false; old.len()];
+        let mut chains = Vec::<(Vec<usize>, [u8; 21], bool)>::new();
+        for first in [BAState::AllocFirst, BAState::FreeFirst] {
+            for start in 0..old.len() {
+                if old[start].get_alloc_state() != first
+                    || claimed[start]
+                    || data[start].title_frame.id != TITLE_MAGIC
+                {
+                    continue;
+                }
+
+                let (walked, _) = walk_chain(old, start);
+                let mut blocks = vec![start];
+                for b in walked.into_iter().skip(1) {
+                    if claimed[b] || plausible_title_frame(&data[b].title_frame) {
+                        break;
+                    }
+                    blocks.push(b);
+                }
+                for b in &blocks {
+                    claimed[*b] = true;
+                }
+                chains.push((blocks, old[start].filename, first == BAState::FreeFirst));
+            }
+        }
+
+        let mut rebuilt = Vec::new();
+        for o in orphans(data, &claimed) {
+            let filename = synth_filename(&o);
+            let mut raw = [0u8; 21];
+            raw[..filename.len()].copy_from_slice(filename.as_bytes());
+            chains.push((o.blocks.clone(), raw, false));
+            rebuilt.push(RebuiltSave {
+                index: o.index,
+                blocks: o.blocks,
+                filename,
+            });
+        }
+
+        let mut dir = vec![DirectoryFrame::free(); old.len()];
+        for (blocks, filename, deleted) in chains {
+            for (n, b) in blocks.iter().enumerate() {
+                let state = match (n, deleted) {
+                    (0, false) => BAState::AllocFirst,
+                    (0, true) => BAState::FreeFirst,
+                    (_, false) if n == blocks.len() - 1 => BAState::AllocLast,
+                    (_, true) if n == blocks.len() - 1 => BAState::FreeLast,
+                    (_, false) => BAState::AllocMid,
+                    (_, true) => BAState::FreeMid,
+                };
+                let next = blocks.get(n + 1).map_or(CHAIN_END, |b| *b as u16);
+                dir[*b].edit(|d| {
+                    d.set_alloc_state(state);
+                    d.next_block = next;
+                    if n == 0 {
+                        d.filesize = (blocks.len() * BLOCK_SIZE) as u32;
+                        d.filename = filename;
+                    }
+                })?;
+            }
+        }
+        self.dir_frames = dir;
+
+        Ok(rebuilt)
+    }
+}
+
+/// Synthesize a filename for `save`. The original product code can not be recovered, so a
+/// placeholder numbered by the slot of the save takes its place. The region is Japan if the
+/// title holds kana or kanji, America otherwise, and the name is the start of the title.
+fn synth_filename(save: &OrphanSave) -> String {
+    let t = save.title_frame();
+
+    // Of the Shift JIS lead bytes, 0x81 only encodes punctuation, and 0x82 encodes digits and
+    // latin letters below 0x9f and hiragana from there on
+    let mut japanese = false;
+    let mut p = 0;
+    while p < t.title.len() && t.title[p] != 0 {
+        match (t.title[p], t.title.get(p + 1).copied().unwrap_or(0)) {
+            (0x82, 0x9f..) | (0x83..=0x9f | 0xe0..=0xef, _) => {
+                japanese = true;
+                break;
+            }
+            (0x81 | 0x82, _) => p += 2,
+            _ => p += 1,
+        }
+    }
+    let region = if japanese { 'I' } else { 'A' };
+
+    let mut name: String = t
+        .decode_title()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .take(8)
+        .collect();
+    if name.is_empty() {
+        name = "SAVE".to_string();
+    }
+
+    format!("B{}RECOV-{:04}{}", region, save.index + 1, name)
+}
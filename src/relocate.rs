@@ -0,0 +1,122 @@
+//! Moving saves off of the frames listed in the broken frame table.
+
+use std::fmt;
+
+use crate::{
+    BAState, BrokenFrame, DataBlock, DirectoryFrame, Frame, MCError, MemCard, BLOCK_SIZE,
+    FRAMES_PER_BLOCK, FRAME_SIZE,
+};
+
+/// Relocation
+///
+/// A block of a save that was moved by `MemCard::relocate_broken`. Blocks are directory indices
+/// (0..15).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    /// The block that holds a broken frame.
+    pub from: usize,
+
+    /// The healthy block the data was moved to.
+    pub to: usize,
+}
+
+impl fmt::Display for Relocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Moved block {} to block {}", self.from, self.to)
+    }
+}
+
+/// The broken frame table entries of `card` that are in use, with the directory index of the
+/// block that holds their frame.
+fn broken_entries(card: &MemCard) -> Vec<(usize, usize)> {
+    card.info
+        .broken_frames
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.broken_frame != 0xffffffff)
+        .filter_map(|(n, b)| {
+            // Block 0 and frames past the end of the card can not be relocated
+            let block = (b.broken_frame as usize / FRAMES_PER_BLOCK).checked_sub(1)?;
+            (block < card.data.len()).then_some((n, block))
+        })
+        .collect()
+}
+
+/// Move every block of a live save that holds a broken frame to a free block without one.
+pub(crate) fn relocate_broken(card: &mut MemCard) -> Result<Vec<Relocation>, MCError> {
+    let entries = broken_entries(card);
+    let mut bad = vec![false; card.data.len()];
+    for (_, block) in &entries {
+        bad[*block] = true;
+    }
+
+    let allocated = |d: &DirectoryFrame| {
+        matches!(
+            d.get_alloc_state(),
+            BAState::AllocFirst | BAState::AllocMid | BAState::AllocLast
+        )
+    };
+    let mut free = card
+        .info
+        .dir_frames
+        .iter()
+        .enumerate()
+        .filter(|(n, d)| !allocated(d) && !bad[*n])
+        .map(|(n, _)| n);
+
+    // Plan every move first, so the card is left untouched if there is no room
+    let mut moves = Vec::<(Option<usize>, Relocation)>::new();
+    for save in card.saves() {
+        for (n, from) in save.blocks.iter().enumerate() {
+            if bad[*from] {
+                let to = free.next().ok_or(MCError::CardFull)?;
+                let prev = n.checked_sub(1).map(|p| save.blocks[p]);
+                moves.push((prev, Relocation { from: *from, to }));
+            }
+        }
+    }
+
+    for (prev, r) in &moves {
+        let mut b = [0u8; BLOCK_SIZE];
+        card.data[r.from].write_raw(&mut b)?;
+
+        // The replacement frame of broken frame entry `n` is unused frame `n`. Dumps of cards
+        // that never used it hold fill there, in which case the frame is copied as it is.
+        for (n, block) in &entries {
+            if block != &r.from {
+                continue;
+            }
+            let replacement = card.info.unused_frames[*n].data;
+            if replacement.iter().all(|b| *b == 0xff) || replacement.iter().all(|b| *b == 0) {
+                continue;
+            }
+            let frame = card.info.broken_frames[*n].broken_frame as usize % FRAMES_PER_BLOCK;
+            b[frame * FRAME_SIZE..(frame + 1) * FRAME_SIZE].copy_from_slice(&replacement);
+        }
+
+        card.data[r.to] = DataBlock::from_raw(&b);
+        card.info.dir_frames[r.to] = card.info.dir_frames[r.from];
+        card.info.dir_frames[r.from] = DirectoryFrame::free();
+        if let Some(p) = prev {
+            // The previous block may have been moved as well
+            let p = moves
+                .iter()
+                .find(|(_, m)| m.from == *p)
+                .map_or(*p, |(_, m)| m.to);
+            card.info.dir_frames[p].edit(|d| d.next_block = r.to as u16)?;
+        }
+    }
+
+    // Release the entries of the frames that no longer hold save data. The console lists frames
+    // that are still bad again when it next fails to write them.
+    for (n, block) in entries {
+        if moves.iter().any(|(_, r)| r.from == block) {
+            card.info.broken_frames[n] = BrokenFrame::none();
+            card.info.unused_frames[n] = Frame {
+                data: [0xff; FRAME_SIZE],
+            };
+        }
+    }
+
+    Ok(moves.into_iter().map(|(_, r)| r).collect())
+}
@@ -0,0 +1,188 @@
+//! Syncing memory card images with network attached card emulators, such as the MemCard PRO2,
+//! over their FTP interface.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+use crate::{CardGeometry, MCError, MemCard};
+
+/// The directory that holds the virtual cards, one directory per game.
+pub const CARD_DIR: &str = "/MemoryCards";
+
+/// The extensions of card image files.
+const CARD_EXTENSIONS: [&str; 2] = [".mcd", ".mcr"];
+
+/// RemoteCards
+///
+/// A connection to the FTP server of a card emulator. Virtual cards are listed with `list`,
+/// downloaded into `MemCard`s with `download`, and written back with `upload`. Transfers use
+/// passive mode, so they work from behind NAT.
+#[derive(Debug)]
+pub struct RemoteCards {
+    control: BufReader<TcpStream>,
+    peer: SocketAddr,
+}
+
+impl RemoteCards {
+    /// Connect to the FTP server at `addr` and log in.
+    pub fn connect<A: ToSocketAddrs>(addr: A, user: &str, password: &str) -> Result<Self, MCError> {
+        let stream = TcpStream::connect(addr)?;
+        let peer = stream.peer_addr()?;
+        let mut remote = RemoteCards {
+            control: BufReader::new(stream),
+            peer,
+        };
+
+        remote.response()?;
+        if remote.command(&format!("USER {}", user))?.0 == 331 {
+            remote.command(&format!("PASS {}", password))?;
+        }
+        remote.command("TYPE I")?;
+
+        Ok(remote)
+    }
+
+    /// List the paths of all card images below `CARD_DIR`.
+    pub fn list(&mut self) -> Result<Vec<String>, MCError> {
+        let mut cards = Vec::new();
+        for entry in self.list_dir(CARD_DIR)? {
+            if is_card(&entry) {
+                cards.push(entry);
+            } else {
+                cards.extend(self.list_dir(&entry)?.into_iter().filter(|e| is_card(e)));
+            }
+        }
+        cards.sort();
+
+        Ok(cards)
+    }
+
+    /// List the paths of the entries of the directory `dir`.
+    pub fn list_dir(&mut self, dir: &str) -> Result<Vec<String>, MCError> {
+        let listing = self.transfer(&format!("NLST {}", dir), None)?;
+
+        Ok(String::from_utf8_lossy(&listing)
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.ends_with("/.") && !l.ends_with("/.."))
+            .map(|l| match l.contains('/') {
+                true => l.to_string(),
+                false => format!("{}/{}", dir.trim_end_matches('/'), l),
+            })
+            .collect())
+    }
+
+    /// Download the card image at `path`.
+    pub fn download(&mut self, path: &str) -> Result<MemCard, MCError> {
+        let image = self.transfer(&format!("RETR {}", path), None)?;
+        if image.len() != CardGeometry::STANDARD.size() {
+            return Err(MCError::InvalidValue(format!(
+                "{} is {} bytes, not a standard card image",
+                path,
+                image.len()
+            )));
+        }
+
+        MemCard::from_reader(&image[..]).map_err(|e| e.context(format!("Unable to open {}", path)))
+    }
+
+    /// Upload `card` to `path`, replacing the image that is there.
+    pub fn upload(&mut self, path: &str, card: &MemCard) -> Result<(), MCError> {
+        let mut image = Vec::<u8>::with_capacity(CardGeometry::STANDARD.size());
+        card.write_into(&mut image)?;
+        self.transfer(&format!("STOR {}", path), Some(&image))?;
+
+        Ok(())
+    }
+
+    /// Log out and close the connection.
+    pub fn quit(mut self) -> Result<(), MCError> {
+        self.command("QUIT")?;
+
+        Ok(())
+    }
+
+    /// Run the transfer command `cmd` over a passive data connection. If `upload` is set it is
+    /// sent, otherwise everything the server sends is returned.
+    fn transfer(&mut self, cmd: &str, upload: Option<&[u8]>) -> Result<Vec<u8>, MCError> {
+        let (_, text) = self.command("PASV")?;
+        let port = parse_pasv(&text)?;
+        let mut data = TcpStream::connect(SocketAddr::new(self.peer.ip(), port))?;
+
+        self.command(cmd)?;
+        let mut out = Vec::new();
+        match upload {
+            Some(u) => data.write_all(u)?,
+            None => {
+                data.read_to_end(&mut out)?;
+            }
+        }
+        drop(data);
+        self.response()?;
+
+        Ok(out)
+    }
+
+    /// Send `cmd` and read the response.
+    fn command(&mut self, cmd: &str) -> Result<(u32, String), MCError> {
+        trace_event!(trace, verb = cmd.split(' ').next(), "ftp command");
+        // Send the command in one write, as a separate line ending could be held back by Nagle
+        self.control
+            .get_mut()
+            .write_all(format!("{}\r\n", cmd).as_bytes())?;
+
+        self.response()
+    }
+
+    /// Read a response, which can span multiple lines. Error codes are returned as errors.
+    fn response(&mut self) -> Result<(u32, String), MCError> {
+        let mut line = String::new();
+        self.control.read_line(&mut line)?;
+        let code = line
+            .get(..3)
+            .and_then(|c| c.parse::<u32>().ok())
+            .ok_or_else(|| ftp_error(&line))?;
+
+        // Multi-line responses continue until the code is repeated followed by a space
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let end = format!("{} ", code);
+            let mut next = String::new();
+            while !next.starts_with(&end) {
+                next.clear();
+                if self.control.read_line(&mut next)? == 0 {
+                    return Err(ftp_error(&line));
+                }
+            }
+        }
+
+        if code >= 400 {
+            return Err(ftp_error(&line));
+        }
+
+        Ok((code, line.trim_end().to_string()))
+    }
+}
+
+fn is_card(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    CARD_EXTENSIONS.iter().any(|e| lower.ends_with(e))
+}
+
+fn ftp_error(line: &str) -> MCError {
+    io::Error::other(format!("FTP error: {}", line.trim_end())).into()
+}
+
+/// Get the port from a "227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)" response. The address is
+/// ignored, as servers behind NAT often report their private address.
+fn parse_pasv(text: &str) -> Result<u16, MCError> {
+    let fields: Vec<u16> = text
+        .rsplit_once('(')
+        .and_then(|(_, f)| f.split_once(')'))
+        .map(|(f, _)| f.split(',').filter_map(|v| v.trim().parse().ok()).collect())
+        .unwrap_or_default();
+
+    match fields[..] {
+        [_, _, _, _, hi, lo] => Ok(hi << 8 | lo),
+        _ => Err(ftp_error(text)),
+    }
+}
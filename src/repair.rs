@@ -0,0 +1,188 @@
+//! Automatic repair of the directory of a memory card.
+
+use std::fmt;
+
+use crate::validate::walk_chain;
+use crate::{
+    update_checksum, BAState, DirectoryFrame, Finding, InfoBlock, MCError, MemCard, BLOCK_SIZE,
+    CHAIN_END, FRAME_SIZE,
+};
+
+/// RepairOptions
+///
+/// The repairs `MemCard::repair` makes. By default every repair is made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepairOptions {
+    /// Recompute the checksums of the frames of the `InfoBlock` that do not match.
+    pub fix_checksums: bool,
+
+    /// End allocation chains that loop, link out of range, or link to a block that is not part
+    /// of the save, at the last good block.
+    pub terminate_chains: bool,
+
+    /// Set the filesize of every save to the size of its allocation chain.
+    pub fix_filesizes: bool,
+
+    /// Free directory entries with a nonstandard state, and mark middle and last blocks that no
+    /// chain reaches as deleted, so their data can still be recovered.
+    pub clear_invalid_states: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        RepairOptions {
+            fix_checksums: true,
+            terminate_chains: true,
+            fix_filesizes: true,
+            clear_invalid_states: true,
+        }
+    }
+}
+
+/// RepairAction
+///
+/// A change made by `MemCard::repair`. Blocks are directory indices (0..15), frames are frames
+/// of card block 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairAction {
+    /// The nonstandard `state` of `block` was replaced by a free directory entry.
+    ClearedState { block: usize, state: u32 },
+
+    /// The chain that starts at `start` was ended at `block`, which linked to `next`.
+    TerminatedChain {
+        start: usize,
+        block: usize,
+        next: u16,
+    },
+
+    /// `block` was not reached by any chain, and was marked as deleted.
+    FreedOrphan { block: usize },
+
+    /// The filesize of the save that starts at `block` was changed from `old` to `new`.
+    FixedFilesize { block: usize, old: u32, new: u32 },
+
+    /// The checksum of `frame` was recomputed.
+    FixedChecksum { frame: usize },
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairAction::ClearedState { block, state } => {
+                write!(f, "Cleared block {} with state {:#x}", block, state)
+            }
+            RepairAction::TerminatedChain { start, block, next } => write!(
+                f,
+                "Ended the chain of block {} at block {}, which linked to {:#x}",
+                start, block, next
+            ),
+            RepairAction::FreedOrphan { block } => write!(f, "Freed orphaned block {}", block),
+            RepairAction::FixedFilesize { block, old, new } => write!(
+                f,
+                "Changed the filesize of block {} from {} to {}",
+                block, old, new
+            ),
+            RepairAction::FixedChecksum { frame } => {
+                write!(f, "Fixed the checksum of frame {} of block 0", frame)
+            }
+        }
+    }
+}
+
+/// Repair `card` as selected by `opts`, returning every change made. The repairs are made in
+/// the order states, chains, orphans, filesizes, checksums, since each can uncover the next.
+pub(crate) fn repair(
+    card: &mut MemCard,
+    opts: RepairOptions,
+) -> Result<Vec<RepairAction>, MCError> {
+    let mut log = Vec::new();
+    let dir = &mut card.info.dir_frames;
+
+    if opts.clear_invalid_states {
+        for (block, d) in dir.iter_mut().enumerate() {
+            if d.get_alloc_state() == BAState::UNKNOWN {
+                log.push(RepairAction::ClearedState {
+                    block,
+                    state: d.state,
+                });
+                *d = DirectoryFrame::free();
+            }
+        }
+    }
+
+    if opts.terminate_chains {
+        for start in first_blocks(dir) {
+            // Every pass ends the chain one block earlier, so this ends within `dir.len()` passes
+            while let (blocks, Some(finding)) = walk_chain(dir, start) {
+                let block = match finding {
+                    Finding::ChainBadLink { .. } => blocks[blocks.len() - 2],
+                    Finding::ChainBadEnd { block, .. } => block,
+                    _ => blocks[blocks.len() - 1],
+                };
+                log.push(RepairAction::TerminatedChain {
+                    start,
+                    block,
+                    next: dir[block].next_block,
+                });
+                dir[block].edit(|d| {
+                    d.next_block = CHAIN_END;
+                    if block != start {
+                        d.set_alloc_state(BAState::AllocLast);
+                    }
+                })?;
+            }
+        }
+    }
+
+    if opts.clear_invalid_states {
+        for f in crate::validate::validate_chains(dir) {
+            if let Finding::ChainOrphan { block } = f {
+                let state = match dir[block].get_alloc_state() {
+                    BAState::AllocLast => BAState::FreeLast,
+                    _ => BAState::FreeMid,
+                };
+                dir[block].edit(|d| d.set_alloc_state(state))?;
+                log.push(RepairAction::FreedOrphan { block });
+            }
+        }
+    }
+
+    if opts.fix_filesizes {
+        for start in first_blocks(dir) {
+            let (blocks, _) = walk_chain(dir, start);
+            let (old, new) = (dir[start].filesize, (blocks.len() * BLOCK_SIZE) as u32);
+            if old != new {
+                dir[start].edit(|d| d.filesize = new)?;
+                log.push(RepairAction::FixedFilesize {
+                    block: start,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    if opts.fix_checksums {
+        let bad = card.info.validate_checksums();
+        if !bad.is_empty() {
+            let mut b = [0u8; BLOCK_SIZE];
+            card.info.write_raw(&mut b);
+            for f in bad {
+                if let Finding::BadChecksum { frame, .. } = f {
+                    update_checksum(&mut b[frame * FRAME_SIZE..(frame + 1) * FRAME_SIZE])?;
+                    log.push(RepairAction::FixedChecksum { frame });
+                }
+            }
+            card.info = InfoBlock::from_raw(&b);
+        }
+    }
+
+    Ok(log)
+}
+
+/// The directory indices that start an allocated save.
+fn first_blocks(dir: &[DirectoryFrame]) -> Vec<usize> {
+    (0..dir.len())
+        .filter(|n| dir[*n].get_alloc_state() == BAState::AllocFirst)
+        .collect()
+}
@@ -0,0 +1,316 @@
+//! Views of the save files stored on a memory card.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::validate::walk_chain;
+use crate::{
+    BAState, DataBlock, DirectoryFrame, Frame, License, MCError, MemCard, Region, RegionInfo,
+    TitleFrame, BLOCK_SIZE, FRAME_SIZE, TITLE_MAGIC,
+};
+
+/// Save
+///
+/// A `Save` pairs the directory entry of a save file with the `DataBlock`s its allocation chain
+/// points to. Saves that were deleted, but whose blocks have not been reused yet, are also
+/// represented as a `Save` with `deleted` set.
+#[derive(Clone)]
+pub struct Save<'a> {
+    /// The directory index of the first block of the save.
+    pub index: usize,
+
+    /// The directory frame of the first block of the save.
+    pub dir_frame: &'a DirectoryFrame,
+
+    /// The directory indices of every block in the save, in chain order.
+    pub blocks: Vec<usize>,
+
+    /// The data blocks of the save, in chain order.
+    pub data: Vec<&'a DataBlock>,
+
+    /// Whether this save has been deleted (its blocks are marked as free).
+    pub deleted: bool,
+
+    /// The decoded titles of the card the save is on.
+    titles: &'a TitleCache,
+}
+
+/// Saves are equal if their blocks are, whatever titles were decoded already.
+impl PartialEq for Save<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.dir_frame == other.dir_frame
+            && self.blocks == other.blocks
+            && self.data == other.data
+            && self.deleted == other.deleted
+    }
+}
+
+impl Eq for Save<'_> {}
+
+impl fmt::Debug for Save<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Save")
+            .field("index", &self.index)
+            .field("dir_frame", &self.dir_frame)
+            .field("blocks", &self.blocks)
+            .field("data", &self.data)
+            .field("deleted", &self.deleted)
+            .finish()
+    }
+}
+
+/// The decoded titles of the data blocks of a card, each along with the raw title it was decoded
+/// from, so a title that was changed since is decoded again.
+#[derive(Debug, Default)]
+pub(crate) struct TitleCache {
+    pub(crate) titles: Mutex<Vec<Option<CachedTitle>>>,
+}
+
+/// A raw title, and the title decoded from it.
+type CachedTitle = ([u8; 64], String);
+
+impl TitleCache {
+    /// Decode the title frame `t` of data block `n`, reusing the title decoded before if the raw
+    /// title is the same. Returns `MCError::NotASave` if `t` is not a title frame.
+    pub(crate) fn get(&self, n: usize, t: &TitleFrame) -> Result<String, MCError> {
+        t.validate()?;
+        let mut titles = self.titles.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(Some((raw, title))) = titles.get(n) {
+            if *raw == t.title {
+                return Ok(title.clone());
+            }
+        }
+
+        let title = t.decode_title()?;
+        if titles.len() <= n {
+            titles.resize(n + 1, None);
+        }
+        titles[n] = Some((t.title, title.clone()));
+
+        Ok(title)
+    }
+}
+
+impl Clone for TitleCache {
+    fn clone(&self) -> Self {
+        let titles = self.titles.lock().unwrap_or_else(|e| e.into_inner());
+        TitleCache {
+            titles: Mutex::new(titles.clone()),
+        }
+    }
+}
+
+/// SaveMetadata
+///
+/// The details of a save, gathered from both its directory frame and its title frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveMetadata {
+    /// The decoded title of the save.
+    pub title: String,
+
+    /// The product code of the game, such as "SLUS-00123".
+    pub serial: String,
+
+    /// The region of the game, from the directory filename.
+    pub region: Region,
+
+    /// The license of the game, from the directory filename.
+    pub license: License,
+
+    /// The game specific part of the directory filename.
+    pub name: String,
+
+    /// The size of the save in bytes, as recorded in the directory.
+    pub size: u32,
+
+    /// The number of blocks in the save's allocation chain.
+    pub blocks: usize,
+
+    /// The number of icon frames.
+    pub icon_frames: usize,
+
+    /// The allocation state of the first block of the save.
+    pub state: BAState,
+}
+
+impl<'a> Save<'a> {
+    /// Build the `Save` whose chain starts at directory index `index`. If `strict` is set,
+    /// returns `None` if the chain is broken or the first block does not hold a title frame.
+    /// Otherwise the save holds the blocks that could be walked before the chain broke.
+    pub(crate) fn from_chain(card: &'a MemCard, index: usize, strict: bool) -> Option<Self> {
+        let dir = &card.info.dir_frames;
+        let (blocks, finding) = walk_chain(dir, index);
+        if strict && finding.is_some() {
+            return None;
+        }
+
+        let data: Vec<&DataBlock> = blocks.iter().map(|b| &card.data[*b]).collect();
+        if strict && data[0].title_frame.id != TITLE_MAGIC {
+            return None;
+        }
+
+        Some(Save {
+            index,
+            dir_frame: &dir[index],
+            blocks,
+            data,
+            deleted: dir[index].get_alloc_state() == BAState::FreeFirst,
+            titles: &card.titles,
+        })
+    }
+
+    /// Decode the title of the save. Returns `MCError::NotASave` if the first block does not
+    /// hold a title frame. Titles are cached by the card, so they are only decoded again once
+    /// the title frame changes.
+    pub fn title(&self) -> Result<String, MCError> {
+        self.titles.get(self.index, &self.data[0].title_frame)
+    }
+
+    /// Get the region, license and name info from the directory filename.
+    pub fn region_info(&self) -> Result<RegionInfo, MCError> {
+        self.dir_frame.get_region_info()
+    }
+
+    /// Get the product code of the save, such as "SLUS-00123".
+    pub fn serial(&self) -> String {
+        self.dir_frame.get_serial()
+    }
+
+    /// The directory filename of the save, such as "BASLUS-00123GAME", which identifies the
+    /// save across cards.
+    pub fn filename(&self) -> String {
+        self.dir_frame.get_filename()
+    }
+
+    /// The size of the save in bytes, as recorded in the directory.
+    pub fn size(&self) -> u32 {
+        self.dir_frame.filesize
+    }
+
+    /// The icon frames of the save.
+    pub fn icon_frames(&self) -> &'a [Frame] {
+        &self.data[0].icon_frames
+    }
+
+    /// The payload of the save: everything after the title and icon frames, that is the rest of
+    /// the first block followed by the following blocks in chain order. This is the data that
+    /// `SaveCursor` reads and writes.
+    pub fn payload(&self) -> Result<Vec<u8>, MCError> {
+        let start = FRAME_SIZE * (1 + self.icon_frames().len());
+        let mut image = vec![0u8; self.data.len() * BLOCK_SIZE];
+        for (d, b) in self.data.iter().zip(image.as_chunks_mut::<BLOCK_SIZE>().0) {
+            d.write_raw(b)?;
+        }
+        image.drain(..start);
+
+        Ok(image)
+    }
+
+    /// Read `len` bytes of the payload, starting at `offset`. Returns `MCError::InvalidValue` if
+    /// the range reaches past the end of the payload.
+    pub fn read_at(&self, offset: usize, len: usize) -> Result<Vec<u8>, MCError> {
+        let payload = self.payload()?;
+        let range = payload_range(offset, len, payload.len())?;
+
+        Ok(payload[range].to_vec())
+    }
+
+    /// Export the save as a `.mcs` file: its directory frame followed by its data blocks.
+    pub fn to_mcs(&self) -> Result<Vec<u8>, MCError> {
+        crate::mcs::export(self)
+    }
+
+    /// Gather the details of the save from its directory and title frames.
+    pub fn metadata(&self) -> Result<SaveMetadata, MCError> {
+        let info = self.region_info()?;
+
+        Ok(SaveMetadata {
+            title: self.title()?,
+            serial: self.serial(),
+            region: info.region,
+            license: info.license,
+            name: info.name.trim_end_matches('\0').to_string(),
+            size: self.size(),
+            blocks: self.blocks.len(),
+            icon_frames: self.icon_frames().len(),
+            state: self.dir_frame.get_alloc_state(),
+        })
+    }
+}
+
+/// SaveHandle
+///
+/// An owned handle to a save, for holding on to saves beyond the borrow of the `MemCard`, such as
+/// in the list of a card browser. Handles share the card through an `Arc`, so creating them never
+/// copies any blocks. Modifying the card through a handle is copy-on-write: the card is only
+/// cloned if other handles still share it.
+#[derive(Clone, Debug)]
+pub struct SaveHandle {
+    card: Arc<MemCard>,
+    index: usize,
+}
+
+impl SaveHandle {
+    /// Create a handle to the save that starts at directory index `index`. Returns `None` if
+    /// the index is out of range.
+    pub fn new(card: Arc<MemCard>, index: usize) -> Option<Self> {
+        if index >= card.info.dir_frames.len() {
+            return None;
+        }
+
+        Some(SaveHandle { card, index })
+    }
+
+    /// The directory index of the first block of the save.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The card the save is stored on.
+    pub fn card(&self) -> &Arc<MemCard> {
+        &self.card
+    }
+
+    /// Borrow the save. The chain is walked again on every call, so the `Save` always reflects
+    /// the current contents of the card.
+    pub fn save(&self) -> Save<'_> {
+        Save::from_chain(&self.card, self.index, false).expect("lenient chain walks always succeed")
+    }
+
+    /// Get mutable access to the card, cloning it first if it is shared with other handles.
+    pub fn card_mut(&mut self) -> &mut MemCard {
+        Arc::make_mut(&mut self.card)
+    }
+
+    /// Overwrite the payload of the save at `offset` with `bytes`, cloning the card first if it is
+    /// shared with other handles. See `MemCard::patch_save`.
+    pub fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<(), MCError> {
+        let index = self.index;
+        self.card_mut().patch_save(index, offset, bytes)
+    }
+
+    /// Get mutable access to block `n` of the save, in chain order, cloning the card first if it
+    /// is shared with other handles. Returns `None` if the save has fewer blocks.
+    pub fn block_mut(&mut self, n: usize) -> Option<&mut DataBlock> {
+        let b = *self.save().blocks.get(n)?;
+        Some(&mut self.card_mut().data[b])
+    }
+}
+
+/// The range of `len` bytes at `offset` of a payload of `size` bytes. Returns
+/// `MCError::InvalidValue` if it reaches past the end.
+pub(crate) fn payload_range(
+    offset: usize,
+    len: usize,
+    size: usize,
+) -> Result<Range<usize>, MCError> {
+    match offset.checked_add(len) {
+        Some(end) if end <= size => Ok(offset..end),
+        _ => Err(MCError::InvalidValue(format!(
+            "{} bytes at offset {} are past the end of the {} byte payload",
+            len, offset, size
+        ))),
+    }
+}
@@ -0,0 +1,269 @@
+//! Declarative descriptions of the fields in the payload of a game's saves, loaded from JSON or
+//! TOML, so that community maintained schemas can drive generic save editors.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FieldValue, GameSaveCodec, MCError, MemCard, Save, SaveFields};
+
+/// FieldType
+///
+/// How a field of a `SaveSchema` is stored in the payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+
+    /// ASCII text of `len` bytes, padded with NULs.
+    Text,
+
+    /// Raw bytes, `len` of them.
+    Bytes,
+}
+
+/// Endian
+///
+/// The byte order of an integer field. The PS1 is little endian, so that is the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// SchemaField
+///
+/// A field of a `SaveSchema`, at `offset` bytes into the payload of the save.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub name: String,
+    pub offset: usize,
+    #[serde(rename = "type")]
+    pub ty: FieldType,
+    #[serde(default)]
+    pub endian: Endian,
+
+    /// The length of text and bytes fields. Integer fields have the length of their type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub len: Option<usize>,
+}
+
+/// SaveSchema
+///
+/// The fields of the saves of a game, such as:
+///
+/// ```toml
+/// name = "ff7"
+/// serials = ["SCUS-94163", "SCUS-94164"]
+///
+/// [[fields]]
+/// name = "gil"
+/// offset = 0x0b7c
+/// type = "u32"
+/// ```
+///
+/// A schema is also a `GameSaveCodec`, so it can be added to a `CodecRegistry`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveSchema {
+    pub name: String,
+
+    /// The product codes of the saves the schema describes, such as "SCUS-94163".
+    pub serials: Vec<String>,
+
+    pub fields: Vec<SchemaField>,
+}
+
+impl SchemaField {
+    /// The number of bytes the field takes up.
+    pub fn size(&self) -> usize {
+        match self.ty {
+            FieldType::U8 | FieldType::I8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 => 4,
+            FieldType::Text | FieldType::Bytes => self.len.unwrap_or(0),
+        }
+    }
+
+    /// Decode the field from `payload`.
+    pub fn decode(&self, payload: &[u8]) -> Result<FieldValue, MCError> {
+        let raw = self.range(payload.len()).map(|r| &payload[r])?;
+        let value = match self.ty {
+            FieldType::Text => {
+                let end = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+                FieldValue::Text(String::from_utf8_lossy(&raw[..end]).into_owned())
+            }
+            FieldType::Bytes => FieldValue::Bytes(raw.to_vec()),
+            _ => {
+                let mut b = [0u8; 8];
+                match self.endian {
+                    Endian::Little => b[..raw.len()].copy_from_slice(raw),
+                    Endian::Big => raw.iter().rev().zip(&mut b).for_each(|(s, d)| *d = *s),
+                }
+                let n = u64::from_le_bytes(b);
+                let bits = 64 - 8 * raw.len() as u32;
+                FieldValue::Int(match self.ty {
+                    // Sign extend by shifting the top bit of the field into the top of the i64
+                    FieldType::I8 | FieldType::I16 | FieldType::I32 => ((n << bits) as i64) >> bits,
+                    _ => n as i64,
+                })
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Encode `value` into the field in `payload`. Returns `MCError::InvalidValue` if the value
+    /// is of the wrong kind, or does not fit.
+    pub fn encode(&self, value: &FieldValue, payload: &mut [u8]) -> Result<(), MCError> {
+        let range = self.range(payload.len())?;
+        let raw = self.to_bytes(value)?;
+        payload[range].copy_from_slice(&raw);
+
+        Ok(())
+    }
+
+    /// Encode `value` into the bytes of the field.
+    fn to_bytes(&self, value: &FieldValue) -> Result<Vec<u8>, MCError> {
+        let size = self.size();
+        let invalid = || MCError::InvalidValue(format!("{:?} for field {}", value, self.name));
+        match (self.ty, value) {
+            (FieldType::Text, FieldValue::Text(t)) if t.is_ascii() && t.len() <= size => {
+                let mut b = t.as_bytes().to_vec();
+                b.resize(size, 0);
+                Ok(b)
+            }
+            (FieldType::Bytes, FieldValue::Bytes(b)) if b.len() == size => Ok(b.clone()),
+            (FieldType::Text | FieldType::Bytes, _) => Err(invalid()),
+            (ty, FieldValue::Int(n)) => {
+                let bits = 8 * size as u32;
+                let fits = match ty {
+                    FieldType::I8 | FieldType::I16 | FieldType::I32 => {
+                        (-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(n)
+                    }
+                    _ => (0..1i64 << bits).contains(n),
+                };
+                if !fits {
+                    return Err(invalid());
+                }
+
+                let mut b = n.to_le_bytes()[..size].to_vec();
+                if self.endian == Endian::Big {
+                    b.reverse();
+                }
+                Ok(b)
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The bytes of the field in a payload of `len` bytes.
+    fn range(&self, len: usize) -> Result<std::ops::Range<usize>, MCError> {
+        crate::save::payload_range(self.offset, self.size(), len)
+    }
+}
+
+impl SaveSchema {
+    /// Parse a schema from JSON.
+    pub fn from_json(json: &str) -> Result<Self, MCError> {
+        let schema: Self = serde_json::from_str(json)
+            .map_err(|e| MCError::InvalidValue(format!("schema: {}", e)))?;
+        schema.check()
+    }
+
+    /// Parse a schema from TOML.
+    pub fn from_toml(toml: &str) -> Result<Self, MCError> {
+        let schema: Self =
+            toml::from_str(toml).map_err(|e| MCError::InvalidValue(format!("schema: {}", e)))?;
+        schema.check()
+    }
+
+    /// Read a schema from a ".json" or ".toml" file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MCError> {
+        let path = path.as_ref();
+        let load = || -> Result<Self, MCError> {
+            let text = fs::read_to_string(path)?;
+            match path.extension().and_then(|e| e.to_str()) {
+                Some(e) if e.eq_ignore_ascii_case("toml") => Self::from_toml(&text),
+                _ => Self::from_json(&text),
+            }
+        };
+        load().map_err(|e| e.context(format!("Unable to load {}", path.display())))
+    }
+
+    /// Find the field called `name`. Returns `MCError::NotFound` if there is none.
+    pub fn field(&self, name: &str) -> Result<&SchemaField, MCError> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| MCError::NotFound(format!("no field named {}", name)))
+    }
+
+    /// Check that every text and bytes field has a length.
+    fn check(self) -> Result<Self, MCError> {
+        for f in &self.fields {
+            if matches!(f.ty, FieldType::Text | FieldType::Bytes) && f.len.is_none() {
+                return Err(MCError::InvalidValue(format!(
+                    "schema: field {} has no len",
+                    f.name
+                )));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+impl GameSaveCodec for SaveSchema {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, serial: &str) -> bool {
+        self.serials.iter().any(|s| s == serial)
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<SaveFields, MCError> {
+        self.fields
+            .iter()
+            .map(|f| Ok((f.name.clone(), f.decode(payload)?)))
+            .collect()
+    }
+
+    fn encode(&self, fields: &SaveFields, payload: &mut [u8]) -> Result<(), MCError> {
+        for (name, value) in fields {
+            self.field(name)?.encode(value, payload)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Save<'_> {
+    /// Read the field called `name` of `schema` from the payload of the save.
+    pub fn get_field(&self, schema: &SaveSchema, name: &str) -> Result<FieldValue, MCError> {
+        schema.field(name)?.decode(&self.payload()?)
+    }
+}
+
+impl MemCard {
+    /// Write `value` to the field called `name` of `schema` in the payload of the save that
+    /// starts at directory index `index`, as `patch_save` does.
+    pub fn set_save_field(
+        &mut self,
+        index: usize,
+        schema: &SaveSchema,
+        name: &str,
+        value: &FieldValue,
+    ) -> Result<(), MCError> {
+        let field = schema.field(name)?;
+        self.patch_save(index, field.offset, &field.to_bytes(value)?)
+    }
+}
@@ -0,0 +1,176 @@
+//! Searching the payloads of saves for byte patterns, to find where a game keeps a value.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{MCError, MemCard, Save};
+
+/// BytePattern
+///
+/// A sequence of bytes to search for, with a mask that selects the bits of each byte that must
+/// match. A mask byte of 0xff matches the byte exactly, 0x00 matches any byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytePattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+/// SearchMatch
+///
+/// A match of a `BytePattern` found by `MemCard::grep`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// The directory index of the first block of the save.
+    pub index: usize,
+
+    /// The directory filename of the save.
+    pub filename: String,
+
+    /// The offset of the match in the payload of the save, as used by `Save::read_at`.
+    pub offset: usize,
+}
+
+impl BytePattern {
+    /// A pattern that matches `bytes` exactly.
+    pub fn new(bytes: &[u8]) -> Self {
+        BytePattern {
+            bytes: bytes.to_vec(),
+            mask: vec![0xff; bytes.len()],
+        }
+    }
+
+    /// A pattern that matches the bits of `bytes` selected by `mask`. Returns
+    /// `MCError::InvalidValue` if they differ in length.
+    pub fn with_mask(bytes: &[u8], mask: &[u8]) -> Result<Self, MCError> {
+        if bytes.len() != mask.len() {
+            return Err(MCError::InvalidValue(format!(
+                "pattern of {} bytes has a mask of {} bytes",
+                bytes.len(),
+                mask.len()
+            )));
+        }
+
+        Ok(BytePattern {
+            bytes: bytes.to_vec(),
+            mask: mask.to_vec(),
+        })
+    }
+
+    /// The length of the pattern in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the pattern is empty, in which case it never matches.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Check if the pattern matches the start of `data`.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.len()
+            && self
+                .bytes
+                .iter()
+                .zip(&self.mask)
+                .zip(data)
+                .all(|((b, m), d)| (b ^ d) & m == 0)
+    }
+
+    /// The offsets of every match in `data`, including overlapping ones.
+    pub fn find_in(&self, data: &[u8]) -> Vec<usize> {
+        if self.is_empty() || data.len() < self.len() {
+            return Vec::new();
+        }
+
+        (0..=data.len() - self.len())
+            .filter(|n| self.matches(&data[*n..]))
+            .collect()
+    }
+}
+
+impl FromStr for BytePattern {
+    type Err = MCError;
+
+    /// Parse a pattern of hex bytes, such as "de ad ?? ef". Whitespace is ignored, "??" matches
+    /// any byte, and "?" in place of one digit matches any value of that nibble, such as "4?".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) {
+            return Err(MCError::InvalidValue(format!(
+                "pattern {:?} has an odd number of digits",
+                s
+            )));
+        }
+
+        let nibble = |c: char| match c {
+            '?' => Ok((0, 0)),
+            c => c
+                .to_digit(16)
+                .map(|d| (d as u8, 0x0f))
+                .ok_or_else(|| MCError::InvalidValue(format!("pattern {:?} is not hex", s))),
+        };
+
+        let (mut bytes, mut mask) = (Vec::new(), Vec::new());
+        for pair in digits.chunks_exact(2) {
+            let (hi, hi_mask) = nibble(pair[0])?;
+            let (lo, lo_mask) = nibble(pair[1])?;
+            bytes.push(hi << 4 | lo);
+            mask.push(hi_mask << 4 | lo_mask);
+        }
+
+        Ok(BytePattern { bytes, mask })
+    }
+}
+
+impl fmt::Display for BytePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, (b, m)) in self.bytes.iter().zip(&self.mask).enumerate() {
+            if n > 0 {
+                write!(f, " ")?;
+            }
+            for shift in [4, 0] {
+                match (m >> shift) & 0x0f {
+                    0x0f => write!(f, "{:x}", (b >> shift) & 0x0f)?,
+                    _ => write!(f, "?")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SearchMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>2} {} {:#06x}",
+            self.index + 1,
+            self.filename,
+            self.offset
+        )
+    }
+}
+
+impl Save<'_> {
+    /// The payload offsets of every match of `pattern` in the save, including overlapping ones.
+    pub fn find(&self, pattern: &BytePattern) -> Result<Vec<usize>, MCError> {
+        Ok(pattern.find_in(&self.payload()?))
+    }
+}
+
+/// Search the payloads of the live saves of `card` for `pattern`.
+pub(crate) fn grep(card: &MemCard, pattern: &BytePattern) -> Result<Vec<SearchMatch>, MCError> {
+    let mut out = Vec::new();
+    for s in card.saves() {
+        let filename = s.filename();
+        out.extend(s.find(pattern)?.into_iter().map(|offset| SearchMatch {
+            index: s.index,
+            filename: filename.clone(),
+            offset,
+        }));
+    }
+
+    Ok(out)
+}
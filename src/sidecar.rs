@@ -0,0 +1,159 @@
+//! A JSON sidecar file kept next to a card, recording when each save was created and last
+//! modified, since the saves themselves carry no timestamps, along with free-text notes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{atomic, MCError, MemCard, Save, BLOCK_SIZE};
+
+/// SaveNotes
+///
+/// What the sidecar records about one save. Times are in seconds since the Unix epoch, and are
+/// the times `Sidecar::update` first saw the save and first saw its current contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveNotes {
+    pub created: u64,
+    pub modified: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+
+    /// The FNV-1a hash of the data blocks, to notice when the save changes.
+    #[serde(default)]
+    fingerprint: String,
+}
+
+/// Sidecar
+///
+/// The sidecar of a card, with the `SaveNotes` of every save by directory filename. A renamed
+/// save is seen as a new save.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub saves: BTreeMap<String, SaveNotes>,
+}
+
+impl Sidecar {
+    /// The sidecar file of the card file at `card`, named after it, such as "card.mcr.json".
+    pub fn path<P: AsRef<Path>>(card: P) -> PathBuf {
+        let card = card.as_ref();
+        let mut name = card.file_name().unwrap_or_default().to_os_string();
+        name.push(".json");
+        card.with_file_name(name)
+    }
+
+    /// Read the sidecar of the card file at `card`. A card without a sidecar file gets an
+    /// empty one.
+    pub fn load<P: AsRef<Path>>(card: P) -> Result<Self, MCError> {
+        let path = Self::path(card);
+        let read = || -> Result<Self, MCError> {
+            match fs::read(&path) {
+                Ok(json) => Ok(serde_json::from_slice(&json).map_err(io::Error::from)?),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+                Err(e) => Err(e.into()),
+            }
+        };
+        read().map_err(|e| e.context(format!("Unable to read {}", path.display())))
+    }
+
+    /// Write the sidecar of the card file at `card`, replacing it atomically.
+    pub fn store<P: AsRef<Path>>(&self, card: P) -> Result<(), MCError> {
+        let path = Self::path(card);
+        let write = || -> Result<(), MCError> {
+            let json = serde_json::to_vec_pretty(self).map_err(io::Error::from)?;
+            atomic::write_atomic(&path, &json, false)?;
+            Ok(())
+        };
+        write().map_err(|e| e.context(format!("Unable to write {}", path.display())))
+    }
+
+    /// Bring the sidecar up to date with the live saves of `card`, as of now.
+    pub fn update(&mut self, card: &MemCard) -> Result<(), MCError> {
+        self.update_at(card, SystemTime::now())
+    }
+
+    /// Bring the sidecar up to date with the live saves of `card`, as of `time`. New saves are
+    /// created and modified at `time`, saves whose contents changed are modified at `time`, and
+    /// saves that are no longer on the card are dropped along with their notes. If several saves
+    /// have the same filename, the first one is used.
+    pub fn update_at(&mut self, card: &MemCard, time: SystemTime) -> Result<(), MCError> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut saves = BTreeMap::new();
+        for s in card.saves() {
+            let filename = s.filename();
+            if saves.contains_key(&filename) {
+                continue;
+            }
+            let fingerprint = fingerprint(&s)?;
+            let notes = match self.saves.remove(&filename) {
+                Some(n) if n.fingerprint == fingerprint => n,
+                Some(n) => SaveNotes {
+                    modified: secs,
+                    fingerprint,
+                    ..n
+                },
+                None => SaveNotes {
+                    created: secs,
+                    modified: secs,
+                    notes: String::new(),
+                    fingerprint,
+                },
+            };
+            saves.insert(filename, notes);
+        }
+        self.saves = saves;
+
+        Ok(())
+    }
+
+    /// The notes kept about the save named `filename`.
+    pub fn get(&self, filename: &str) -> Option<&SaveNotes> {
+        self.saves.get(filename)
+    }
+
+    /// Replace the free-text notes of the save named `filename`. Returns `MCError::NotFound` if
+    /// the sidecar has no such save; call `update` first to add new saves.
+    pub fn set_notes(&mut self, filename: &str, notes: &str) -> Result<(), MCError> {
+        let save = self
+            .saves
+            .get_mut(filename)
+            .ok_or_else(|| MCError::NotFound(format!("No save named {}", filename)))?;
+        save.notes = notes.to_string();
+
+        Ok(())
+    }
+}
+
+impl MemCard {
+    /// Load the sidecar of the card file at `card`, bring it up to date with this card, and
+    /// write it back. Call this after writing the card to keep its sidecar current.
+    pub fn update_sidecar<P: AsRef<Path>>(&self, card: P) -> Result<Sidecar, MCError> {
+        let mut sidecar = Sidecar::load(&card)?;
+        sidecar.update(self)?;
+        sidecar.store(&card)?;
+
+        Ok(sidecar)
+    }
+}
+
+/// The 64-bit FNV-1a hash of the data blocks of `save`, in hex. Unlike the hashers of the
+/// standard library, it is the same on every platform and Rust version.
+fn fingerprint(save: &Save) -> Result<String, MCError> {
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut block = [0u8; BLOCK_SIZE];
+    for d in &save.data {
+        d.write_raw(&mut block)?;
+        for b in block {
+            hash = (hash ^ u64::from(b)).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    Ok(format!("{:016x}", hash))
+}
@@ -0,0 +1,272 @@
+//! The serial protocol spoken by memory cards on the controller port of the console, for
+//! emulators and hardware projects that want to present a `MemCard` to a game.
+
+use crate::{CardGeometry, MCError, MemCard, OpenOptions, FRAME_SIZE};
+
+/// The address byte that selects a memory card, rather than a controller.
+pub const SIO_ADDRESS: u8 = 0x81;
+
+/// The commands.
+const READ: u8 = b'R';
+const WRITE: u8 = b'W';
+const GET_ID: u8 = b'S';
+
+/// The bytes sent back by the card.
+const ID1: u8 = 0x5a;
+const ID2: u8 = 0x5d;
+const ACK1: u8 = 0x5c;
+const ACK2: u8 = 0x5d;
+const GOOD: u8 = b'G';
+const BAD_CHECKSUM: u8 = b'N';
+const BAD_SECTOR: u8 = 0xff;
+
+/// The reply to the get ID command, after the two acknowledge bytes.
+const CARD_ID: [u8; 4] = [0x04, 0x00, 0x00, 0x80];
+
+/// The flag bits, sent in reply to the command byte.
+const FLAG_ERROR: u8 = 0x04;
+const FLAG_FRESH: u8 = 0x08;
+
+/// SioReply
+///
+/// The reply of the card to one byte of a transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SioReply {
+    /// The byte shifted out by the card.
+    pub data: u8,
+
+    /// Whether the card pulls /ACK, asking for the next byte. The last byte of a command is not
+    /// acknowledged.
+    pub ack: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Selected, waiting for the address byte.
+    Idle,
+    /// Not addressed, or done with a command, until deselected.
+    Ignore,
+    /// Waiting for the command byte.
+    Command,
+    /// In the middle of a command, at the given byte of the command.
+    Read(usize),
+    Write(usize),
+    GetId(usize),
+}
+
+/// SioCard
+///
+/// A memory card on the serial bus of the controller port. Bytes are exchanged one at a time
+/// with `transfer`, while the card is selected with `select`. Supports the read, write and get
+/// ID commands, addressing the card by 128 byte sector, which is a `Frame`.
+///
+/// The card works on a raw image, which is parsed back into a `MemCard` by `to_card`. Writes
+/// from the game are stored when their checksum matches.
+#[derive(Clone, Debug)]
+pub struct SioCard {
+    image: Vec<u8>,
+    state: State,
+    flag: u8,
+    sector: [u8; 2],
+    buf: [u8; FRAME_SIZE],
+    checksum: u8,
+    last: u8,
+    status: u8,
+    dirty: bool,
+}
+
+impl SioCard {
+    /// Put `card` on the bus.
+    pub fn new(card: &MemCard) -> Result<Self, MCError> {
+        let mut image = Vec::with_capacity(CardGeometry::STANDARD.size());
+        card.write_into(&mut image)?;
+
+        Ok(SioCard {
+            image,
+            state: State::Idle,
+            flag: FLAG_FRESH,
+            sector: [0; 2],
+            buf: [0; FRAME_SIZE],
+            checksum: 0,
+            last: 0,
+            status: GOOD,
+            dirty: false,
+        })
+    }
+
+    /// Select the card, pulling /CS low, which starts a new transfer.
+    pub fn select(&mut self) {
+        self.state = State::Idle;
+    }
+
+    /// Deselect the card, releasing /CS, which aborts any unfinished command.
+    pub fn deselect(&mut self) {
+        self.state = State::Ignore;
+    }
+
+    /// Exchange one byte with the card.
+    pub fn transfer(&mut self, byte: u8) -> SioReply {
+        let (data, ack) = match self.state {
+            State::Idle => self.address(byte),
+            State::Ignore => (0xff, false),
+            State::Command => self.command(byte),
+            State::Read(n) => self.read(n, byte),
+            State::Write(n) => self.write(n, byte),
+            State::GetId(n) => self.get_id(n),
+        };
+        self.last = byte;
+
+        if !ack {
+            self.state = State::Ignore;
+        }
+
+        SioReply { data, ack }
+    }
+
+    /// Whether the game wrote to the card since it was created.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// The raw image of the card.
+    pub fn image(&self) -> &[u8] {
+        &self.image
+    }
+
+    /// Parse the image back into a `MemCard`, with the changes written by the game.
+    pub fn to_card(&self) -> Result<MemCard, MCError> {
+        let opts = OpenOptions {
+            skip_checksums: true,
+            ..Default::default()
+        };
+        MemCard::from_reader_with(self.image.as_slice(), opts)
+    }
+
+    fn address(&mut self, byte: u8) -> (u8, bool) {
+        match byte {
+            SIO_ADDRESS => {
+                self.state = State::Command;
+                (0xff, true)
+            }
+            _ => (0xff, false),
+        }
+    }
+
+    fn command(&mut self, byte: u8) -> (u8, bool) {
+        self.state = match byte {
+            READ => State::Read(0),
+            WRITE => State::Write(0),
+            GET_ID => State::GetId(0),
+            _ => return (0xff, false),
+        };
+
+        (self.flag, true)
+    }
+
+    /// The sector that was sent, if it is on the card.
+    fn frame(&self) -> Option<usize> {
+        let n = u16::from_be_bytes(self.sector) as usize;
+        (n < self.image.len() / FRAME_SIZE).then_some(n * FRAME_SIZE)
+    }
+
+    fn read(&mut self, n: usize, byte: u8) -> (u8, bool) {
+        self.state = State::Read(n + 1);
+        match n {
+            0 => (ID1, true),
+            1 => (ID2, true),
+            2 => {
+                self.sector[0] = byte;
+                (0x00, true)
+            }
+            3 => {
+                self.sector[1] = byte;
+                (self.last, true)
+            }
+            4 => (ACK1, true),
+            5 => (ACK2, true),
+            // The sector is confirmed, or FFh FFh ends the command if it is invalid
+            6 => match self.frame() {
+                Some(f) => {
+                    self.buf.copy_from_slice(&self.image[f..f + FRAME_SIZE]);
+                    self.checksum = self.sector[0] ^ self.sector[1];
+                    (self.sector[0], true)
+                }
+                None => (0xff, true),
+            },
+            7 => match self.frame() {
+                Some(_) => (self.sector[1], true),
+                None => (0xff, false),
+            },
+            8..136 => {
+                let d = self.buf[n - 8];
+                self.checksum ^= d;
+                (d, true)
+            }
+            136 => (self.checksum, true),
+            _ => (GOOD, false),
+        }
+    }
+
+    fn write(&mut self, n: usize, byte: u8) -> (u8, bool) {
+        self.state = State::Write(n + 1);
+        // Until the acknowledge bytes, the card echoes the previous byte
+        let echo = self.last;
+        match n {
+            0 => (ID1, true),
+            1 => (ID2, true),
+            2 => {
+                self.sector[0] = byte;
+                (0x00, true)
+            }
+            3 => {
+                self.sector[1] = byte;
+                self.checksum = self.sector[0] ^ self.sector[1];
+                (echo, true)
+            }
+            4..132 => {
+                self.buf[n - 4] = byte;
+                self.checksum ^= byte;
+                (echo, true)
+            }
+            132 => {
+                self.status = self.store(byte);
+                (echo, true)
+            }
+            133 => (ACK1, true),
+            134 => (ACK2, true),
+            _ => (self.status, false),
+        }
+    }
+
+    /// Store the received sector if the checksum `expected` matches, returning the end status.
+    fn store(&mut self, expected: u8) -> u8 {
+        let status = match self.frame() {
+            None => BAD_SECTOR,
+            Some(_) if expected != self.checksum => BAD_CHECKSUM,
+            Some(f) => {
+                self.image[f..f + FRAME_SIZE].copy_from_slice(&self.buf);
+                self.dirty = true;
+                GOOD
+            }
+        };
+
+        self.flag = match status {
+            GOOD => 0,
+            _ => self.flag | FLAG_ERROR,
+        };
+
+        status
+    }
+
+    fn get_id(&mut self, n: usize) -> (u8, bool) {
+        self.state = State::GetId(n + 1);
+        match n {
+            0 => (ID1, true),
+            1 => (ID2, true),
+            2 => (ACK1, true),
+            3 => (ACK2, true),
+            4..7 => (CARD_ID[n - 4], true),
+            _ => (CARD_ID[3], false),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! The pair of memory cards in the two slots of an emulator, loaded and written together.
+
+use std::io;
+use std::ops::{Index, IndexMut};
+use std::path::{Path, PathBuf};
+
+use crate::{atomic, backup, MCError, MemCard, OpenOptions, WriteOptions};
+
+/// CardSlots
+///
+/// The memory cards of slot 1 and slot 2, such as ePSXe's "epsxe000.mcr" and "epsxe001.mcr".
+/// The cards are indexed by slot, 0 or 1. Most work, such as copying a save from one card to
+/// the other, touches both cards, so they are written back together with `write`, which only
+/// writes the cards that changed.
+#[derive(Clone, Debug)]
+pub struct CardSlots {
+    cards: [MemCard; 2],
+    paths: [PathBuf; 2],
+
+    /// The cards as they are on disk, or `None` if there is no file yet.
+    written: [Option<MemCard>; 2],
+}
+
+impl CardSlots {
+    /// Open the cards at `slot1` and `slot2`. A card file that does not exist yet is opened as a
+    /// freshly formatted card, and created by the next `write`, as emulators do.
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(slot1: P, slot2: Q) -> Result<Self, MCError> {
+        Self::open_with(slot1, slot2, OpenOptions::default())
+    }
+
+    /// Open the cards at `slot1` and `slot2` using the provided `OpenOptions`.
+    pub fn open_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        slot1: P,
+        slot2: Q,
+        opts: OpenOptions,
+    ) -> Result<Self, MCError> {
+        let paths = [slot1.as_ref().to_path_buf(), slot2.as_ref().to_path_buf()];
+        let open = |path: &Path| match MemCard::open_with(path, opts) {
+            Ok(card) => Ok((card.clone(), Some(card))),
+            Err(e) if is_not_found(&e) => {
+                let mut card = MemCard::new();
+                card.set_read_only(opts.read_only);
+                Ok((card, None))
+            }
+            Err(e) => Err(e),
+        };
+        let (card1, written1) = open(&paths[0])?;
+        let (card2, written2) = open(&paths[1])?;
+
+        Ok(CardSlots {
+            cards: [card1, card2],
+            paths,
+            written: [written1, written2],
+        })
+    }
+
+    /// Open the shared cards of ePSXe in its memory card directory `dir`, "epsxe000.mcr" and
+    /// "epsxe001.mcr".
+    pub fn epsxe<P: AsRef<Path>>(dir: P) -> Result<Self, MCError> {
+        let dir = dir.as_ref();
+        Self::open(dir.join("epsxe000.mcr"), dir.join("epsxe001.mcr"))
+    }
+
+    /// Open the per-game cards ePSXe keeps for the game with product code `serial`, such as
+    /// "SLUS-00594", in the "games" directory below its memory card directory `dir`.
+    pub fn epsxe_game<P: AsRef<Path>>(dir: P, serial: &str) -> Result<Self, MCError> {
+        let games = dir.as_ref().join("games");
+        Self::open(
+            games.join(format!("{}-00.mcr", serial)),
+            games.join(format!("{}-01.mcr", serial)),
+        )
+    }
+
+    /// The path of the card in `slot`, 0 or 1.
+    pub fn path(&self, slot: usize) -> &Path {
+        &self.paths[slot]
+    }
+
+    /// The slots whose cards changed since they were opened or last written.
+    pub fn changed(&self) -> Vec<usize> {
+        (0..2)
+            .filter(|slot| self.written[*slot].as_ref() != Some(&self.cards[*slot]))
+            .collect()
+    }
+
+    /// Copy the save that starts at directory index `index` on the card in slot `from` to the
+    /// free blocks of the card in the other slot. Returns the directory index of the copy.
+    pub fn copy_save(&mut self, from: usize, index: usize) -> Result<usize, MCError> {
+        let mcs = self.cards[from]
+            .saves()
+            .find(|s| s.index == index)
+            .ok_or_else(|| MCError::NotFound(format!("no save starts at block {}", index)))?
+            .to_mcs()?;
+
+        self.cards[1 - from].import_mcs(&mcs)
+    }
+
+    /// Move the save that starts at directory index `index` on the card in slot `from` to the
+    /// other slot. The save is only deleted once the copy succeeded. Returns the directory index
+    /// of the moved save.
+    pub fn move_save(&mut self, from: usize, index: usize) -> Result<usize, MCError> {
+        self.cards[from].check_writable()?;
+        let to = self.copy_save(from, index)?;
+        self.cards[from].delete_save(index)?;
+
+        Ok(to)
+    }
+
+    /// Write back the cards that changed, returning their slots. Both cards are serialized
+    /// before either file is touched, so a card that can not be written leaves both files as
+    /// they were.
+    pub fn write(&mut self, opts: WriteOptions) -> Result<Vec<usize>, MCError> {
+        let changed = self.changed();
+        let mut images = Vec::new();
+        for slot in &changed {
+            let card = &self.cards[*slot];
+            card.check_writable()?;
+            let mut buf = Vec::new();
+            card.write_into(&mut buf)?;
+            images.push((*slot, buf));
+        }
+
+        for (slot, buf) in images {
+            let path = &self.paths[slot];
+            let write = || -> io::Result<()> {
+                backup::rotate(path, opts.backups)?;
+                atomic::write_atomic(path, &buf, opts.sync)
+            };
+            write().map_err(|e| {
+                MCError::from(e).context(format!("Unable to write {}", path.display()))
+            })?;
+            self.written[slot] = Some(self.cards[slot].clone());
+        }
+
+        Ok(changed)
+    }
+}
+
+impl Index<usize> for CardSlots {
+    type Output = MemCard;
+
+    /// Access the card in `slot`, 0 or 1.
+    fn index(&self, slot: usize) -> &MemCard {
+        &self.cards[slot]
+    }
+}
+
+impl IndexMut<usize> for CardSlots {
+    fn index_mut(&mut self, slot: usize) -> &mut MemCard {
+        &mut self.cards[slot]
+    }
+}
+
+/// Check if opening a card failed because the file does not exist.
+fn is_not_found(e: &MCError) -> bool {
+    match e {
+        MCError::Io(e) => e.kind() == io::ErrorKind::NotFound,
+        MCError::Context { source, .. } => is_not_found(source),
+        _ => false,
+    }
+}
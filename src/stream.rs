@@ -0,0 +1,108 @@
+//! Incremental parsing of memory cards from streams.
+
+use std::io::Read;
+
+use deku::prelude::*;
+
+use crate::{DataBlock, InfoBlock, MCError, MemCard, OpenOptions, BLOCKS_PER_CARD, BLOCK_SIZE};
+
+/// CardStream
+///
+/// Parses a memory card block by block as it arrives from a stream that can not seek, such as a
+/// serial link or a socket. The `InfoBlock` is available as soon as the first block has been
+/// read, and the data blocks are yielded by the iterator as they come in.
+#[derive(Debug)]
+pub struct CardStream<R: Read> {
+    input: R,
+    opts: OpenOptions,
+    info: Option<InfoBlock>,
+    next: usize,
+    failed: bool,
+}
+
+impl<R: Read> CardStream<R> {
+    /// Create a stream parser for `input`. Nothing is read until the `InfoBlock` or the first
+    /// data block is requested.
+    pub fn new(input: R) -> Self {
+        Self::with_options(input, OpenOptions::default())
+    }
+
+    /// Create a stream parser for `input` using the provided `OpenOptions`.
+    pub fn with_options(input: R, opts: OpenOptions) -> Self {
+        CardStream {
+            input,
+            opts,
+            info: None,
+            next: 0,
+            failed: false,
+        }
+    }
+
+    /// Get the `InfoBlock`, reading and validating the first block of the card if it has not
+    /// arrived yet.
+    pub fn info(&mut self) -> Result<&InfoBlock, MCError> {
+        if self.info.is_none() {
+            let mut data = vec![0u8; BLOCK_SIZE];
+            self.input.read_exact(&mut data)?;
+
+            InfoBlock::validate_block(&data, self.opts)?;
+            let (_, info) = InfoBlock::from_bytes((&data, 0))?;
+            self.info = Some(info);
+        }
+
+        Ok(self.info.as_ref().expect("info block was just read"))
+    }
+
+    /// Read the next data block, or `None` once all 15 have been read.
+    fn next_block(&mut self) -> Result<Option<DataBlock>, MCError> {
+        self.info()?;
+        if self.next >= BLOCKS_PER_CARD - 1 {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; BLOCK_SIZE];
+        self.input.read_exact(&mut data)?;
+        self.next += 1;
+
+        DataBlock::parse_block(self.next, &data).map(Some)
+    }
+
+    /// Read the rest of the stream into a `MemCard`.
+    pub fn into_memcard(mut self) -> Result<MemCard, MCError> {
+        self.info()?;
+        let mut data = Vec::<DataBlock>::with_capacity(BLOCKS_PER_CARD - 1);
+        for d in &mut self {
+            data.push(d?);
+        }
+        if data.len() != BLOCKS_PER_CARD - 1 {
+            return Err(MCError::InvalidValue(
+                "data blocks were already taken from the stream".to_string(),
+            ));
+        }
+
+        let mut card = MemCard::from_parts(self.info.expect("info block was read"), data);
+        card.set_read_only(self.opts.read_only);
+
+        Ok(card)
+    }
+}
+
+impl<R: Read> Iterator for CardStream<R> {
+    type Item = Result<DataBlock, MCError>;
+
+    /// Read and parse the next data block. Iteration stops after the 15th block, or after the
+    /// first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.next_block() {
+            Ok(d) => d.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Statistics about the contents of a memory card.
+
+use crate::{BAState, License, MemCard, Region};
+
+/// CardSummary
+///
+/// The usage statistics of a memory card, as shown in the header of most card managers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CardSummary {
+    /// The number of blocks allocated to saves.
+    pub used_blocks: usize,
+
+    /// The number of blocks that are free, including the blocks of deleted saves.
+    pub free_blocks: usize,
+
+    /// The number of live saves.
+    pub saves: usize,
+
+    /// The number of live saves per region, in the order Japan, America, Europe, UNKNOWN.
+    /// Regions without any saves are included with a count of 0.
+    pub regions: Vec<(Region, usize)>,
+
+    /// The number of frames recorded in the broken frame list.
+    pub broken_frames: usize,
+
+    /// The total size of the live saves in bytes, as recorded in the directory.
+    pub payload_bytes: u64,
+}
+
+/// RegionStats
+///
+/// The number of live saves per region and license, as returned by `MemCard::region_stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionStats {
+    /// The number of live saves for every pair of region and license, in the order of
+    /// `CardSummary::regions` and then Sony, Licensed, UNKNOWN. Pairs without any saves are
+    /// included with a count of 0.
+    pub counts: Vec<(Region, License, usize)>,
+
+    /// The directory indices of the saves whose region does not match the product code, such
+    /// as a "BE" filename with a "SLUS" product code. These were often imported with a
+    /// hand-edited filename.
+    pub mismatched: Vec<usize>,
+}
+
+impl RegionStats {
+    /// The number of live saves with both `region` and `license`.
+    pub fn count(&self, region: Region, license: License) -> usize {
+        self.counts
+            .iter()
+            .find(|(r, l, _)| *r == region && *l == license)
+            .map_or(0, |(_, _, n)| *n)
+    }
+
+    /// The number of live saves of `region`, with any license.
+    pub fn region(&self, region: Region) -> usize {
+        self.counts
+            .iter()
+            .filter(|(r, _, _)| *r == region)
+            .map(|(_, _, n)| n)
+            .sum()
+    }
+
+    /// The number of live saves with `license`, in any region.
+    pub fn license(&self, license: License) -> usize {
+        self.counts
+            .iter()
+            .filter(|(_, l, _)| *l == license)
+            .map(|(_, _, n)| n)
+            .sum()
+    }
+}
+
+/// Gather the `CardSummary` of `card`.
+pub(crate) fn summarize(card: &MemCard) -> CardSummary {
+    let used_blocks = card
+        .info
+        .dir_frames
+        .iter()
+        .filter(|d| {
+            matches!(
+                d.get_alloc_state(),
+                BAState::AllocFirst | BAState::AllocMid | BAState::AllocLast
+            )
+        })
+        .count();
+
+    let mut regions = vec![
+        (Region::Japan, 0),
+        (Region::America, 0),
+        (Region::Europe, 0),
+        (Region::UNKNOWN, 0),
+    ];
+    let mut saves = 0;
+    let mut payload_bytes = 0;
+    for s in card.saves() {
+        let region = s.region_info().map(|r| r.region).unwrap_or(Region::UNKNOWN);
+        if let Some(r) = regions.iter_mut().find(|(r, _)| *r == region) {
+            r.1 += 1;
+        }
+        saves += 1;
+        payload_bytes += s.size() as u64;
+    }
+
+    let broken_frames = card
+        .info
+        .broken_frames
+        .iter()
+        .filter(|b| b.broken_frame != 0xffffffff)
+        .count();
+
+    CardSummary {
+        used_blocks,
+        free_blocks: card.info.dir_frames.len() - used_blocks,
+        saves,
+        regions,
+        broken_frames,
+        payload_bytes,
+    }
+}
+
+/// Gather the `RegionStats` of `card`.
+pub(crate) fn region_stats(card: &MemCard) -> RegionStats {
+    let mut counts = Vec::new();
+    for region in [
+        Region::Japan,
+        Region::America,
+        Region::Europe,
+        Region::UNKNOWN,
+    ] {
+        for license in [License::Sony, License::Licensed, License::UNKNOWN] {
+            counts.push((region, license, 0));
+        }
+    }
+
+    let mut mismatched = Vec::new();
+    for s in card.saves() {
+        let (region, license) = match s.region_info() {
+            Ok(info) => (info.region, info.license),
+            Err(_) => (Region::UNKNOWN, License::UNKNOWN),
+        };
+        if let Some(c) = counts
+            .iter_mut()
+            .find(|(r, l, _)| *r == region && *l == license)
+        {
+            c.2 += 1;
+        }
+
+        if serial_region(&s.serial()).is_some_and(|r| r != region) {
+            mismatched.push(s.index);
+        }
+    }
+
+    RegionStats { counts, mismatched }
+}
+
+/// The region implied by the product code `serial`, such as America for "SLUS-00123", or
+/// `None` if the prefix is not one of the common ones.
+fn serial_region(serial: &str) -> Option<Region> {
+    match serial.get(2..4)? {
+        "PS" | "PM" => Some(Region::Japan),
+        "US" => Some(Region::America),
+        "ES" | "ED" => Some(Region::Europe),
+        _ => None,
+    }
+}
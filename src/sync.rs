@@ -0,0 +1,175 @@
+//! Merging two copies of a memory card, such as a local card and its copy in cloud storage, for
+//! save sync tools.
+
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::{MCError, MemCard, Save, BLOCK_SIZE};
+
+/// SyncSide
+///
+/// Which of the two cards being merged a save came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
+
+/// SyncAction
+///
+/// What happened to a save when two cards were merged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncAction {
+    /// The save is the same on both cards.
+    Unchanged,
+
+    /// The save is only on one of the cards, and was kept.
+    Only(SyncSide),
+
+    /// The save differs between the cards, and the copy from the newer card was kept. This is a
+    /// conflict, since the other copy is lost.
+    Newer(SyncSide),
+
+    /// The save is only on one of the cards, but did not fit on the merged card. This is a
+    /// conflict.
+    Dropped(SyncSide),
+}
+
+/// SyncEntry
+///
+/// The outcome of merging one save, identified by its directory filename.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncEntry {
+    pub filename: String,
+    pub action: SyncAction,
+}
+
+impl SyncEntry {
+    /// Whether a copy of the save was lost in the merge.
+    pub fn is_conflict(&self) -> bool {
+        matches!(self.action, SyncAction::Newer(_) | SyncAction::Dropped(_))
+    }
+}
+
+/// SyncReport
+///
+/// The outcome of merging two cards, with one entry per save, sorted by filename.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub entries: Vec<SyncEntry>,
+}
+
+impl SyncReport {
+    /// The saves of which a copy was lost in the merge.
+    pub fn conflicts(&self) -> impl Iterator<Item = &SyncEntry> {
+        self.entries.iter().filter(|e| e.is_conflict())
+    }
+
+    /// Whether the merge did not lose any copy of a save.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts().next().is_none()
+    }
+}
+
+/// Merge the cards `local` and `remote`, which were last modified at `local_modified` and
+/// `remote_modified`. Saves are matched by their directory filename and compared by a hash of
+/// their contents.
+///
+/// The merged card starts out as a copy of the newer card, with local winning ties, so saves
+/// that differ are resolved in favor of the newer card. Saves that are only on the older card
+/// are then copied over, as far as they fit.
+pub fn merge(
+    local: &MemCard,
+    local_modified: SystemTime,
+    remote: &MemCard,
+    remote_modified: SystemTime,
+) -> Result<(MemCard, SyncReport), MCError> {
+    let (newer, older, newer_side, older_side) = match remote_modified > local_modified {
+        true => (remote, local, SyncSide::Remote, SyncSide::Local),
+        false => (local, remote, SyncSide::Local, SyncSide::Remote),
+    };
+
+    let mut merged = newer.clone();
+    let newer_saves = hashed_saves(newer)?;
+    let older_saves = hashed_saves(older)?;
+
+    let mut report = SyncReport::default();
+    for (filename, (_, hash)) in &newer_saves {
+        let action = match older_saves.get(filename) {
+            Some((_, h)) if h == hash => SyncAction::Unchanged,
+            Some(_) => SyncAction::Newer(newer_side),
+            None => SyncAction::Only(newer_side),
+        };
+        report.entries.push(SyncEntry {
+            filename: filename.clone(),
+            action,
+        });
+    }
+
+    for (filename, (mcs, _)) in &older_saves {
+        if newer_saves.contains_key(filename) {
+            continue;
+        }
+
+        let action = match merged.import_mcs(mcs) {
+            Ok(_) => SyncAction::Only(older_side),
+            Err(MCError::CardFull) => SyncAction::Dropped(older_side),
+            Err(e) => return Err(e.context(format!("Unable to copy {}", filename))),
+        };
+        report.entries.push(SyncEntry {
+            filename: filename.clone(),
+            action,
+        });
+    }
+    report.entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Ok((merged, report))
+}
+
+/// Merge the card files at `local` and `remote`, using their modification times. See `merge`.
+/// The merged card is returned rather than written, so the caller can review the report first.
+pub fn merge_files<P: AsRef<Path>, Q: AsRef<Path>>(
+    local: P,
+    remote: Q,
+) -> Result<(MemCard, SyncReport), MCError> {
+    let local_modified = fs::metadata(&local)?.modified()?;
+    let remote_modified = fs::metadata(&remote)?.modified()?;
+
+    merge(
+        &MemCard::open(local)?,
+        local_modified,
+        &MemCard::open(remote)?,
+        remote_modified,
+    )
+}
+
+/// The live saves of `card` as `.mcs` files, with a hash of their contents, by filename. If
+/// several saves have the same filename, the first one is used.
+fn hashed_saves(card: &MemCard) -> Result<BTreeMap<String, (Vec<u8>, u64)>, MCError> {
+    let mut saves = BTreeMap::new();
+    for s in card.saves() {
+        if let Entry::Vacant(e) = saves.entry(s.filename()) {
+            e.insert(hashed(&s)?);
+        }
+    }
+
+    Ok(saves)
+}
+
+/// Hash the size and the data blocks of `save`, but not the links of its directory frames,
+/// which depend on where the save is stored on the card.
+fn hashed(save: &Save) -> Result<(Vec<u8>, u64), MCError> {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u32(save.size());
+    let mut block = [0u8; BLOCK_SIZE];
+    for d in &save.data {
+        d.write_raw(&mut block)?;
+        hasher.write(&block);
+    }
+
+    Ok((save.to_mcs()?, hasher.finish()))
+}
@@ -0,0 +1,185 @@
+//! A memory card that tracks which blocks have been modified.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use deku::prelude::*;
+
+use crate::atomic::write_atomic;
+use crate::backup;
+use crate::{DataBlock, InfoBlock, MCError, MemCard, WriteOptions, BLOCKS_PER_CARD, BLOCK_SIZE};
+
+/// TrackedCard
+///
+/// Wraps a `MemCard` together with its raw image, and tracks which blocks have been borrowed
+/// mutably since they were last serialized. Writing only re-serializes and re-checksums the
+/// dirty blocks, the rest of the image is written out as it was loaded.
+///
+/// Read access goes through `Deref` to the `MemCard`, changes through `info_mut`, `data_mut`
+/// or `card_mut`.
+#[derive(Clone, Debug)]
+pub struct TrackedCard {
+    card: MemCard,
+    image: Vec<u8>,
+    dirty: [bool; BLOCKS_PER_CARD],
+}
+
+impl TrackedCard {
+    /// Open and parse the memory card file from a filename.
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        let path = filename.as_ref();
+        let file = File::open(path)?;
+        Self::from_reader(file).map_err(|e| e.context(format!("Unable to open {}", path.display())))
+    }
+
+    /// Parse a memory card from anything that implements `Read`.
+    pub fn from_reader<R: Read>(mut input: R) -> Result<Self, MCError> {
+        let mut image = vec![0u8; BLOCK_SIZE * BLOCKS_PER_CARD];
+        input.read_exact(&mut image)?;
+        let card = MemCard::from_reader(&image[..])?;
+
+        Ok(TrackedCard {
+            card,
+            image,
+            dirty: [false; BLOCKS_PER_CARD],
+        })
+    }
+
+    /// Start tracking an existing `MemCard`. It is serialized once, and starts out clean. A
+    /// read-only card stays read-only, so it can not be written to a file.
+    pub fn from_memcard(card: MemCard) -> Result<Self, MCError> {
+        let mut image = Vec::<u8>::with_capacity(BLOCK_SIZE * BLOCKS_PER_CARD);
+        card.write_into(&mut image)?;
+
+        Ok(TrackedCard {
+            card,
+            image,
+            dirty: [false; BLOCKS_PER_CARD],
+        })
+    }
+
+    /// Get mutable access to the `InfoBlock`, marking block 0 as dirty.
+    pub fn info_mut(&mut self) -> &mut InfoBlock {
+        self.dirty[0] = true;
+        &mut self.card.info
+    }
+
+    /// Get mutable access to the data block at directory index `n` (0..15), marking card block
+    /// `n + 1` as dirty.
+    pub fn data_mut(&mut self, n: usize) -> Option<&mut DataBlock> {
+        let d = self.card.data.get_mut(n)?;
+        self.dirty[n + 1] = true;
+        Some(d)
+    }
+
+    /// Get mutable access to the whole `MemCard`, marking every block as dirty.
+    pub fn card_mut(&mut self) -> &mut MemCard {
+        self.dirty = [true; BLOCKS_PER_CARD];
+        &mut self.card
+    }
+
+    /// The card blocks (0..16) that have been modified since they were last serialized.
+    pub fn dirty_blocks(&self) -> Vec<usize> {
+        (0..BLOCKS_PER_CARD).filter(|n| self.dirty[*n]).collect()
+    }
+
+    /// Re-serialize the dirty blocks into the image, and mark every block as clean. Returns
+    /// the card blocks that were serialized.
+    pub fn flush(&mut self) -> Result<Vec<usize>, MCError> {
+        let dirty = self.dirty_blocks();
+        for n in &dirty {
+            let b = match n {
+                0 => {
+                    let mut b = Vec::<u8>::with_capacity(BLOCK_SIZE);
+                    self.card.info.write(&mut b)?;
+                    b
+                }
+                _ => self.card.data[n - 1].to_bytes()?,
+            };
+            self.image[n * BLOCK_SIZE..(n + 1) * BLOCK_SIZE].copy_from_slice(&b);
+        }
+        self.dirty = [false; BLOCKS_PER_CARD];
+
+        Ok(dirty)
+    }
+
+    /// Flush the dirty blocks and get the raw image.
+    pub fn image(&mut self) -> Result<&[u8], MCError> {
+        self.flush()?;
+
+        Ok(&self.image)
+    }
+
+    /// Flush the dirty blocks and write out the image to `out`.
+    pub fn write_to<T: Write>(&mut self, out: &mut T) -> Result<(), MCError> {
+        out.write_all(self.image()?)?;
+
+        Ok(())
+    }
+
+    /// Flush the dirty blocks and write out the image to a file, replacing it atomically. See
+    /// `MemCard::write`. Returns `MCError::ReadOnly` if the card is read-only.
+    pub fn write<P: AsRef<Path>>(&mut self, filename: P) -> Result<(), MCError> {
+        self.write_with(filename, WriteOptions::default())
+    }
+
+    /// Flush the dirty blocks and write out the image to a file using the provided
+    /// `WriteOptions`, the same way `MemCard::write_with` does.
+    pub fn write_with<P: AsRef<Path>>(
+        &mut self,
+        filename: P,
+        opts: WriteOptions,
+    ) -> Result<(), MCError> {
+        self.card.check_writable()?;
+        let path = filename.as_ref();
+        let mut write = || -> Result<(), MCError> {
+            let image = self.image()?;
+            backup::rotate(path, opts.backups)?;
+            write_atomic(path, image, opts.sync)?;
+            Ok(())
+        };
+        write().map_err(|e| e.context(format!("Unable to write {}", path.display())))
+    }
+
+    /// Write only the dirty blocks to `out`, which must already hold the image this card was
+    /// loaded from or last written to. Each block is written at its own offset, so untouched
+    /// blocks are never rewritten. Returns the card blocks that were written, or
+    /// `MCError::ReadOnly` if the card is read-only.
+    pub fn write_changes<T: Write + Seek>(&mut self, out: &mut T) -> Result<Vec<usize>, MCError> {
+        self.card.check_writable()?;
+        let dirty = self.flush()?;
+        for n in &dirty {
+            out.seek(SeekFrom::Start((n * BLOCK_SIZE) as u64))?;
+            out.write_all(&self.image[n * BLOCK_SIZE..(n + 1) * BLOCK_SIZE])?;
+        }
+        out.flush()?;
+
+        Ok(dirty)
+    }
+
+    /// Write only the dirty blocks back to the existing file `filename`. See `write_changes`.
+    pub fn update<P: AsRef<Path>>(&mut self, filename: P) -> Result<Vec<usize>, MCError> {
+        self.card.check_writable()?;
+        let path = filename.as_ref();
+        let mut update = || -> Result<Vec<usize>, MCError> {
+            let mut file = fs::OpenOptions::new().write(true).open(path)?;
+            self.write_changes(&mut file)
+        };
+        update().map_err(|e| e.context(format!("Unable to update {}", path.display())))
+    }
+
+    /// Stop tracking and get the `MemCard`.
+    pub fn into_inner(self) -> MemCard {
+        self.card
+    }
+}
+
+impl Deref for TrackedCard {
+    type Target = MemCard;
+
+    fn deref(&self) -> &MemCard {
+        &self.card
+    }
+}
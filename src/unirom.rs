@@ -0,0 +1,210 @@
+//! Dumping and restoring physical memory cards over a serial cable, by talking to a console
+//! running Unirom with the protocol of its `nops` companion tool.
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::{CardGeometry, MCError, MemCard, OpenOptions};
+
+/// The baud rate Unirom listens at.
+pub const BAUD_RATE: u32 = 115_200;
+
+/// The size of the chunks that are checksummed by the V2 protocol.
+const CHUNK_SIZE: usize = 2048;
+
+/// How long to wait for the console, which includes the time to read or write a whole card.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Unirom
+///
+/// A connection to a console running Unirom. Cards are dumped with `read_card` and restored with
+/// `write_card`, the slot being 0 for the first memory card port and 1 for the second.
+///
+/// Each command starts with a challenge: the command is sent, and Unirom answers "OKAY", or
+/// "OKV2" if it supports the V2 protocol, which is then selected with "UPV2". With V2, every
+/// 2048 byte chunk of a transfer is checked against the sum of its bytes, and sent again if it
+/// does not match.
+#[derive(Debug)]
+pub struct Unirom<T> {
+    port: T,
+    v2: bool,
+}
+
+impl Unirom<Box<dyn serialport::SerialPort>> {
+    /// Open the serial port at `path`, such as "/dev/ttyUSB0" or "COM3".
+    pub fn open(path: &str) -> Result<Self, MCError> {
+        let port = serialport::new(path, BAUD_RATE)
+            .timeout(TIMEOUT)
+            .open()
+            .map_err(io::Error::from)?;
+
+        Ok(Unirom::new(port))
+    }
+}
+
+impl<T: Read + Write> Unirom<T> {
+    /// Talk to Unirom over `port`, which must already be set up for `BAUD_RATE` 8N1.
+    pub fn new(port: T) -> Self {
+        Unirom { port, v2: false }
+    }
+
+    /// Dump the raw image of the card in `slot`. Returns `MCError::InvalidValue` if Unirom
+    /// reports any size other than that of a standard card, before anything is transferred.
+    pub fn read_raw(&mut self, slot: u32) -> Result<Vec<u8>, MCError> {
+        // Unirom copies the card to RAM, and reports where to dump it from
+        self.challenge(b"MCDN")?;
+        self.write_u32(slot)?;
+        self.wait_for(b"MCRD")?;
+        let addr = self.read_u32()?;
+        let size = self.read_u32()?;
+        if size as usize != CardGeometry::STANDARD.size() {
+            return Err(MCError::InvalidValue(format!(
+                "Unirom reported a card of {} bytes",
+                size
+            )));
+        }
+
+        self.challenge(b"DUMP")?;
+        self.write_u32(addr)?;
+        self.write_u32(size)?;
+
+        self.read_chunks(size as usize)
+    }
+
+    /// Dump the card in `slot`.
+    pub fn read_card(&mut self, slot: u32) -> Result<MemCard, MCError> {
+        self.read_card_with(slot, OpenOptions::default())
+    }
+
+    /// Dump the card in `slot`, parsing it with the provided `OpenOptions`. Use
+    /// `skip_checksums` to recover damaged cards.
+    pub fn read_card_with(&mut self, slot: u32, opts: OpenOptions) -> Result<MemCard, MCError> {
+        let data = self.read_raw(slot)?;
+
+        MemCard::from_reader_with(data.as_slice(), opts)
+    }
+
+    /// Write the raw image `data` to the card in `slot`.
+    pub fn write_raw(&mut self, slot: u32, data: &[u8]) -> Result<(), MCError> {
+        self.challenge(b"MCUP")?;
+        self.write_u32(slot)?;
+        self.write_u32(data.len() as u32)?;
+        self.write_u32(sum(data))?;
+
+        self.write_chunks(data)
+    }
+
+    /// Write `card` to the card in `slot`.
+    pub fn write_card(&mut self, slot: u32, card: &MemCard) -> Result<(), MCError> {
+        let mut data = Vec::with_capacity(CardGeometry::STANDARD.size());
+        card.write_to(&mut data)?;
+
+        self.write_raw(slot, &data)
+    }
+
+    /// Release the port.
+    pub fn into_inner(self) -> T {
+        self.port
+    }
+
+    /// Send `cmd` and wait for Unirom to accept it, switching to the V2 protocol if supported.
+    fn challenge(&mut self, cmd: &[u8; 4]) -> Result<(), MCError> {
+        self.port.write_all(cmd)?;
+        self.port.flush()?;
+
+        self.v2 = match self.wait_for_any(&[b"OKAY", b"OKV2"])? {
+            b"OKV2" => {
+                self.port.write_all(b"UPV2")?;
+                self.port.flush()?;
+                self.wait_for(b"OKAY")?;
+                true
+            }
+            _ => false,
+        };
+
+        Ok(())
+    }
+
+    fn read_chunks(&mut self, size: usize) -> Result<Vec<u8>, MCError> {
+        let mut data = vec![0u8; size];
+        for chunk in data.chunks_mut(CHUNK_SIZE) {
+            loop {
+                self.port.read_exact(chunk)?;
+                if !self.v2 {
+                    break;
+                }
+
+                self.port.write_all(b"CHEK")?;
+                self.port.flush()?;
+                if self.read_u32()? == sum(chunk) {
+                    self.port.write_all(b"MORE")?;
+                    self.port.flush()?;
+                    break;
+                }
+                self.port.write_all(b"ERR!")?;
+                self.port.flush()?;
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn write_chunks(&mut self, data: &[u8]) -> Result<(), MCError> {
+        for chunk in data.chunks(CHUNK_SIZE) {
+            loop {
+                self.port.write_all(chunk)?;
+                self.port.flush()?;
+                if !self.v2 {
+                    break;
+                }
+
+                self.wait_for(b"CHEK")?;
+                self.write_u32(sum(chunk))?;
+                if self.wait_for_any(&[b"MORE", b"ERR!"])? == b"MORE" {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn wait_for(&mut self, token: &[u8; 4]) -> Result<(), MCError> {
+        self.wait_for_any(&[token]).map(|_| ())
+    }
+
+    /// Read until one of `tokens` is received, skipping anything else, such as the debug
+    /// output of Unirom. Returns the token that was received.
+    fn wait_for_any<'t>(&mut self, tokens: &[&'t [u8; 4]]) -> Result<&'t [u8; 4], MCError> {
+        let mut last = [0u8; 4];
+        loop {
+            let mut b = [0u8; 1];
+            self.port.read_exact(&mut b)?;
+            last.rotate_left(1);
+            last[3] = b[0];
+
+            if let Some(t) = tokens.iter().find(|t| ***t == last) {
+                return Ok(t);
+            }
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MCError> {
+        let mut b = [0u8; 4];
+        self.port.read_exact(&mut b)?;
+
+        Ok(u32::from_le_bytes(b))
+    }
+
+    fn write_u32(&mut self, v: u32) -> Result<(), MCError> {
+        self.port.write_all(&v.to_le_bytes())?;
+        self.port.flush()?;
+
+        Ok(())
+    }
+}
+
+/// The checksum of the protocol, the sum of all bytes.
+fn sum(d: &[u8]) -> u32 {
+    d.iter().fold(0, |s, b| s.wrapping_add(*b as u32))
+}
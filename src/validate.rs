@@ -0,0 +1,448 @@
+//! Structural validation of the directory in the `InfoBlock`.
+
+use std::iter::FusedIterator;
+
+use crate::{BAState, DirectoryFrame};
+
+/// The `next_block` value that marks the end of an allocation chain.
+pub const CHAIN_END: u16 = 0xffff;
+
+/// Finding
+///
+/// A single problem discovered while validating the memory card structure. Block indices in the
+/// chain findings are directory indices (0..15), which map to data blocks 1..16 on the card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum Finding {
+    /// The chain that starts at `start` loops back onto `block`.
+    ChainCycle { start: usize, block: usize },
+
+    /// `block` in the chain that starts at `start` links to `next`, which is not a valid
+    /// directory index.
+    ChainOutOfRange {
+        start: usize,
+        block: usize,
+        next: u16,
+    },
+
+    /// `block` was reached from the chain that starts at `start`, but is not marked as a middle
+    /// or last block of the same kind (allocated or free).
+    ChainBadLink {
+        start: usize,
+        block: usize,
+        state: BAState,
+    },
+
+    /// The chain that starts at `start` ends at `block` incorrectly: either the end marker was
+    /// hit on a block that is not the last block, or the last block links somewhere else.
+    ChainBadEnd { start: usize, block: usize },
+
+    /// `block` is marked as part of an allocated chain, but no chain reaches it.
+    ChainOrphan { block: usize },
+
+    /// The frame at `frame` within card block `block` (0..16) has a bad checksum.
+    BadChecksum { block: usize, frame: usize },
+
+    /// `block` is the first block of an allocated save, but its title frame does not start with
+    /// the "SC" magic.
+    BadTitleMagic { block: usize },
+}
+
+impl Finding {
+    /// How serious the problem is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Finding::ChainOrphan { .. } => Severity::Warning,
+            Finding::BadChecksum {
+                block: 0,
+                frame: UNUSED_FRAMES..WRITE_TEST_FRAME,
+            } => Severity::Info,
+            Finding::BadChecksum { .. } => Severity::Warning,
+            Finding::ChainCycle { .. }
+            | Finding::ChainOutOfRange { .. }
+            | Finding::ChainBadLink { .. }
+            | Finding::ChainBadEnd { .. }
+            | Finding::BadTitleMagic { .. } => Severity::Error,
+        }
+    }
+
+    /// A stable, machine-readable code for the kind of problem, such as "chain.cycle". Bad
+    /// checksums are told apart by the structure the frame holds.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Finding::ChainCycle { .. } => "chain.cycle",
+            Finding::ChainOutOfRange { .. } => "chain.out_of_range",
+            Finding::ChainBadLink { .. } => "chain.bad_link",
+            Finding::ChainBadEnd { .. } => "chain.bad_end",
+            Finding::ChainOrphan { .. } => "chain.orphan",
+            Finding::BadChecksum { block: 0, frame } => match frame {
+                0 => "checksum.header",
+                1..16 => "checksum.directory",
+                16..UNUSED_FRAMES => "checksum.broken_frame",
+                UNUSED_FRAMES..WRITE_TEST_FRAME => "checksum.unused",
+                _ => "checksum.write_test",
+            },
+            Finding::BadChecksum { .. } => "checksum.data",
+            Finding::BadTitleMagic { .. } => "title.bad_magic",
+        }
+    }
+}
+
+/// The first unused frame of block 0, after the header, directory and broken frames.
+const UNUSED_FRAMES: usize = 36;
+
+/// The write test frame, the last frame of block 0, after the unused frames.
+const WRITE_TEST_FRAME: usize = 63;
+
+/// Severity
+///
+/// How serious a `Finding` is, for tools deciding what is safe to fix automatically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Severity {
+    /// Harmless, such as garbage in the unused frames of block 0, which real cards often have.
+    /// Safe to fix automatically.
+    Info,
+
+    /// An inconsistency that does not lose saves by itself, such as a bad directory checksum or
+    /// an orphaned block. Usually fixable, but the result should be checked.
+    Warning,
+
+    /// Damage to the structure of a save, such as a broken allocation chain, which needs the
+    /// user to decide how to recover.
+    Error,
+}
+
+/// ValidationReport
+///
+/// Every `Finding` of a full validation of a memory card, as returned by `MemCard::validate`.
+/// With the `serde` feature the report can be serialized, such as to JSON for CI pipelines
+/// verifying archived dumps. Findings are tagged with their snake_case name under `kind`, and
+/// the field names are kept stable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    /// The problems found, in the order checksums, chains, titles.
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// Whether no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The highest severity of the findings, or `None` if the report is clean.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity()).max()
+    }
+
+    /// The findings of at least severity `min`.
+    pub fn at_least(&self, min: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |f| f.severity() >= min)
+    }
+}
+
+/// ChainWalker
+///
+/// Walks the allocation chain that starts at a directory index, yielding the directory index of
+/// every block visited, in order. Each hop is checked: the following entries must be middle or
+/// last blocks, links must be in range, and the chain must end on its last block without
+/// looping. The walk stops at the first problem, which is then returned by `finding`. The block
+/// the problem was found on is still yielded, except for a loop, where the block is not
+/// visited twice.
+///
+/// If the chain starts on a `FreeFirst` entry, it is walked as a deleted save and the following
+/// entries are expected to be `FreeMid`/`FreeLast` instead.
+///
+/// The walk never visits more than `dir.len()` entries, so corrupted links can not cause it to
+/// loop forever.
+#[derive(Clone, Debug)]
+pub struct ChainWalker<'a> {
+    dir: &'a [DirectoryFrame],
+    start: usize,
+    next: Option<usize>,
+    visited: Vec<bool>,
+    mid: BAState,
+    last: BAState,
+    finding: Option<Finding>,
+}
+
+impl<'a> ChainWalker<'a> {
+    /// Walk the chain in `dir` that starts at directory index `start`. Returns `None` if `start`
+    /// is out of range.
+    pub fn new(dir: &'a [DirectoryFrame], start: usize) -> Option<Self> {
+        let (mid, last) = match dir.get(start)?.get_alloc_state() {
+            BAState::FreeFirst => (BAState::FreeMid, BAState::FreeLast),
+            _ => (BAState::AllocMid, BAState::AllocLast),
+        };
+
+        Some(ChainWalker {
+            dir,
+            start,
+            next: Some(start),
+            visited: vec![false; dir.len()],
+            mid,
+            last,
+            finding: None,
+        })
+    }
+
+    /// The problem that stopped the walk, if any.
+    pub fn finding(&self) -> Option<Finding> {
+        self.finding
+    }
+
+    /// Stop the walk on `finding`, after yielding `block`.
+    fn stop(&mut self, block: usize, finding: Finding) -> Option<usize> {
+        self.finding = Some(finding);
+        Some(block)
+    }
+}
+
+impl Iterator for ChainWalker<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let (start, current) = (self.start, self.next.take()?);
+        if self.visited[current] {
+            self.finding = Some(Finding::ChainCycle {
+                start,
+                block: current,
+            });
+            return None;
+        }
+        self.visited[current] = true;
+
+        let frame = &self.dir[current];
+        let state = frame.get_alloc_state();
+        if current != start && state != self.mid && state != self.last {
+            return self.stop(
+                current,
+                Finding::ChainBadLink {
+                    start,
+                    block: current,
+                    state,
+                },
+            );
+        }
+
+        if frame.next_block == CHAIN_END {
+            // A single-block save ends on its first block, otherwise the end must be the last block
+            if state != self.last && current != start {
+                return self.stop(
+                    current,
+                    Finding::ChainBadEnd {
+                        start,
+                        block: current,
+                    },
+                );
+            }
+            return Some(current);
+        }
+
+        if state == self.last {
+            return self.stop(
+                current,
+                Finding::ChainBadEnd {
+                    start,
+                    block: current,
+                },
+            );
+        }
+
+        let next = frame.next_block as usize;
+        if next >= self.dir.len() {
+            return self.stop(
+                current,
+                Finding::ChainOutOfRange {
+                    start,
+                    block: current,
+                    next: frame.next_block,
+                },
+            );
+        }
+        self.next = Some(next);
+
+        Some(current)
+    }
+}
+
+impl FusedIterator for ChainWalker<'_> {}
+
+/// Walk the allocation chain that starts at directory index `start` with a `ChainWalker`.
+/// Returns the directory indices visited (in order), along with the first `Finding` that
+/// stopped the walk, if any.
+pub(crate) fn walk_chain(dir: &[DirectoryFrame], start: usize) -> (Vec<usize>, Option<Finding>) {
+    let mut walker = ChainWalker::new(dir, start).expect("chains start in the directory");
+    let blocks = walker.by_ref().collect();
+
+    (blocks, walker.finding())
+}
+
+/// Validate every allocation chain in the directory. Each chain that starts with an
+/// `AllocFirst` entry is walked, and any allocated entry that is not reached by a chain is
+/// reported as an orphan.
+pub(crate) fn validate_chains(dir: &[DirectoryFrame]) -> Vec<Finding> {
+    let mut findings = Vec::<Finding>::new();
+    let mut reached = vec![false; dir.len()];
+
+    for (n, d) in dir.iter().enumerate() {
+        if d.get_alloc_state() != BAState::AllocFirst {
+            continue;
+        }
+
+        let (blocks, finding) = walk_chain(dir, n);
+        for b in blocks {
+            reached[b] = true;
+        }
+        if let Some(f) = finding {
+            findings.push(f);
+        }
+    }
+
+    for (n, d) in dir.iter().enumerate() {
+        let state = d.get_alloc_state();
+        if !reached[n] && (state == BAState::AllocMid || state == BAState::AllocLast) {
+            findings.push(Finding::ChainOrphan { block: n });
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_frame(state: u32, next_block: u16) -> DirectoryFrame {
+        DirectoryFrame {
+            state,
+            filesize: 0,
+            next_block,
+            filename: [0u8; 21],
+            pad: [0u8; 96],
+            checksum: 0,
+        }
+    }
+
+    #[test]
+    fn chain_findings() {
+        let mut dir = vec![dir_frame(0xa0, CHAIN_END); 15];
+
+        // 0 -> 1 -> 2 (valid), 3 -> 4 -> 3 (cycle), 5 -> 20 (out of range), 7 (orphan)
+        dir[0] = dir_frame(0x51, 1);
+        dir[1] = dir_frame(0x52, 2);
+        dir[2] = dir_frame(0x53, CHAIN_END);
+        dir[3] = dir_frame(0x51, 4);
+        dir[4] = dir_frame(0x52, 3);
+        dir[5] = dir_frame(0x51, 20);
+        dir[7] = dir_frame(0x53, CHAIN_END);
+
+        assert_eq!(walk_chain(&dir, 0), (vec![0, 1, 2], None));
+        assert_eq!(
+            walk_chain(&dir, 3),
+            (vec![3, 4], Some(Finding::ChainCycle { start: 3, block: 3 }))
+        );
+        assert_eq!(
+            validate_chains(&dir),
+            vec![
+                Finding::ChainCycle { start: 3, block: 3 },
+                Finding::ChainOutOfRange {
+                    start: 5,
+                    block: 5,
+                    next: 20
+                },
+                Finding::ChainOrphan { block: 7 },
+            ]
+        );
+    }
+
+    #[test]
+    fn chain_walker() {
+        let mut dir = vec![dir_frame(0xa0, CHAIN_END); 15];
+
+        // 0 -> 1 -> 2 (deleted), 3 -> 4 (free block in a live chain), 6 -> 7 (ends on a middle)
+        dir[0] = dir_frame(0xa1, 1);
+        dir[1] = dir_frame(0xa2, 2);
+        dir[2] = dir_frame(0xa3, CHAIN_END);
+        dir[3] = dir_frame(0x51, 4);
+        dir[6] = dir_frame(0x51, 7);
+        dir[7] = dir_frame(0x52, CHAIN_END);
+
+        let mut w = ChainWalker::new(&dir, 0).unwrap();
+        assert_eq!(w.by_ref().collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!((w.next(), w.finding()), (None, None));
+
+        let mut w = ChainWalker::new(&dir, 3).unwrap();
+        assert_eq!(w.next(), Some(3));
+        assert_eq!(w.finding(), None);
+        assert_eq!(w.next(), Some(4));
+        assert_eq!(
+            w.finding(),
+            Some(Finding::ChainBadLink {
+                start: 3,
+                block: 4,
+                state: BAState::Free
+            })
+        );
+        assert_eq!(w.next(), None);
+
+        let mut w = ChainWalker::new(&dir, 6).unwrap();
+        assert_eq!(w.by_ref().count(), 2);
+        assert_eq!(
+            w.finding(),
+            Some(Finding::ChainBadEnd { start: 6, block: 7 })
+        );
+
+        assert!(ChainWalker::new(&dir, 15).is_none());
+    }
+
+    #[test]
+    fn severities() {
+        let checksum = |frame| Finding::BadChecksum { block: 0, frame };
+        let cases = [
+            (checksum(0), Severity::Warning, "checksum.header"),
+            (checksum(15), Severity::Warning, "checksum.directory"),
+            (checksum(35), Severity::Warning, "checksum.broken_frame"),
+            (checksum(36), Severity::Info, "checksum.unused"),
+            (checksum(62), Severity::Info, "checksum.unused"),
+            (checksum(63), Severity::Warning, "checksum.write_test"),
+            (
+                Finding::ChainOrphan { block: 7 },
+                Severity::Warning,
+                "chain.orphan",
+            ),
+            (
+                Finding::ChainCycle { start: 3, block: 3 },
+                Severity::Error,
+                "chain.cycle",
+            ),
+            (
+                Finding::BadTitleMagic { block: 1 },
+                Severity::Error,
+                "title.bad_magic",
+            ),
+        ];
+        for (f, severity, code) in cases {
+            assert_eq!((f.severity(), f.code()), (severity, code), "{:?}", f);
+        }
+
+        let mut report = ValidationReport::default();
+        assert_eq!(report.max_severity(), None);
+        report.findings = vec![checksum(40), checksum(3), checksum(50)];
+        assert_eq!(report.max_severity(), Some(Severity::Warning));
+        assert_eq!(
+            report.at_least(Severity::Warning).collect::<Vec<_>>(),
+            [&checksum(3)]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Borrowed, zero-copy views over raw memory card images.
+
+use deku::prelude::*;
+
+use crate::{
+    BAState, MCError, MemCard, TitleFrame, BLOCKS_PER_CARD, BLOCK_SIZE, FRAMES_PER_BLOCK,
+    FRAME_SIZE, HEADER_MAGIC, TITLE_MAGIC,
+};
+
+/// MemCardRef
+///
+/// A read-only view of a raw memory card image that decodes fields on demand, straight from the
+/// borrowed buffer. Nothing is copied when the view is created, which makes it suited to
+/// scanning large archives of card images where only the directory of each card is needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemCardRef<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> MemCardRef<'a> {
+    /// Create a view over the first 128KB of `data`. Only the length and header magic are
+    /// checked, use `MemCard::from_reader` for a fully validated card.
+    pub fn new(data: &'a [u8]) -> Result<Self, MCError> {
+        let size = BLOCK_SIZE * BLOCKS_PER_CARD;
+        if data.len() < size {
+            return Err(MCError::InvalidValue(format!(
+                "{} bytes is too short for a memory card",
+                data.len()
+            )));
+        }
+
+        if data[..2] != HEADER_MAGIC {
+            return Err(MCError::NotAMemoryCard);
+        }
+
+        Ok(MemCardRef {
+            data: &data[..size],
+        })
+    }
+
+    /// The raw bytes of card block `n` (0..16).
+    pub fn block(&self, n: usize) -> Option<&'a [u8]> {
+        self.data.chunks_exact(BLOCK_SIZE).nth(n)
+    }
+
+    /// The raw bytes of frame `frame` (0..64) of card block `block` (0..16).
+    pub fn frame(&self, block: usize, frame: usize) -> Option<&'a [u8; FRAME_SIZE]> {
+        if frame >= FRAMES_PER_BLOCK {
+            return None;
+        }
+
+        let start = frame * FRAME_SIZE;
+        self.block(block)?[start..start + FRAME_SIZE]
+            .try_into()
+            .ok()
+    }
+
+    /// The directory entry at directory index `n` (0..15).
+    pub fn dir_entry(&self, n: usize) -> Option<DirEntryRef<'a>> {
+        if n >= BLOCKS_PER_CARD - 1 {
+            return None;
+        }
+
+        Some(DirEntryRef {
+            data: self.frame(0, n + 1)?,
+        })
+    }
+
+    /// Iterate over the 15 directory entries.
+    pub fn dir_entries(&self) -> impl Iterator<Item = DirEntryRef<'a>> + '_ {
+        (0..BLOCKS_PER_CARD - 1).filter_map(|n| self.dir_entry(n))
+    }
+
+    /// Decode the title of the save whose first block is at directory index `n`. Returns
+    /// `MCError::NotASave` if the block does not start with a title frame.
+    pub fn title(&self, n: usize) -> Result<String, MCError> {
+        let f = self
+            .frame(n + 1, 0)
+            .ok_or_else(|| MCError::InvalidValue(format!("block {} is out of range", n)))?;
+        if f[..2] != TITLE_MAGIC {
+            return Err(MCError::NotASave);
+        }
+
+        let (_, t) = TitleFrame::from_bytes((f, 0))?;
+        t.decode_title()
+    }
+
+    /// Parse the whole image into an owned, validated `MemCard`.
+    pub fn to_memcard(&self) -> Result<MemCard, MCError> {
+        MemCard::from_reader(self.data)
+    }
+}
+
+/// DirEntryRef
+///
+/// A read-only view of a directory frame inside a `MemCardRef`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirEntryRef<'a> {
+    data: &'a [u8; FRAME_SIZE],
+}
+
+impl<'a> DirEntryRef<'a> {
+    /// The raw allocation state.
+    pub fn state(&self) -> u32 {
+        u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]])
+    }
+
+    /// The allocation state.
+    pub fn alloc_state(&self) -> BAState {
+        BAState::from_raw(self.state())
+    }
+
+    /// The size of the save in bytes.
+    pub fn filesize(&self) -> u32 {
+        u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]])
+    }
+
+    /// The directory index of the next block in the chain, or `CHAIN_END`.
+    pub fn next_block(&self) -> u16 {
+        u16::from_le_bytes([self.data[8], self.data[9]])
+    }
+
+    /// The filename, without the trailing NUL padding.
+    pub fn filename(&self) -> &'a [u8] {
+        let name = &self.data[10..31];
+        let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        &name[..len]
+    }
+
+    /// The raw bytes of the directory frame.
+    pub fn as_bytes(&self) -> &'a [u8; FRAME_SIZE] {
+        self.data
+    }
+}
@@ -0,0 +1,109 @@
+//! `wasm-bindgen` wrappers for viewing memory cards from JavaScript, such as in a browser based
+//! card viewer. Cards are always opened from and written to byte arrays, as there is no file
+//! system to use.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{MCError, MemCard, OpenOptions, Save};
+
+/// WasmCard
+///
+/// A memory card opened from the bytes of a raw image, exported to JavaScript as `Card`.
+#[wasm_bindgen(js_name = Card)]
+pub struct WasmCard {
+    card: MemCard,
+}
+
+/// WasmSave
+///
+/// The details of a save on a `WasmCard`, exported to JavaScript as `Save`.
+#[wasm_bindgen(js_name = Save, getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmSave {
+    /// The 1-based slot of the first block of the save.
+    pub slot: usize,
+
+    /// The decoded title, or an empty string if the save has no title frame.
+    pub title: String,
+
+    /// The product code of the game, such as "SLUS-00123".
+    pub serial: String,
+
+    /// The size of the save in bytes, as recorded in the directory.
+    pub size: u32,
+
+    /// The number of blocks in the save.
+    pub blocks: usize,
+
+    /// The number of icon frames.
+    pub icon_frames: usize,
+}
+
+impl From<&Save<'_>> for WasmSave {
+    fn from(s: &Save) -> Self {
+        WasmSave {
+            slot: s.index + 1,
+            title: s.title().unwrap_or_default(),
+            serial: s.serial(),
+            size: s.size(),
+            blocks: s.blocks.len(),
+            icon_frames: s.icon_frames().len(),
+        }
+    }
+}
+
+#[wasm_bindgen(js_class = Card)]
+impl WasmCard {
+    /// Open a card from the bytes of a raw image. Checksum errors are ignored if
+    /// `skip_checksums` is set.
+    #[wasm_bindgen(constructor)]
+    pub fn from_bytes(data: &[u8], skip_checksums: bool) -> Result<WasmCard, JsError> {
+        let opts = OpenOptions {
+            skip_checksums,
+            ..Default::default()
+        };
+        let card = MemCard::from_reader_with(data, opts)?;
+
+        Ok(WasmCard { card })
+    }
+
+    /// List the live saves on the card.
+    pub fn saves(&self) -> Vec<WasmSave> {
+        self.card.saves().map(|s| WasmSave::from(&s)).collect()
+    }
+
+    /// Encode icon frame `frame` of the save in `slot` as `.png` bytes.
+    pub fn icon_png(&self, slot: usize, frame: usize) -> Result<Vec<u8>, JsError> {
+        Ok(self.save(slot)?.data[0].icon_png(frame)?)
+    }
+
+    /// Encode the icon of the save in `slot` as animated `.gif` bytes.
+    pub fn icon_gif(&self, slot: usize) -> Result<Vec<u8>, JsError> {
+        let mut out = Vec::<u8>::new();
+        self.save(slot)?.data[0].write_icon_gif(&mut out)?;
+
+        Ok(out)
+    }
+
+    /// Export the save in `slot` as the bytes of a `.mcs` file.
+    pub fn export_mcs(&self, slot: usize) -> Result<Vec<u8>, JsError> {
+        Ok(self.save(slot)?.to_mcs()?)
+    }
+
+    /// Write the card out as the bytes of a raw image.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        let mut out = Vec::<u8>::new();
+        self.card.write_into(&mut out)?;
+
+        Ok(out)
+    }
+}
+
+impl WasmCard {
+    fn save(&self, slot: usize) -> Result<Save<'_>, MCError> {
+        self.card
+            .saves()
+            .find(|s| s.index + 1 == slot)
+            .ok_or_else(|| MCError::NotFound(format!("no save in slot {}", slot)))
+    }
+}
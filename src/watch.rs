@@ -0,0 +1,172 @@
+//! Memory cards that are reloaded when their image file changes on disk.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::event::{AccessKind, AccessMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{MCError, MemCard, Save};
+
+/// How long to wait for more changes after the first one, so a file that is written in several
+/// parts is only reloaded once.
+const SETTLE_TIME: Duration = Duration::from_millis(50);
+
+/// CardEvent
+///
+/// A change to the saves on a `WatchedCard`, found by comparing the card before and after a
+/// reload. Saves are identified by the directory index of their first block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardEvent {
+    /// A save was added.
+    Added(usize),
+
+    /// A save was deleted.
+    Removed(usize),
+
+    /// The directory entry or the data of a save changed.
+    Changed(usize),
+}
+
+/// WatchedCard
+///
+/// A `MemCard` that is parsed again whenever its image file is rewritten, such as by an emulator
+/// saving a game. Changes are picked up by calling `poll` or `wait`, which report what changed
+/// as `CardEvent`s. Read access to the card goes through `Deref`.
+///
+/// The directory holding the file is watched rather than the file itself, so images that are
+/// replaced by renaming a new file over them are also picked up.
+#[derive(Debug)]
+pub struct WatchedCard {
+    path: PathBuf,
+    card: MemCard,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl WatchedCard {
+    /// Open the memory card file at `filename` and start watching it for changes.
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, MCError> {
+        let path = filename.as_ref().canonicalize()?;
+        let card = MemCard::open(&path)?;
+
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        let dir = path.parent().unwrap_or(Path::new("/"));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(WatchedCard {
+            path,
+            card,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The path of the watched image file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reload the card if the file changed since the last call, without blocking. Returns the
+    /// changes to the saves, which is empty if nothing changed.
+    pub fn poll(&mut self) -> Result<Vec<CardEvent>, MCError> {
+        let mut changed = false;
+        while let Ok(e) = self.events.try_recv() {
+            changed |= self.is_change(&e?);
+        }
+
+        match changed {
+            true => self.reload(),
+            false => Ok(Vec::new()),
+        }
+    }
+
+    /// Wait up to `timeout` for the file to change, then reload the card. Returns the changes
+    /// to the saves, which is empty if the file did not change in time.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<CardEvent>, MCError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(left) {
+                Ok(e) => {
+                    if self.is_change(&e?) {
+                        break;
+                    }
+                }
+                Err(_) => return Ok(Vec::new()),
+            }
+        }
+
+        // Let the writer finish, and skip the events it caused in the meantime
+        thread::sleep(SETTLE_TIME);
+        while self.events.try_recv().is_ok() {}
+
+        self.reload()
+    }
+
+    /// Parse the file again, and compare the saves against the previous contents. If the file
+    /// can not be parsed, such as while it is still being written, the error is returned and
+    /// the previous contents are kept.
+    pub fn reload(&mut self) -> Result<Vec<CardEvent>, MCError> {
+        let card = MemCard::open(&self.path)?;
+        let events = diff_saves(&self.card, &card);
+        trace_event!(debug, path = %self.path.display(), changes = events.len(), "reloaded card");
+        self.card = card;
+
+        Ok(events)
+    }
+
+    /// Stop watching, and return the card as it was last loaded.
+    pub fn into_inner(self) -> MemCard {
+        self.card
+    }
+
+    /// Whether `e` is a write to the watched file.
+    fn is_change(&self, e: &Event) -> bool {
+        let kind = matches!(
+            e.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(_)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+
+        kind && e.paths.contains(&self.path)
+    }
+}
+
+impl Deref for WatchedCard {
+    type Target = MemCard;
+
+    fn deref(&self) -> &MemCard {
+        &self.card
+    }
+}
+
+/// Compare the live saves of `old` and `new`.
+fn diff_saves(old: &MemCard, new: &MemCard) -> Vec<CardEvent> {
+    let key = |s: &Save| (s.index, s.dir_frame.filename);
+    let old: Vec<Save> = old.saves().collect();
+    let new: Vec<Save> = new.saves().collect();
+
+    let mut events = Vec::new();
+    for o in &old {
+        match new.iter().find(|n| key(n) == key(o)) {
+            Some(n) if n.dir_frame != o.dir_frame || n.blocks != o.blocks || n.data != o.data => {
+                events.push(CardEvent::Changed(o.index))
+            }
+            Some(_) => (),
+            None => events.push(CardEvent::Removed(o.index)),
+        }
+    }
+    for n in &new {
+        if !old.iter().any(|o| key(o) == key(n)) {
+            events.push(CardEvent::Added(n.index));
+        }
+    }
+
+    events
+}
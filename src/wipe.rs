@@ -0,0 +1,42 @@
+//! Overwriting the contents of a card, for sanitizing cards and images before they are given
+//! away.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// WipePattern
+///
+/// What `MemCard::wipe` overwrites the data blocks with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WipePattern {
+    /// 0x00 fill, as on a card that was formatted by this library.
+    #[default]
+    Zeros,
+
+    /// 0xFF fill, as on a factory fresh card.
+    Ones,
+
+    /// Random bytes, so that no trace of the old contents, or of the wipe, is left.
+    Random,
+}
+
+impl WipePattern {
+    /// Fill `buf` with the pattern.
+    pub(crate) fn fill(self, buf: &mut [u8]) {
+        match self {
+            WipePattern::Zeros => buf.fill(0),
+            WipePattern::Ones => buf.fill(0xff),
+            WipePattern::Random => {
+                // A xorshift generator, seeded from the random keys of the standard library. This
+                // is not cryptographically secure, but the old contents are gone either way.
+                let mut x = RandomState::new().build_hasher().finish() | 1;
+                for c in buf.chunks_mut(8) {
+                    x ^= x << 13;
+                    x ^= x >> 7;
+                    x ^= x << 17;
+                    c.copy_from_slice(&x.to_le_bytes()[..c.len()]);
+                }
+            }
+        }
+    }
+}